@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context as _};
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use serde::Deserialize;
+
+use crate::Opts;
+
+/// Profile of configuration file, values given on command line or in environment variables take precedence
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Profile {
+    /// Cloudflare token, could refer environment variable e.g. "${CLOUDFLARE_TOKEN}"
+    pub(crate) token: Option<String>,
+    /// Zones mapped to their records
+    #[serde(default)]
+    pub(crate) zones: BTreeMap<String, Vec<String>>,
+    pub(crate) cron: Option<String>,
+    pub(crate) jitter: Option<u64>,
+    pub(crate) ttl: Option<u32>,
+    pub(crate) proxied: Option<bool>,
+    pub(crate) cache_ttl: Option<u64>,
+    pub(crate) detect_drift: Option<bool>,
+    pub(crate) max_attempts: Option<u32>,
+    pub(crate) state_file: Option<String>,
+}
+
+/// Configuration file of profiles keyed by name e.g. [home]
+#[derive(Debug, Deserialize)]
+#[serde(transparent)]
+pub(crate) struct Config {
+    profiles: BTreeMap<String, Profile>,
+}
+
+impl Config {
+    pub(crate) fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {path:?}"))?;
+        Self::parse(&content).with_context(|| format!("invalid config file {path:?}"))
+    }
+
+    pub(crate) fn parse(content: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    /// Takes profile out of configuration, environment variables in it are interpolated
+    pub(crate) fn into_profile(mut self, name: &str) -> anyhow::Result<Profile> {
+        let mut profile = match self.profiles.remove(name) {
+            Some(p) => p,
+            None => {
+                let names = self.profiles.keys().cloned().collect::<Vec<String>>();
+                bail!("profile {name} not found, available: {}", names.join(", "));
+            }
+        };
+        let lookup = |key: &str| std::env::var(key).ok();
+        if let Some(ref token) = profile.token {
+            profile.token = Some(interpolate(token, lookup)?);
+        }
+        if let Some(ref state_file) = profile.state_file {
+            profile.state_file = Some(interpolate(state_file, lookup)?);
+        }
+        Ok(profile)
+    }
+}
+
+impl Profile {
+    /// Fills options not given on command line or in environment variables
+    pub(crate) fn apply(self, opts: &mut Opts, matches: &ArgMatches) {
+        let given = |id: &str| {
+            !matches!(
+                matches.value_source(id),
+                None | Some(ValueSource::DefaultValue)
+            )
+        };
+        if let (false, Some(token)) = (given("token"), self.token) {
+            opts.token = Some(token);
+        }
+        if !given("zones") && !self.zones.is_empty() {
+            opts.zones = self
+                .zones
+                .iter()
+                .map(|(zone, records)| format!("{zone}={}", records.join(",")))
+                .collect();
+        }
+        if let (false, Some(cron)) = (given("cron"), self.cron) {
+            opts.cron = cron;
+        }
+        if let (false, Some(jitter)) = (given("jitter"), self.jitter) {
+            opts.jitter = jitter;
+        }
+        if let (false, Some(ttl)) = (given("ttl"), self.ttl) {
+            opts.ttl = ttl;
+        }
+        if let (false, Some(proxied)) = (given("proxied"), self.proxied) {
+            opts.proxied = Some(proxied);
+        }
+        if let (false, Some(cache_ttl)) = (given("cache_ttl"), self.cache_ttl) {
+            opts.cache_ttl = Some(cache_ttl);
+        }
+        if let (false, Some(detect_drift)) = (given("detect_drift"), self.detect_drift) {
+            opts.detect_drift = detect_drift;
+        }
+        if let (false, Some(max_attempts)) = (given("max_attempts"), self.max_attempts) {
+            opts.max_attempts = max_attempts;
+        }
+        if let (false, Some(state_file)) = (given("state_file"), self.state_file) {
+            opts.state_file = Some(PathBuf::from(state_file));
+        }
+    }
+}
+
+/// Replaces ${NAME} with value of environment variable, unset variable is an error
+pub(crate) fn interpolate<F>(value: &str, lookup: F) -> anyhow::Result<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut interpolated = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        interpolated.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("unterminated variable in {value}"))?;
+        let name = &rest[start + 2..start + end];
+        let resolved =
+            lookup(name).ok_or_else(|| anyhow!("environment variable {name} not set"))?;
+        interpolated.push_str(&resolved);
+        rest = &rest[start + end + 1..];
+    }
+    interpolated.push_str(rest);
+    Ok(interpolated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use clap::{CommandFactory as _, FromArgMatches as _};
+
+    const CONFIG: &str = r#"
+[home]
+token = "${HOME_TOKEN}"
+cron = "0 */10 * * * * *"
+ttl = 300
+proxied = true
+cache_ttl = 3600
+detect_drift = true
+
+[home.zones]
+"x.com" = ["a.x.com", "b.x.com"]
+"y.com" = ["c.y.com"]
+
+[office]
+token = "token"
+zones = { "z.com" = ["d.z.com"] }
+"#;
+
+    #[test]
+    fn t_interpolate() {
+        let lookup = |key: &str| (key == "TOKEN").then(|| "secret".to_string());
+        assert_eq!("secret", interpolate("${TOKEN}", lookup).unwrap());
+        assert_eq!("a-secret-b", interpolate("a-${TOKEN}-b", lookup).unwrap());
+        assert_eq!("plain", interpolate("plain", lookup).unwrap());
+        assert!(interpolate("${MISSING}", lookup).is_err());
+        assert!(interpolate("${TOKEN", lookup).is_err());
+    }
+
+    #[test]
+    fn t_config() {
+        let config = Config::parse(CONFIG).unwrap();
+        let err = config.into_profile("unknown").unwrap_err();
+        assert_eq!(
+            "profile unknown not found, available: home, office",
+            err.to_string()
+        );
+        assert!(Config::parse("[home]\nunknown = 1").is_err());
+
+        let parse = |args: &[&str]| {
+            let matches = Opts::command().try_get_matches_from(args).unwrap();
+            let opts = Opts::from_arg_matches(&matches).unwrap();
+            (opts, matches)
+        };
+
+        let profile = Config::parse(CONFIG)
+            .unwrap()
+            .into_profile("office")
+            .unwrap();
+        let (mut opts, matches) = parse(&["--", "--config", "cdu.toml", "--cron", "* * * * * *"]);
+        profile.apply(&mut opts, &matches);
+        assert_eq!(Some("token".to_string()), opts.token);
+        assert_eq!(vec!["z.com=d.z.com"], opts.zones);
+        assert_eq!("* * * * * *", opts.cron);
+        assert_eq!(1, opts.ttl);
+
+        let mut profile = Config::parse(CONFIG)
+            .unwrap()
+            .profiles
+            .remove("home")
+            .unwrap();
+        profile.token = None;
+        let (mut opts, matches) = parse(&["--", "-t", "token", "--ttl", "60"]);
+        profile.apply(&mut opts, &matches);
+        assert_eq!(Some("token".to_string()), opts.token);
+        assert_eq!(vec!["x.com=a.x.com,b.x.com", "y.com=c.y.com"], opts.zones);
+        assert_eq!("0 */10 * * * * *", opts.cron);
+        assert_eq!(60, opts.ttl);
+        assert_eq!(Some(true), opts.proxied);
+        assert_eq!(Some(3600), opts.cache_ttl);
+        assert!(opts.detect_drift);
+    }
+}
@@ -0,0 +1,97 @@
+use thiserror::Error;
+
+/// Error of [`crate::Cdu`], so callers could branch on kind of failure
+#[derive(Debug, Error)]
+pub enum CduError {
+    /// Zone does not exist or is not accessible by token
+    #[error("zone not found: {0}")]
+    ZoneNotFound(String),
+    /// DNS record does not exist in zone
+    #[error("DNS record not found: {0}")]
+    RecordNotFound(String),
+    /// Cloudflare API responded with error status, or token is inactive or lacks permissions
+    #[error("{message}")]
+    Api {
+        /// HTTP status code, None when request succeeded but token is unusable
+        status: Option<u16>,
+        /// Errors told by Cloudflare API, or missing permissions of token
+        message: String,
+    },
+    /// Cannot fetch public IPv4 address
+    #[error("cannot fetch public IPv4 address")]
+    NoPublicIp,
+    /// Network failure, or response could not be read
+    #[error("HTTP error: {0}")]
+    Http(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// State file could not be read or written
+    #[error("state file error: {0}")]
+    State(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl CduError {
+    /// Whether error is transient and the run is worth retrying,
+    /// e.g. public IP address unavailable, network failure, rate limit or server error
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            CduError::NoPublicIp | CduError::Http(_) => true,
+            CduError::Api {
+                status: Some(code), ..
+            } => *code == 429 || *code >= 500,
+            _ => false,
+        }
+    }
+}
+
+impl From<ureq::Error> for CduError {
+    fn from(error: ureq::Error) -> Self {
+        match error {
+            ureq::Error::Status(code, response) => {
+                // Cloudflare responds errors in body e.g. {"errors":[{"code":1003,"message":"..."}]}
+                let messages = response
+                    .into_json::<serde_json::Value>()
+                    .ok()
+                    .and_then(|body| {
+                        let errors = body.get("errors")?.as_array()?.iter();
+                        let messages = errors
+                            .filter_map(|e| e.get("message")?.as_str())
+                            .collect::<Vec<&str>>();
+                        (!messages.is_empty()).then(|| messages.join(", "))
+                    });
+                let message = match messages {
+                    Some(m) => format!("Cloudflare API responded {code}: {m}"),
+                    None => format!("Cloudflare API responded {code}"),
+                };
+                CduError::Api {
+                    status: Some(code),
+                    message,
+                }
+            }
+            ureq::Error::Transport(transport) => CduError::Http(Box::new(transport)),
+        }
+    }
+}
+
+impl From<std::io::Error> for CduError {
+    fn from(error: std::io::Error) -> Self {
+        CduError::Http(Box::new(error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_is_retryable() {
+        assert!(CduError::NoPublicIp.is_retryable());
+        assert!(!CduError::ZoneNotFound("zone".into()).is_retryable());
+        let api = |status| CduError::Api {
+            status,
+            message: String::new(),
+        };
+        assert!(api(Some(429)).is_retryable());
+        assert!(api(Some(503)).is_retryable());
+        assert!(!api(Some(403)).is_retryable());
+        assert!(!api(None).is_retryable());
+    }
+}
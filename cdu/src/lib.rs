@@ -15,21 +15,41 @@
 use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
 use std::net::Ipv4Addr;
-use std::sync::Arc;
-use std::time::Duration;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use anyhow::bail;
+use chrono::Utc;
 use cloudflare::endpoints::dns::{DnsContent, DnsRecord};
+use cloudflare::endpoints::user::UserTokenStatus;
 use cloudflare::endpoints::zone::Zone;
 use cloudflare::framework::response::ApiSuccess;
+use exponential_backoff::Backoff;
 use futures::stream::FuturesUnordered;
-use log::{debug, Level};
+use log::{debug, info, warn, Level};
 use logging_timer::{finish, stimer};
 use moka::sync::Cache;
 use ureq::{Agent, AgentBuilder};
 
+pub use error::CduError;
+pub use report::{Outcome, RecordReport, Report};
+
+use crate::rate_limit::RateLimit;
+use crate::state::{RecordState, State};
+
+mod error;
+mod rate_limit;
+mod report;
+mod state;
+
 const HTTP_TIMEOUT: u64 = 30;
 
+/// Default attempts of [`Cdu::run_with_retry`]
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 10;
+
+const MIN_RETRY_DELAY: Duration = Duration::from_millis(100);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+
 #[cfg(not(test))]
 fn server_url() -> String {
     "https://api.cloudflare.com".to_string()
@@ -40,18 +60,6 @@ fn server_url() -> String {
     mockito::server_url()
 }
 
-/// Cannot fetch public IPv4 address
-#[derive(Clone, Copy, Debug)]
-pub struct NoIPV4;
-
-impl Display for NoIPV4 {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "cannot fetch public IPv4 address")
-    }
-}
-
-impl std::error::Error for NoIPV4 {}
-
 #[derive(Eq, PartialEq, Hash)]
 enum CacheKey {
     LastIP,
@@ -70,12 +78,18 @@ impl Display for Cached {
     }
 }
 
-async fn get_record_identifier<'a, T>(
+/// Where API token is created and edited
+const API_TOKENS_URL: &str = "https://dash.cloudflare.com/profile/api-tokens";
+
+/// DNS records fetched in one page of listing
+const DNS_RECORDS_PER_PAGE: u32 = 100;
+
+/// Lists all A records of zone, following pagination
+async fn list_dns_records<'a, T>(
     agent: Arc<Agent>,
     token: T,
     zone_id: T,
-    record_name: T,
-) -> anyhow::Result<(String, String)>
+) -> Result<Vec<DnsRecord>, CduError>
 where
     T: Into<Cow<'a, str>>,
 {
@@ -83,22 +97,52 @@ where
     let authorization = format!("bearer {}", token);
 
     let zone_id = zone_id.into();
-    let record_name = record_name.into();
 
     let url = format!("{}/client/v4/zones/{zone_id}/dns_records", server_url());
-    let req = agent
-        .get(&url)
-        .query("name", &record_name)
-        .set("content-type", "application/json")
-        .set("authorization", &authorization);
-    let tmr = stimer!(Level::Debug; "FETCH_DNS_RECORD", "zone_id={zone_id}");
-    let res: ApiSuccess<Vec<DnsRecord>> = req.call()?.into_json()?;
-    let identifier = match res.result.first() {
-        Some(record) => record.id.clone(),
-        None => bail!("DNS record not found: {record_name}"),
-    };
-    finish!(tmr, "id={identifier}");
-    Ok((identifier, record_name.into()))
+    let per_page = DNS_RECORDS_PER_PAGE.to_string();
+    let tmr = stimer!(Level::Debug; "FETCH_DNS_RECORDS", "zone_id={zone_id}");
+    let mut records = vec![];
+    let mut page = 1;
+    loop {
+        let req = agent
+            .get(&url)
+            .query("type", "A")
+            .query("page", &page.to_string())
+            .query("per_page", &per_page)
+            .set("content-type", "application/json")
+            .set("authorization", &authorization);
+        let res: ApiSuccess<Vec<DnsRecord>> = req.call()?.into_json()?;
+        let total_pages = res
+            .result_info
+            .as_ref()
+            .and_then(|info| info.get("total_pages"))
+            .and_then(|total_pages| total_pages.as_u64())
+            .unwrap_or(1);
+        let is_empty = res.result.is_empty();
+        records.extend(res.result);
+        if is_empty || page >= total_pages {
+            break;
+        }
+        page += 1;
+    }
+    finish!(tmr, "pages={page},records={}", records.len());
+    Ok(records)
+}
+
+/// TTL and proxy status applied to updated DNS records
+#[derive(Clone, Copy, Debug)]
+struct RecordSettings {
+    ttl: u32,
+    proxied: Option<bool>,
+}
+
+impl Default for RecordSettings {
+    fn default() -> Self {
+        Self {
+            ttl: 1, // 1 for automatic
+            proxied: None,
+        }
+    }
 }
 
 async fn update_dns_record<'a, T>(
@@ -108,7 +152,8 @@ async fn update_dns_record<'a, T>(
     dns_record_id: T,
     dns_record_name: T,
     current_ip: Ipv4Addr,
-) -> anyhow::Result<()>
+    settings: RecordSettings,
+) -> Result<(), CduError>
 where
     T: Into<Cow<'a, str>>,
 {
@@ -125,14 +170,16 @@ where
     );
     let req = agent.put(&url).set("authorization", &authorization);
     let tmr = stimer!(Level::Debug; "UPDATE_DNS_RECORD", "zone_id={zone_id},dns_record_id={dns_record_id}");
-    let res: ApiSuccess<DnsRecord> = req
-        .send_json(ureq::json!({
-            "type": "A",
-            "name":dns_record_name,
-            "content": current_ip,
-            "ttl": 1 // 1 for automatic
-        }))?
-        .into_json()?;
+    let mut body = ureq::json!({
+        "type": "A",
+        "name":dns_record_name,
+        "content": current_ip,
+        "ttl": settings.ttl
+    });
+    if let Some(proxied) = settings.proxied {
+        body["proxied"] = proxied.into();
+    }
+    let res: ApiSuccess<DnsRecord> = req.send_json(body)?.into_json()?;
     let content = match res.result.content {
         DnsContent::A { content } => content.to_string(),
         _ => "(not an A record)".into(),
@@ -141,20 +188,38 @@ where
     Ok(())
 }
 
+fn failed_report(zone: &str, name: &str, error: &CduError, elapsed: Duration) -> RecordReport {
+    RecordReport {
+        zone: zone.to_string(),
+        name: name.to_string(),
+        outcome: Outcome::Failed,
+        elapsed,
+        error: Some(error.to_string()),
+    }
+}
+
 /// Cloudflare DNS Update
 pub struct Cdu<'a> {
     token: Cow<'a, str>,
-    zone: Cow<'a, str>,
-    record_names: Vec<String>,
+    zones: Vec<(Cow<'a, str>, Vec<String>)>,
     cache: Cache<CacheKey, Cached>,
+    state_file: Option<PathBuf>,
+    state: Mutex<State>,
+    max_attempts: u32,
+    rate_limit: RateLimit,
+    settings: RecordSettings,
+    detect_drift: bool,
 }
 
 impl<'a> std::fmt::Debug for Cdu<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Cdu")
             .field("token", &self.token)
-            .field("zone", &self.zone)
-            .field("record_names", &self.record_names)
+            .field("zones", &self.zones)
+            .field("state_file", &self.state_file)
+            .field("max_attempts", &self.max_attempts)
+            .field("settings", &self.settings)
+            .field("detect_drift", &self.detect_drift)
             .finish()
     }
 }
@@ -168,98 +233,484 @@ impl<'a> Cdu<'a> {
     {
         Self {
             token: token.into(),
-            zone: zone.into(),
-            record_names: record_names
-                .iter()
-                .map(|s| s.to_string())
-                .collect::<Vec<String>>(),
+            zones: vec![],
             cache: Cache::new(1), // cache IP address
+            state_file: None,
+            state: Mutex::new(State::default()),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            rate_limit: RateLimit::default(),
+            settings: RecordSettings::default(),
+            detect_drift: false,
         }
+        .with_zone(zone, record_names)
+    }
+
+    /// Sets TTL of DNS records in seconds, 1 for automatic
+    pub fn with_ttl(mut self, ttl: u32) -> Self {
+        self.settings.ttl = ttl;
+        self
+    }
+
+    /// Sets whether DNS records are proxied by Cloudflare, left as is when not set
+    pub fn with_proxied(mut self, proxied: bool) -> Self {
+        self.settings.proxied = Some(proxied);
+        self
+    }
+
+    /// Forgets last IP address after given time, so DNS records are checked again
+    /// even if IP address remains unchanged e.g. after they are modified elsewhere
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache = Cache::builder().max_capacity(1).time_to_live(ttl).build();
+        self
+    }
+
+    /// Fetches DNS records every run even if IP address remains unchanged,
+    /// so those modified elsewhere e.g. on Cloudflare dashboard are corrected
+    pub fn with_drift_detection(mut self, detect_drift: bool) -> Self {
+        self.detect_drift = detect_drift;
+        self
+    }
+
+    /// Sets attempts of [`Cdu::run_with_retry`], including the first one
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Loads and persists last IP address and DNS record states to a file,
+    /// so DNS records are not updated again after restart if IP address remains unchanged
+    pub fn with_state_file<P>(mut self, path: P) -> Result<Self, CduError>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        let state = State::load(&path)?;
+        debug!(
+            "load state from {path:?}, last IP address {:?}",
+            state.last_ip
+        );
+        self.state = Mutex::new(state);
+        self.state_file = Some(path);
+        Ok(self)
+    }
+
+    /// IP address DNS records were updated to last time, if known
+    fn last_ip(&self) -> Option<Ipv4Addr> {
+        match self.cache.get(&CacheKey::LastIP) {
+            Some(Cached::IP(ip)) => Some(ip),
+            None => self.state.lock().unwrap().last_ip,
+        }
+    }
+
+    fn save_state(&self, current_ip: Ipv4Addr) -> Result<(), CduError> {
+        let mut state = self.state.lock().unwrap();
+        let now = Utc::now();
+        state.last_ip = Some(current_ip);
+        for (zone, record_names) in &self.zones {
+            for name in record_names {
+                let record = RecordState {
+                    zone: zone.to_string(),
+                    content: current_ip,
+                    updated_at: now,
+                };
+                state.records.insert(name.clone(), record);
+            }
+        }
+        if let Some(ref path) = self.state_file {
+            state.save(path)?;
+            debug!("state saved to {path:?}");
+        }
+        Ok(())
+    }
+
+    /// Adds another zone with its DNS records to update
+    ///
+    /// ```
+    /// # use cdu::Cdu;
+    /// let cdu = Cdu::new("token", "x.com", &["a.x.com"]).with_zone("y.com", &["b.y.com"]);
+    /// ```
+    pub fn with_zone<T, U>(mut self, zone: T, record_names: &[U]) -> Self
+    where
+        T: Into<Cow<'a, str>>,
+        U: Display,
+    {
+        let record_names = record_names
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        self.zones.push((zone.into(), record_names));
+        self
     }
 
     fn build_agent(&self) -> Agent {
         AgentBuilder::new()
             .timeout(Duration::from_secs(HTTP_TIMEOUT))
+            .middleware(self.rate_limit.clone())
             .build()
     }
 
-    async fn get_zone_identifier(&self, agent: Arc<Agent>) -> anyhow::Result<String> {
-        let zone = &self.zone;
+    /// Waits when Cloudflare asks to retry later or quota of API is nearly used up
+    async fn wait_for_rate_limit(&self) {
+        if let Some(delay) = self.rate_limit.delay() {
+            warn!("near rate limit of Cloudflare API, wait {delay:?}");
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Lists zones accessible by token, or only the one named if given
+    async fn list_zones(
+        &self,
+        agent: Arc<Agent>,
+        zone: Option<&str>,
+    ) -> Result<Vec<Zone>, CduError> {
         let token = &self.token;
-        let req = agent
+        let mut req = agent
             .get(&format!("{}/client/v4/zones", server_url()))
             .set("accept", "application/json")
-            .set("authorization", &format!("bearer {token}"))
-            .query("name", &self.zone);
-        let tmr = stimer!(Level::Debug; "FETCH_ZONE", "zone={zone}");
+            .set("authorization", &format!("bearer {token}"));
+        if let Some(zone) = zone {
+            req = req.query("name", zone);
+        }
         let res: ApiSuccess<Vec<Zone>> = req.call()?.into_json()?;
-        let id = match res.result.first() {
+        Ok(res.result)
+    }
+
+    async fn get_zone_identifier(&self, agent: Arc<Agent>, zone: &str) -> Result<String, CduError> {
+        let tmr = stimer!(Level::Debug; "FETCH_ZONE", "zone={zone}");
+        let zones = self.list_zones(agent, Some(zone)).await?;
+        let id = match zones.first() {
             Some(zone) => zone.id.to_string(),
-            None => bail!("zone not found: {zone}"),
+            None => return Err(CduError::ZoneNotFound(zone.to_string())),
         };
         finish!(tmr, "zone_id={id}");
         Ok(id)
     }
 
-    /// Perform DNS record update on Cloudflare
-    pub async fn run(&self) -> anyhow::Result<()> {
-        use futures::StreamExt as _;
-
-        let tmr = stimer!(Level::Debug; "FETCH_IP_ADDRESS");
-        let current_ip = public_ip::addr_v4().await.ok_or(NoIPV4)?;
-        finish!(tmr, "current_ip={current_ip:?}");
+    /// Verifies token is active and could read zones and edit their DNS records,
+    /// so misconfigured token fails fast with missing permissions instead of 403 in the middle
+    pub async fn verify(&self) -> Result<(), CduError> {
+        let agent = Arc::new(self.build_agent());
+        let authorization = format!("bearer {}", self.token);
 
-        if let Some(Cached::IP(last_ip)) = self.cache.get(&CacheKey::LastIP) {
-            if current_ip == last_ip {
-                debug!("IPv4 address remains unchanged, skip");
-                return Ok(());
+        let tmr = stimer!(Level::Debug; "VERIFY_TOKEN");
+        let url = format!("{}/client/v4/user/tokens/verify", server_url());
+        let req = agent.get(&url).set("authorization", &authorization);
+        let res: ApiSuccess<UserTokenStatus> = match req.call() {
+            Ok(res) => res.into_json()?,
+            Err(ureq::Error::Status(code @ (400 | 401 | 403), _)) => {
+                return Err(CduError::Api {
+                    status: Some(code),
+                    message: format!("token is invalid, create one with Zone:Read and DNS:Edit permissions at {API_TOKENS_URL}"),
+                });
             }
-            debug!("IPv4 address changed from {last_ip} to {current_ip}");
-        } else {
-            debug!("no previous IPv4 address found, continue");
+            Err(e) => return Err(e.into()),
+        };
+        let status = res.result.status;
+        if status != "active" {
+            return Err(CduError::Api {
+                status: None,
+                message: format!(
+                    "token is {status}, activate it or create another at {API_TOKENS_URL}"
+                ),
+            });
         }
+        finish!(tmr, "status={status}");
 
-        let agent = Arc::new(self.build_agent());
-        let zone_id = self.get_zone_identifier(agent.clone()).await?;
-
-        let mut tasks = FuturesUnordered::new();
-        for record_name in &self.record_names {
-            let agent = agent.clone();
-            let token = self.token.to_string();
-            let zone_id = zone_id.clone();
-            let record_name = record_name.clone();
-            tasks.push(tokio::spawn(async move {
-                get_record_identifier(agent, token, zone_id, record_name).await
-            }))
+        let mut missing = vec![];
+        let mut inaccessible = false;
+        for (zone, _) in &self.zones {
+            let found = self.list_zones(agent.clone(), Some(zone)).await?;
+            let found = match found.first() {
+                Some(z) => z,
+                None => {
+                    missing.push(format!("Zone:Read on {zone}"));
+                    inaccessible = true;
+                    continue;
+                }
+            };
+            // permissions are only listed for some kinds of tokens
+            let permissions = &found.permissions;
+            if !permissions.is_empty() && !permissions.iter().any(|p| p == "#dns_records:edit") {
+                missing.push(format!("DNS:Edit on {zone}"));
+                continue;
+            }
+            let url = format!("{}/client/v4/zones/{}/dns_records", server_url(), found.id);
+            let req = agent
+                .get(&url)
+                .query("per_page", "5")
+                .set("authorization", &authorization);
+            match req.call() {
+                Ok(_) => {}
+                // listing records only needs read, edit is told by permissions above
+                Err(ureq::Error::Status(403, _)) => missing.push(format!("DNS:Read on {zone}")),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        if missing.is_empty() {
+            debug!(
+                "token could update DNS records in {} zone(s)",
+                self.zones.len()
+            );
+            return Ok(());
         }
 
-        let mut record_identifiers = vec![];
-        while let Some(task) = tasks.next().await {
-            let (id, name) = task??;
-            record_identifiers.push((id, name));
+        let mut message = format!(
+            "token lacks permission(s): {}, edit it at {API_TOKENS_URL}",
+            missing.join(", ")
+        );
+        if inaccessible {
+            let zones = self.list_zones(agent, None).await?;
+            let names = zones.iter().map(|z| z.name.as_str()).collect::<Vec<_>>();
+            match names.is_empty() {
+                true => message.push_str(", no zone is accessible"),
+                false => message.push_str(&format!(", accessible zone(s): {}", names.join(", "))),
+            }
         }
+        Err(CduError::Api {
+            status: None,
+            message,
+        })
+    }
+
+    /// Updates DNS records in zone, failure of one DNS record does not stop others.
+    /// Returns reports of DNS records, and errors of failed ones
+    async fn update_zone(
+        &self,
+        agent: Arc<Agent>,
+        zone: &str,
+        record_names: &[String],
+        current_ip: Ipv4Addr,
+    ) -> Result<(Vec<RecordReport>, Vec<CduError>), CduError> {
+        use futures::StreamExt as _;
 
+        let zone_id = self.get_zone_identifier(agent.clone(), zone).await?;
+        let records =
+            list_dns_records(agent.clone(), self.token.as_ref(), zone_id.as_str()).await?;
+
+        // IP address remains unchanged, so DNS records differing from it were modified elsewhere
+        let drifting = self.detect_drift && self.last_ip() == Some(current_ip);
+
+        let mut reports = vec![];
+        let mut errors = vec![];
         let mut tasks = FuturesUnordered::new();
-        for (id, name) in record_identifiers {
+        for record_name in record_names {
+            let record = match records.iter().find(|r| &r.name == record_name) {
+                Some(r) => r,
+                None => {
+                    let error = CduError::RecordNotFound(record_name.clone());
+                    reports.push(failed_report(zone, record_name, &error, Duration::ZERO));
+                    errors.push(error);
+                    continue;
+                }
+            };
+            if let DnsContent::A { content } = record.content {
+                if content == current_ip {
+                    debug!("DNS record {record_name} is already {current_ip}, skip");
+                    reports.push(RecordReport {
+                        zone: zone.to_string(),
+                        name: record_name.clone(),
+                        outcome: Outcome::Unchanged,
+                        elapsed: Duration::ZERO,
+                        error: None,
+                    });
+                    continue;
+                }
+            }
+
             let agent = agent.clone();
             let token = self.token.to_string();
             let zone_id = zone_id.clone();
+            let zone = zone.to_string();
+            let id = record.id.clone();
+            let name = record_name.clone();
+            let settings = self.settings;
+            let drifted = drifting.then(|| match record.content {
+                DnsContent::A { content } => content.to_string(),
+                ref content => format!("{content:?}"),
+            });
             tasks.push(tokio::spawn(async move {
-                update_dns_record(agent, token, zone_id, id, name, current_ip).await
+                let start = Instant::now();
+                let result = update_dns_record(
+                    agent,
+                    token,
+                    zone_id,
+                    id,
+                    name.clone(),
+                    current_ip,
+                    settings,
+                )
+                .await;
+                match result {
+                    Ok(_) => {
+                        if let Some(content) = drifted {
+                            info!(
+                                "DNS record {name} drifted to {content}, corrected to {current_ip}"
+                            );
+                        }
+                        Ok(RecordReport {
+                            zone,
+                            name,
+                            outcome: Outcome::Updated,
+                            elapsed: start.elapsed(),
+                            error: None,
+                        })
+                    }
+                    Err(error) => {
+                        Err((failed_report(&zone, &name, &error, start.elapsed()), error))
+                    }
+                }
             }));
         }
 
         let len = tasks.len();
-        let tmr = stimer!(Level::Debug; "UPDATE_DNS_RECORDS", "started={len}");
+        let tmr = stimer!(Level::Debug; "UPDATE_DNS_RECORDS", "zone={zone},started={len}");
         while let Some(task) = tasks.next().await {
-            task??;
+            // tasks are never cancelled, so only panic of task is propagated
+            let result = match task {
+                Ok(result) => result,
+                Err(e) => std::panic::resume_unwind(e.into_panic()),
+            };
+            match result {
+                Ok(report) => reports.push(report),
+                Err((report, error)) => {
+                    warn!("failed to update DNS record {}: {error}", report.name);
+                    reports.push(report);
+                    errors.push(error);
+                }
+            }
         }
         finish!(tmr, "finished={len}");
 
-        // save current IP address when update succeeds
-        self.cache.insert(CacheKey::LastIP, Cached::IP(current_ip));
+        Ok((reports, errors))
+    }
 
-        Ok(())
+    fn unchanged_report(&self, current_ip: Ipv4Addr, start: Instant) -> Report {
+        let mut records = vec![];
+        for (zone, record_names) in &self.zones {
+            for name in record_names {
+                records.push(RecordReport {
+                    zone: zone.to_string(),
+                    name: name.clone(),
+                    outcome: Outcome::Unchanged,
+                    elapsed: Duration::ZERO,
+                    error: None,
+                });
+            }
+        }
+        Report {
+            current_ip,
+            records,
+            elapsed: start.elapsed(),
+        }
+    }
+
+    /// Perform DNS record update on Cloudflare.
+    /// Failed DNS records are reported as [`Outcome::Failed`] without stopping others,
+    /// and IP address is only remembered when every DNS record succeeds
+    pub async fn run(&self) -> Result<Report, CduError> {
+        self.run_inner().await.map(|(report, _)| report)
+    }
+
+    async fn run_inner(&self) -> Result<(Report, Vec<CduError>), CduError> {
+        let start = Instant::now();
+
+        let tmr = stimer!(Level::Debug; "FETCH_IP_ADDRESS");
+        let current_ip = public_ip::addr_v4().await.ok_or(CduError::NoPublicIp)?;
+        finish!(tmr, "current_ip={current_ip:?}");
+
+        if self.detect_drift {
+            debug!("detect drift, check DNS records regardless of IPv4 address");
+        } else if let Some(Cached::IP(last_ip)) = self.cache.get(&CacheKey::LastIP) {
+            if current_ip == last_ip {
+                debug!("IPv4 address remains unchanged, skip");
+                return Ok((self.unchanged_report(current_ip, start), vec![]));
+            }
+            debug!("IPv4 address changed from {last_ip} to {current_ip}");
+        } else if self
+            .state
+            .lock()
+            .unwrap()
+            .is_up_to_date(&self.zones, current_ip)
+        {
+            debug!("IPv4 address remains unchanged since last run, skip");
+            self.cache.insert(CacheKey::LastIP, Cached::IP(current_ip));
+            return Ok((self.unchanged_report(current_ip, start), vec![]));
+        } else {
+            debug!("no previous IPv4 address found, continue");
+        }
+
+        let agent = Arc::new(self.build_agent());
+        let mut records = vec![];
+        let mut errors = vec![];
+        for (zone, record_names) in &self.zones {
+            self.wait_for_rate_limit().await;
+            match self
+                .update_zone(agent.clone(), zone, record_names, current_ip)
+                .await
+            {
+                Ok((reports, e)) => {
+                    records.extend(reports);
+                    errors.extend(e);
+                }
+                Err(error) => {
+                    warn!("failed to update DNS records in zone {zone}: {error}");
+                    for name in record_names {
+                        records.push(failed_report(zone, name, &error, Duration::ZERO));
+                    }
+                    errors.push(error);
+                }
+            }
+        }
+
+        // save current IP address only when every DNS record succeeds,
+        // so failed ones are updated again next run
+        if errors.is_empty() {
+            self.cache.insert(CacheKey::LastIP, Cached::IP(current_ip));
+            self.save_state(current_ip)?;
+        }
+
+        let report = Report {
+            current_ip,
+            records,
+            elapsed: start.elapsed(),
+        };
+        Ok((report, errors))
+    }
+
+    /// Delay of exponential backoff, or longer one when told by rate limit of Cloudflare API
+    fn retry_delay(&self, backoff: Option<Duration>) -> Duration {
+        let delay = backoff.unwrap_or(MAX_RETRY_DELAY);
+        delay.max(self.rate_limit.delay().unwrap_or_default())
+    }
+
+    /// Perform DNS record update on Cloudflare,
+    /// and retry with exponential backoff when error is retryable, see [`CduError::is_retryable`]
+    pub async fn run_with_retry(&self) -> Result<Report, CduError> {
+        let backoff = Backoff::new(self.max_attempts, MIN_RETRY_DELAY, MAX_RETRY_DELAY);
+        let mut delays = backoff.iter();
+        let mut attempt = 1;
+        loop {
+            match self.run_inner().await {
+                Ok((report, errors)) => {
+                    let retryable = errors.iter().any(CduError::is_retryable);
+                    if !retryable || attempt >= self.max_attempts {
+                        return Ok(report);
+                    }
+                    let delay = self.retry_delay(delays.next());
+                    let failed = errors.len();
+                    warn!("{failed} DNS record(s) failed in attempt {attempt}, retry in {delay:?}");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) if attempt < self.max_attempts && e.is_retryable() => {
+                    let delay = self.retry_delay(delays.next());
+                    warn!("attempt {attempt} failed, retry in {delay:?} because of {e}");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
     }
 }
 
@@ -270,20 +721,148 @@ mod tests {
     use mockito::{mock, Matcher};
     use std::sync::Arc;
 
+    fn dns_record_json(id: &str, name: &str, content: &str) -> String {
+        format!(
+            r#"{{"meta":{{"auto_added":false}},"locked":false,"name":"{name}","ttl":0,"zone_id":"1","modified_on":"1970-01-01T00:00:00Z","created_on":"1970-01-01T00:00:00Z","proxiable":false,"content":"{content}","type":"A","id":"{id}","proxied":false,"zone_name":"zone"}}"#
+        )
+    }
+
     #[tokio::test]
-    async fn t_get_record_identifier() {
+    async fn t_list_dns_records() {
+        let page = |page: &str| {
+            Matcher::AllOf(vec![
+                Matcher::UrlEncoded("type".into(), "A".into()),
+                Matcher::UrlEncoded("page".into(), page.into()),
+            ])
+        };
         let _m = mock("GET", "/client/v4/zones/1/dns_records")
-            .match_query(Matcher::UrlEncoded("name".into(), "record".into()))
+            .match_query(page("1"))
             .with_status(200)
-            .with_body(r#"{"success":true,"result":[{"meta":{"auto_added":false},"locked":false,"name":"record","ttl":0,"zone_id":"1","modified_on":"1970-01-01T00:00:00Z","created_on":"1970-01-01T00:00:00Z","proxiable":false,"content":"0.0.0.0","type":"A","id":"2","proxied":false,"zone_name":"zone"}],"messages":[],"errors":[]}"#)
+            .with_body(format!(
+                r#"{{"success":true,"result":[{}],"result_info":{{"page":1,"total_pages":2}},"messages":[],"errors":[]}}"#,
+                dns_record_json("2", "a.zone", "0.0.0.0")
+            ))
+            .create();
+        let _n = mock("GET", "/client/v4/zones/1/dns_records")
+            .match_query(page("2"))
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"success":true,"result":[{}],"result_info":{{"page":2,"total_pages":2}},"messages":[],"errors":[]}}"#,
+                dns_record_json("3", "b.zone", "127.0.0.1")
+            ))
             .create();
         let cdu = Cdu::new("token", "zone", &["record"]);
         let agent = Arc::new(cdu.build_agent());
-        let (id, record_name) = get_record_identifier(agent.clone(), "token", "1", "record")
+        let records = list_dns_records(agent.clone(), "token", "1").await.unwrap();
+        let names = records.iter().map(|r| r.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(vec!["a.zone", "b.zone"], names);
+    }
+
+    #[tokio::test]
+    async fn t_update_zone() {
+        let _z = mock("GET", "/client/v4/zones")
+            .match_query(Matcher::UrlEncoded("name".into(), "update".into()))
+            .with_status(200)
+            .with_body(r#"{"success":true,"result":[{"id":"9","name":"update","account":{"id":"2","name":"a"},"created_on":"1970-01-01T00:00:00Z","development_mode":0,"meta":{"custom_certificate_quota":0,"page_rule_quota":0,"phishing_detected":false,"multiple_railguns_allowed":false},"modified_on":"1970-01-01T00:00:00Z","name_servers":[],"owner":{"type":"user","email":"","id":""},"paused":false,"permissions":[],"status":"active","type":"full"}],"messages":[],"errors":[]}"#)
+            .create();
+        let _l = mock("GET", "/client/v4/zones/9/dns_records")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"success":true,"result":[{},{}],"messages":[],"errors":[]}}"#,
+                dns_record_json("2", "a.update", "0.0.0.0"),
+                dns_record_json("3", "b.update", "127.0.0.1")
+            ))
+            .create();
+        let u = mock("PUT", "/client/v4/zones/9/dns_records/2")
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"success":true,"result":{},"messages":[],"errors":[]}}"#,
+                dns_record_json("2", "a.update", "127.0.0.1")
+            ))
+            .expect(1)
+            .create();
+        let record_names = vec!["a.update".to_string(), "b.update".to_string()];
+        let cdu = Cdu::new("token", "update", &record_names);
+        let agent = Arc::new(cdu.build_agent());
+        let (mut reports, errors) = cdu
+            .update_zone(agent, "update", &record_names, "127.0.0.1".parse().unwrap())
+            .await
+            .unwrap();
+        reports.sort_by(|a, b| a.name.cmp(&b.name));
+        u.assert();
+        assert!(errors.is_empty());
+        assert_eq!(Outcome::Updated, reports[0].outcome);
+        assert_eq!(Outcome::Unchanged, reports[1].outcome);
+
+        let _f = mock("PUT", "/client/v4/zones/9/dns_records/2")
+            .with_status(403)
+            .create();
+        let partial = vec![
+            "a.update".to_string(),
+            "b.update".to_string(),
+            "c.update".to_string(),
+        ];
+        let (mut reports, errors) = cdu
+            .update_zone(
+                Arc::new(cdu.build_agent()),
+                "update",
+                &partial,
+                "127.0.0.1".parse().unwrap(),
+            )
+            .await
+            .unwrap();
+        reports.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(2, errors.len());
+        let outcomes = reports.iter().map(|r| r.outcome).collect::<Vec<_>>();
+        assert_eq!(
+            vec![Outcome::Failed, Outcome::Unchanged, Outcome::Failed],
+            outcomes
+        );
+        assert_eq!(
+            Some("DNS record not found: c.update"),
+            reports[2].error.as_deref()
+        );
+    }
+
+    #[tokio::test]
+    async fn t_detect_drift() {
+        let _z = mock("GET", "/client/v4/zones")
+            .match_query(Matcher::UrlEncoded("name".into(), "drift".into()))
+            .with_status(200)
+            .with_body(r#"{"success":true,"result":[{"id":"8","name":"drift","account":{"id":"2","name":"a"},"created_on":"1970-01-01T00:00:00Z","development_mode":0,"meta":{"custom_certificate_quota":0,"page_rule_quota":0,"phishing_detected":false,"multiple_railguns_allowed":false},"modified_on":"1970-01-01T00:00:00Z","name_servers":[],"owner":{"type":"user","email":"","id":""},"paused":false,"permissions":[],"status":"active","type":"full"}],"messages":[],"errors":[]}"#)
+            .create();
+        let _l = mock("GET", "/client/v4/zones/8/dns_records")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"success":true,"result":[{}],"messages":[],"errors":[]}}"#,
+                dns_record_json("4", "a.drift", "10.0.0.1")
+            ))
+            .create();
+        let u = mock("PUT", "/client/v4/zones/8/dns_records/4")
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"success":true,"result":{},"messages":[],"errors":[]}}"#,
+                dns_record_json("4", "a.drift", "127.0.0.1")
+            ))
+            .expect(1)
+            .create();
+        let record_names = vec!["a.drift".to_string()];
+        let current_ip: Ipv4Addr = "127.0.0.1".parse().unwrap();
+        let cdu = Cdu::new("token", "drift", &record_names).with_drift_detection(true);
+        assert_eq!(None, cdu.last_ip());
+        cdu.cache.insert(CacheKey::LastIP, Cached::IP(current_ip));
+        assert_eq!(Some(current_ip), cdu.last_ip());
+
+        let agent = Arc::new(cdu.build_agent());
+        let (reports, errors) = cdu
+            .update_zone(agent, "drift", &record_names, current_ip)
             .await
             .unwrap();
-        assert_eq!("2", id);
-        assert_eq!("record", record_name);
+        u.assert();
+        assert!(errors.is_empty());
+        assert_eq!(Outcome::Updated, reports[0].outcome);
     }
 
     #[tokio::test]
@@ -295,10 +874,67 @@ mod tests {
             .create();
         let cdu = Cdu::new("token", "zone", &["record"]);
         let agent = Arc::new(cdu.build_agent());
-        let zone_identifier = cdu.get_zone_identifier(agent.clone()).await.unwrap();
+        let zone_identifier = cdu
+            .get_zone_identifier(agent.clone(), "zone")
+            .await
+            .unwrap();
         assert_eq!(zone_identifier, "1");
     }
 
+    #[test]
+    fn t_max_attempts() {
+        let cdu = Cdu::new("token", "zone", &["record"]).with_max_attempts(0);
+        assert_eq!(1, cdu.max_attempts);
+    }
+
+    #[tokio::test]
+    async fn t_is_retryable_status() {
+        let _m = mock("GET", "/client/v4/zones")
+            .match_query(Matcher::UrlEncoded("name".into(), "unavailable".into()))
+            .with_status(503)
+            .create();
+        let _n = mock("GET", "/client/v4/zones")
+            .match_query(Matcher::UrlEncoded("name".into(), "forbidden".into()))
+            .with_status(403)
+            .create();
+        let cdu = Cdu::new("token", "zone", &["record"]);
+        let agent = Arc::new(cdu.build_agent());
+        let e = cdu
+            .get_zone_identifier(agent.clone(), "unavailable")
+            .await
+            .unwrap_err();
+        assert!(e.is_retryable());
+        let e = cdu
+            .get_zone_identifier(agent.clone(), "forbidden")
+            .await
+            .unwrap_err();
+        assert!(!e.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn t_observe_rate_limit() {
+        let _m = mock("GET", "/client/v4/zones")
+            .match_query(Matcher::UrlEncoded("name".into(), "limited".into()))
+            .with_status(429)
+            .with_header("retry-after", "3")
+            .create();
+        let cdu = Cdu::new("token", "limited", &["record"]);
+        let agent = Arc::new(cdu.build_agent());
+        assert!(cdu.get_zone_identifier(agent, "limited").await.is_err());
+        let delay = cdu.rate_limit.delay().unwrap();
+        assert!(delay <= Duration::from_secs(3));
+        assert!(cdu.retry_delay(Some(MIN_RETRY_DELAY)) >= Duration::from_secs(2));
+    }
+
+    #[test]
+    fn t_with_zone() {
+        let cdu =
+            Cdu::new("token", "x.com", &["a.x.com"]).with_zone("y.com", &["b.y.com", "c.y.com"]);
+        assert_eq!(2, cdu.zones.len());
+        assert_eq!("y.com", cdu.zones[1].0);
+        assert_eq!(vec!["b.y.com", "c.y.com"], cdu.zones[1].1);
+    }
+
     #[tokio::test]
     async fn t_update_dns_record() {
         let _m2 = mock("PUT", "/client/v4/zones/1/dns_records/2")
@@ -315,8 +951,116 @@ mod tests {
             "2",
             "record",
             "127.0.0.1".parse().unwrap(),
+            RecordSettings::default(),
         )
         .await
         .unwrap();
+
+        let _m3 = mock("PUT", "/client/v4/zones/1/dns_records/3")
+            .match_body(r#"{"content":"127.0.0.1","name":"record","proxied":true,"ttl":300,"type":"A"}"#)
+            .with_status(200)
+            .with_body(r#"{"success":true,"result":{"meta":{"auto_added":false},"locked":false,"name":"record","ttl":300,"zone_id":"1","modified_on":"1970-01-01T00:00:00Z","created_on":"1970-01-01T00:00:00Z","proxiable":true,"content":"127.0.0.1","type":"A","id":"3","proxied":true,"zone_name":"zone"},"messages":[],"errors":[]}"#)
+            .create();
+        let settings = RecordSettings {
+            ttl: 300,
+            proxied: Some(true),
+        };
+        update_dns_record(
+            agent.clone(),
+            "token",
+            "1",
+            "3",
+            "record",
+            "127.0.0.1".parse().unwrap(),
+            settings,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn t_verify() {
+        let token = |token: &str, status: &str| {
+            mock("GET", "/client/v4/user/tokens/verify")
+                .match_header("authorization", format!("bearer {token}").as_str())
+                .with_status(200)
+                .with_body(format!(
+                    r#"{{"success":true,"result":{{"id":"1","status":"{status}"}},"messages":[],"errors":[]}}"#
+                ))
+                .create()
+        };
+        let _a = token("active", "active");
+        let _d = token("disabled", "disabled");
+        let _i = mock("GET", "/client/v4/user/tokens/verify")
+            .match_header("authorization", "bearer invalid")
+            .with_status(401)
+            .create();
+        let zones = |name: &str, body: &str| {
+            mock("GET", "/client/v4/zones")
+                .match_query(Matcher::UrlEncoded("name".into(), name.into()))
+                .with_status(200)
+                .with_body(format!(
+                    r#"{{"success":true,"result":[{body}],"messages":[],"errors":[]}}"#
+                ))
+                .create()
+        };
+        let _z1 = zones(
+            "verified",
+            r##"{"id":"11","name":"verified","account":{"id":"2","name":"a"},"created_on":"1970-01-01T00:00:00Z","development_mode":0,"meta":{"custom_certificate_quota":0,"page_rule_quota":0,"phishing_detected":false,"multiple_railguns_allowed":false},"modified_on":"1970-01-01T00:00:00Z","name_servers":[],"owner":{"type":"user","email":"","id":""},"paused":false,"permissions":["#dns_records:edit","#zone:read"],"status":"active","type":"full"}"##,
+        );
+        let _z2 = zones(
+            "readonly",
+            r##"{"id":"12","name":"readonly","account":{"id":"2","name":"a"},"created_on":"1970-01-01T00:00:00Z","development_mode":0,"meta":{"custom_certificate_quota":0,"page_rule_quota":0,"phishing_detected":false,"multiple_railguns_allowed":false},"modified_on":"1970-01-01T00:00:00Z","name_servers":[],"owner":{"type":"user","email":"","id":""},"paused":false,"permissions":["#dns_records:read","#zone:read"],"status":"active","type":"full"}"##,
+        );
+        let _z3 = zones("hidden", "");
+        let _z4 = zones(
+            "forbidden",
+            r##"{"id":"13","name":"forbidden","account":{"id":"2","name":"a"},"created_on":"1970-01-01T00:00:00Z","development_mode":0,"meta":{"custom_certificate_quota":0,"page_rule_quota":0,"phishing_detected":false,"multiple_railguns_allowed":false},"modified_on":"1970-01-01T00:00:00Z","name_servers":[],"owner":{"type":"user","email":"","id":""},"paused":false,"permissions":[],"status":"active","type":"full"}"##,
+        );
+        let _all = mock("GET", "/client/v4/zones")
+            .match_query(Matcher::Missing)
+            .with_status(200)
+            .with_body(r##"{"success":true,"result":[{"id":"11","name":"verified","account":{"id":"2","name":"a"},"created_on":"1970-01-01T00:00:00Z","development_mode":0,"meta":{"custom_certificate_quota":0,"page_rule_quota":0,"phishing_detected":false,"multiple_railguns_allowed":false},"modified_on":"1970-01-01T00:00:00Z","name_servers":[],"owner":{"type":"user","email":"","id":""},"paused":false,"permissions":["#dns_records:edit","#zone:read"],"status":"active","type":"full"}],"messages":[],"errors":[]}"##)
+            .create();
+        let _r1 = mock("GET", "/client/v4/zones/11/dns_records")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(r#"{"success":true,"result":[],"messages":[],"errors":[]}"#)
+            .create();
+        let _r2 = mock("GET", "/client/v4/zones/13/dns_records")
+            .match_query(Matcher::Any)
+            .with_status(403)
+            .create();
+
+        Cdu::new("active", "verified", &["a.verified"])
+            .verify()
+            .await
+            .unwrap();
+
+        let e = Cdu::new("invalid", "verified", &["a.verified"])
+            .verify()
+            .await
+            .unwrap_err();
+        assert!(e.to_string().starts_with("token is invalid"), "{e}");
+        let e = Cdu::new("disabled", "verified", &["a.verified"])
+            .verify()
+            .await
+            .unwrap_err();
+        assert!(e.to_string().starts_with("token is disabled"), "{e}");
+
+        let e = Cdu::new("active", "verified", &["a.verified"])
+            .with_zone("readonly", &["a.readonly"])
+            .with_zone("hidden", &["a.hidden"])
+            .with_zone("forbidden", &["a.forbidden"])
+            .verify()
+            .await
+            .unwrap_err();
+        assert_eq!(
+            format!(
+                "token lacks permission(s): DNS:Edit on readonly, Zone:Read on hidden, \
+                 DNS:Read on forbidden, edit it at {API_TOKENS_URL}, accessible zone(s): verified"
+            ),
+            e.to_string()
+        );
     }
 }
@@ -13,17 +13,27 @@
 //! Cloudflare DNS record update
 
 use std::borrow::Cow;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
 use std::str::FromStr;
-use std::thread;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use clap::Parser;
-use cloudflare::framework::response::ApiFailure;
+use anyhow::bail;
+use chrono::{DateTime, Utc};
+use clap::{CommandFactory as _, FromArgMatches as _, Parser};
 use cron::Schedule;
-use log::{debug, info, warn, Level};
+use log::{debug, error, info, warn, Level};
 use logging_timer::{finish, timer};
+use serde::Serialize;
+use warp::http::StatusCode;
+use warp::Filter;
 
-use cdu::{Cdu, NoIPV4};
+use cdu::{Cdu, Report};
+
+use crate::config::Config;
+
+mod config;
 
 /// Argument parser
 #[derive(Debug, Parser)]
@@ -31,94 +41,330 @@ use cdu::{Cdu, NoIPV4};
 pub struct Opts {
     /// Cloudflare token
     #[arg(short, long, env = "CLOUDFLARE_TOKEN")]
-    pub token: String,
-    /// Cloudflare zone name
-    #[arg(short, long, env = "CLOUDFLARE_ZONE")]
-    pub zone: String,
-    /// Cloudflare records separated with comma e.g. a.x.com,b.x.com
+    pub token: Option<String>,
+    /// Cloudflare zone name, or zone mapped to its records e.g. x.com=a.x.com,b.x.com.
+    /// Could be repeated, or separated with semicolon in environment variable
+    #[arg(short, long = "zone", env = "CLOUDFLARE_ZONE", value_delimiter = ';')]
+    pub zones: Vec<String>,
+    /// Cloudflare records separated with comma e.g. a.x.com,b.x.com.
+    /// Applied to zones without records
     #[arg(short, long, env = "CLOUDFLARE_RECORDS")]
-    pub records: String,
+    pub records: Option<String>,
     /// Daemon mode
     #[arg(short, long, env = "DAEMON", action = clap::ArgAction::SetTrue)]
     pub daemon: bool,
+    /// Persist last IP address and DNS record states to file across restarts
+    #[arg(long, env = "STATE_FILE")]
+    pub state_file: Option<PathBuf>,
     /// Cron. Only in effect in daemon mode
     #[arg(short, long, default_value = "0 */5 * * * * *", env = "CRON")]
     pub cron: String,
+    /// Random delay up to given seconds added to each cron tick. Only in effect in daemon mode
+    #[arg(long, default_value = "0", env = "JITTER")]
+    pub jitter: u64,
+    /// Update DNS records immediately on startup. Only in effect in daemon mode
+    #[arg(long, env = "RUN_IMMEDIATELY")]
+    pub run_immediately: bool,
+    /// Print result of the run as JSON. Not in effect in daemon mode
+    #[arg(long)]
+    pub status_json: bool,
+    /// Attempts to update DNS records when error is transient e.g. network failure
+    #[arg(long, default_value = "10", env = "MAX_ATTEMPTS")]
+    pub max_attempts: u32,
+    /// Exit with error when any DNS record fails, instead of only when all of them fail.
+    /// Not in effect in daemon mode
+    #[arg(long, env = "STRICT")]
+    pub strict: bool,
+    /// Bind host and port of HTTP /status and /healthz endpoints e.g. 127.0.0.1:8080.
+    /// Only in effect in daemon mode
+    #[arg(long, visible_alias = "health-bind", env = "STATUS_BIND")]
+    pub status_bind: Option<SocketAddr>,
+    /// Runs failed in a row before /healthz reports unhealthy, 0 to ignore failures
+    #[arg(long, default_value = "3", env = "MAX_FAILURES")]
+    pub max_failures: u32,
+    /// Skip verifying token and its permissions on zones on startup
+    #[arg(long, env = "SKIP_VERIFY")]
+    pub skip_verify: bool,
+    /// TTL of DNS records in seconds, 1 for automatic
+    #[arg(long, default_value = "1", env = "TTL")]
+    pub ttl: u32,
+    /// Whether DNS records are proxied by Cloudflare, left as is when not given
+    #[arg(long, env = "PROXIED")]
+    pub proxied: Option<bool>,
+    /// Seconds to remember last IP address, DNS records are checked again after it expires
+    #[arg(long, env = "CACHE_TTL")]
+    pub cache_ttl: Option<u64>,
+    /// Fetch DNS records every run and correct those modified elsewhere,
+    /// even if IP address remains unchanged
+    #[arg(long, env = "DETECT_DRIFT")]
+    pub detect_drift: bool,
+    /// Configuration file in TOML with profiles. Options given on command line take precedence
+    #[arg(long, env = "CDU_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Profile in configuration file
+    #[arg(long, default_value = "default", env = "CDU_PROFILE")]
+    pub profile: String,
+}
+
+/// Run overdue by longer than this means daemon is wedged
+const STALE_AFTER: Duration = Duration::from_secs(3600);
+
+/// Status of daemon
+#[derive(Debug, Default, Serialize)]
+struct Status {
+    last_run_at: Option<DateTime<Utc>>,
+    last_ip: Option<Ipv4Addr>,
+    last_error: Option<String>,
+    /// Runs failed in a row, reset by successful run
+    consecutive_failures: u32,
+    next_run_at: Option<DateTime<Utc>>,
+}
+
+impl Status {
+    /// Why daemon is unhealthy, None if healthy
+    fn unhealthy(&self, now: DateTime<Utc>, max_failures: u32) -> Option<String> {
+        if max_failures > 0 && self.consecutive_failures >= max_failures {
+            return Some(format!(
+                "{} run(s) failed in a row: {}",
+                self.consecutive_failures,
+                self.last_error.as_deref().unwrap_or_default()
+            ));
+        }
+        let due = self.next_run_at?;
+        let stale = chrono::Duration::from_std(STALE_AFTER).ok()?;
+        (now - due > stale).then(|| format!("run due at {due} is overdue"))
+    }
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     pretty_env_logger::init();
 
-    let opts: Opts = Opts::parse();
-
-    let record_names = opts
-        .records
-        .split(',')
-        .map(String::from)
-        .collect::<Vec<String>>();
+    let matches = Opts::command().get_matches();
+    let mut opts = Opts::from_arg_matches(&matches)?;
+    if let Some(ref path) = opts.config {
+        debug!("load profile {} from {path:?}", opts.profile);
+        let profile = Config::load(path)?.into_profile(&opts.profile)?;
+        profile.apply(&mut opts, &matches);
+    }
 
-    let cdu = Cdu::new(&opts.token, &opts.zone, &record_names);
+    let token = match opts.token {
+        Some(ref token) => token,
+        None => bail!("no token specified"),
+    };
+    let zones = parse_zones(&opts.zones, opts.records.as_deref())?;
+    let mut cdu = build_cdu(token, &zones)?
+        .with_max_attempts(opts.max_attempts)
+        .with_ttl(opts.ttl)
+        .with_drift_detection(opts.detect_drift);
+    if let Some(proxied) = opts.proxied {
+        cdu = cdu.with_proxied(proxied);
+    }
+    if let Some(cache_ttl) = opts.cache_ttl {
+        cdu = cdu.with_cache_ttl(Duration::from_secs(cache_ttl));
+    }
+    if let Some(ref path) = opts.state_file {
+        cdu = cdu.with_state_file(path)?;
+    }
+    if !opts.skip_verify {
+        cdu.verify().await?;
+    }
 
     if opts.daemon {
         let cron = &opts.cron;
         debug!("run as daemon with cron {cron}");
-        run_daemon(&cdu, cron).await?;
+        let status = Arc::new(Mutex::new(Status::default()));
+        if let Some(bind) = opts.status_bind {
+            serve_status(bind, status.clone(), opts.max_failures);
+        }
+        let jitter = Duration::from_secs(opts.jitter);
+        run_daemon(&cdu, cron, jitter, opts.run_immediately, status).await?;
     } else {
-        let zone = &opts.zone;
-        let tmr = timer!(Level::Debug; "RUN_ONCE", "zone {zone}");
-        run_once(&cdu).await?;
+        let zones = opts.zones.join(";");
+        let tmr = timer!(Level::Debug; "RUN_ONCE", "zones {zones}");
+        let result = run_once(&cdu).await;
         finish!(tmr);
+        if opts.status_json {
+            let json = match result {
+                Ok(ref report) => serde_json::to_string(report)?,
+                Err(ref e) => serde_json::json!({ "error": e.to_string() }).to_string(),
+            };
+            println!("{json}");
+        }
+        let report = result?;
+        if let Some(message) = failure_message(&report) {
+            if opts.strict || report.all_failed() {
+                bail!(message);
+            }
+            warn!("{message}");
+        }
     }
 
     Ok(())
 }
 
-async fn run_once(cdu: &Cdu<'_>) -> anyhow::Result<()> {
-    let min = Duration::from_millis(100);
-    let max = Duration::from_secs(10);
-    let backoff = exponential_backoff::Backoff::new(10, min, max);
+fn parse_zones(
+    zones: &[String],
+    records: Option<&str>,
+) -> anyhow::Result<Vec<(String, Vec<String>)>> {
+    let mut parsed = vec![];
+    for zone in zones {
+        let (zone, record_names) = match zone.split_once('=') {
+            Some((zone, record_names)) => (zone, record_names),
+            None => match records {
+                Some(record_names) => (zone.as_str(), record_names),
+                None => bail!("no records specified for zone {zone}"),
+            },
+        };
+        let record_names = record_names
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect::<Vec<String>>();
+        if record_names.is_empty() {
+            bail!("no records specified for zone {zone}");
+        }
+        parsed.push((zone.trim().to_string(), record_names));
+    }
+    Ok(parsed)
+}
 
-    let mut iter = backoff.iter();
-    loop {
-        let duration = iter.next();
-        match cdu.run().await {
-            Ok(_) => break,
-            Err(e) => {
-                if let Some(duration) = duration {
-                    if e.is::<ApiFailure>() || e.is::<NoIPV4>() {
-                        warn!("retry in {duration:?} because of {e}");
-                        thread::sleep(duration);
-                    } else {
-                        return Err(e);
-                    }
-                } else {
-                    return Err(e);
-                }
+fn build_cdu<'a>(token: &'a str, zones: &'a [(String, Vec<String>)]) -> anyhow::Result<Cdu<'a>> {
+    let ((zone, record_names), rest) = match zones.split_first() {
+        Some(z) => z,
+        None => bail!("no zone specified"),
+    };
+    let mut cdu = Cdu::new(token, zone, record_names);
+    for (zone, record_names) in rest {
+        cdu = cdu.with_zone(zone, record_names);
+    }
+    Ok(cdu)
+}
+
+fn serve_status(bind: SocketAddr, status: Arc<Mutex<Status>>, max_failures: u32) {
+    let health = status.clone();
+    let status = warp::path("status").and(warp::path::end()).map(move || {
+        let status = status.lock().unwrap();
+        warp::reply::json(&*status)
+    });
+    let healthz = warp::path("healthz").and(warp::path::end()).map(move || {
+        let unhealthy = health.lock().unwrap().unhealthy(Utc::now(), max_failures);
+        match unhealthy {
+            Some(reason) => {
+                warn!("unhealthy: {reason}");
+                warp::reply::with_status(reason, StatusCode::SERVICE_UNAVAILABLE)
             }
+            None => warp::reply::with_status("ok".to_string(), StatusCode::OK),
         }
+    });
+    info!("serve status and health endpoints on {bind}");
+    tokio::spawn(warp::serve(status.or(healthz)).run(bind));
+}
+
+async fn run_once(cdu: &Cdu<'_>) -> anyhow::Result<Report> {
+    Ok(cdu.run_with_retry().await?)
+}
+
+/// Summarizes failed DNS records, None if every DNS record succeeded
+fn failure_message(report: &Report) -> Option<String> {
+    let failures = report.failures();
+    if failures.is_empty() {
+        return None;
     }
+    let details = failures
+        .iter()
+        .map(|r| format!("{}: {}", r.name, r.error.as_deref().unwrap_or_default()))
+        .collect::<Vec<String>>();
+    Some(format!(
+        "{} of {} DNS record(s) failed: {}",
+        failures.len(),
+        report.records.len(),
+        details.join("; ")
+    ))
+}
 
-    Ok(())
+/// Sleep until wall clock reaches target.
+/// Sleep in chunks so suspension of the machine is noticed soon after resume.
+async fn sleep_until(target: DateTime<Utc>) {
+    const MAX_CHUNK: Duration = Duration::from_secs(60);
+    loop {
+        let remaining = match (target - Utc::now()).to_std() {
+            Ok(d) if !d.is_zero() => d,
+            _ => break,
+        };
+        tokio::time::sleep(remaining.min(MAX_CHUNK)).await;
+    }
+}
+
+fn random_jitter(max: Duration) -> chrono::Duration {
+    use rand::Rng as _;
+
+    if max.is_zero() {
+        return chrono::Duration::zero();
+    }
+    let millis = rand::thread_rng().gen_range(0..=max.as_millis() as i64);
+    chrono::Duration::milliseconds(millis)
 }
 
-async fn run_daemon<'a, T>(cdu: &Cdu<'_>, cron: T) -> anyhow::Result<()>
+/// Count cron ticks after given tick that already passed.
+fn missed_ticks(schedule: &Schedule, tick: DateTime<Utc>, now: DateTime<Utc>) -> usize {
+    schedule.after(&tick).take_while(|t| *t <= now).count()
+}
+
+async fn run_scheduled(cdu: &Cdu<'_>, status: &Mutex<Status>) {
+    let result = run_once(cdu).await;
+
+    let mut status = status.lock().unwrap();
+    status.last_run_at = Some(Utc::now());
+    match result {
+        Ok(report) => {
+            status.last_ip = Some(report.current_ip);
+            status.last_error = failure_message(&report);
+            if let Some(ref message) = status.last_error {
+                error!("{message}");
+            }
+        }
+        Err(e) => {
+            error!("failed to update DNS records: {e}");
+            status.last_error = Some(e.to_string());
+        }
+    }
+    status.consecutive_failures = match status.last_error {
+        Some(_) => status.consecutive_failures + 1,
+        None => 0,
+    };
+}
+
+async fn run_daemon<'a, T>(
+    cdu: &Cdu<'_>,
+    cron: T,
+    jitter: Duration,
+    run_immediately: bool,
+    status: Arc<Mutex<Status>>,
+) -> anyhow::Result<()>
 where
     T: Into<Cow<'a, str>>,
 {
     let schedule = Schedule::from_str(cron.into().as_ref())?;
-    for datetime in schedule.upcoming(chrono::Utc) {
-        info!("update DNS records at {datetime}");
-
-        loop {
-            if chrono::Utc::now() > datetime {
-                break;
-            } else {
-                tokio::time::sleep(Duration::from_millis(999)).await;
-            }
+
+    if run_immediately {
+        info!("update DNS records immediately");
+        run_scheduled(cdu, &status).await;
+    }
+
+    while let Some(tick) = schedule.upcoming(Utc).next() {
+        let target = tick + random_jitter(jitter);
+        info!("update DNS records at {target}");
+        status.lock().unwrap().next_run_at = Some(target);
+        sleep_until(target).await;
+
+        let missed = missed_ticks(&schedule, tick, Utc::now());
+        if missed > 0 {
+            warn!("{missed} cron tick(s) missed since {tick}, catch up once");
         }
 
-        run_once(cdu).await?;
+        run_scheduled(cdu, &status).await;
     }
 
     Ok(())
@@ -135,8 +381,123 @@ mod tests {
         ])
         .unwrap();
         assert!(opts.daemon);
-        assert_eq!(opts.records, "records");
-        assert_eq!(opts.token, "token");
-        assert_eq!(opts.zone, "zone");
+        assert_eq!(opts.records, Some("records".to_string()));
+        assert_eq!(opts.token, Some("token".to_string()));
+        assert_eq!(opts.zones, vec!["zone"]);
+    }
+
+    #[test]
+    fn t_multiple_zones() {
+        let opts = Opts::try_parse_from(vec![
+            "--",
+            "-t",
+            "token",
+            "-z",
+            "x.com=a.x.com,b.x.com",
+            "-z",
+            "y.com",
+            "-r",
+            "c.y.com",
+        ])
+        .unwrap();
+        let zones = parse_zones(&opts.zones, opts.records.as_deref()).unwrap();
+        assert_eq!(
+            vec![
+                (
+                    "x.com".to_string(),
+                    vec!["a.x.com".to_string(), "b.x.com".to_string()]
+                ),
+                ("y.com".to_string(), vec!["c.y.com".to_string()]),
+            ],
+            zones
+        );
+    }
+
+    #[test]
+    fn t_missed_ticks() {
+        let schedule = Schedule::from_str("0 */5 * * * * *").unwrap();
+        let tick: DateTime<Utc> = "2023-01-01T00:00:00Z".parse().unwrap();
+        let now: DateTime<Utc> = "2023-01-01T00:01:00Z".parse().unwrap();
+        assert_eq!(0, missed_ticks(&schedule, tick, now));
+        let now: DateTime<Utc> = "2023-01-01T00:12:00Z".parse().unwrap();
+        assert_eq!(2, missed_ticks(&schedule, tick, now));
+    }
+
+    #[test]
+    fn t_random_jitter() {
+        assert_eq!(chrono::Duration::zero(), random_jitter(Duration::ZERO));
+        let max = Duration::from_secs(3);
+        for _ in 0..100 {
+            let jitter = random_jitter(max);
+            assert!(jitter >= chrono::Duration::zero());
+            assert!(jitter <= chrono::Duration::seconds(3));
+        }
+    }
+
+    #[tokio::test]
+    async fn t_sleep_until_past() {
+        let past = Utc::now() - chrono::Duration::seconds(1);
+        tokio::time::timeout(Duration::from_millis(100), sleep_until(past))
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn t_status_unhealthy() {
+        let now: DateTime<Utc> = "2023-01-01T02:00:00Z".parse().unwrap();
+        let mut status = Status {
+            last_error: Some("timeout".into()),
+            consecutive_failures: 2,
+            next_run_at: Some("2023-01-01T01:05:00Z".parse().unwrap()),
+            ..Status::default()
+        };
+        assert_eq!(None, status.unhealthy(now, 3));
+        status.consecutive_failures = 3;
+        assert_eq!(
+            Some("3 run(s) failed in a row: timeout".to_string()),
+            status.unhealthy(now, 3)
+        );
+        assert_eq!(None, status.unhealthy(now, 0));
+        status.next_run_at = Some("2023-01-01T00:55:00Z".parse().unwrap());
+        assert_eq!(
+            Some("run due at 2023-01-01 00:55:00 UTC is overdue".to_string()),
+            status.unhealthy(now, 0)
+        );
+        assert_eq!(None, Status::default().unhealthy(now, 3));
+    }
+
+    #[test]
+    fn t_zone_without_records() {
+        assert!(parse_zones(&["x.com".to_string()], None).is_err());
+        assert!(parse_zones(&["x.com=".to_string()], None).is_err());
+    }
+
+    #[test]
+    fn t_failure_message() {
+        use cdu::{Outcome, RecordReport};
+
+        let record = |name: &str, outcome, error: Option<&str>| RecordReport {
+            zone: "x.com".into(),
+            name: name.into(),
+            outcome,
+            elapsed: Duration::ZERO,
+            error: error.map(|e| e.to_string()),
+        };
+        let mut report = Report {
+            current_ip: "127.0.0.1".parse().unwrap(),
+            records: vec![record("a.x.com", Outcome::Updated, None)],
+            elapsed: Duration::ZERO,
+        };
+        assert_eq!(None, failure_message(&report));
+
+        report.records.push(record(
+            "b.x.com",
+            Outcome::Failed,
+            Some("DNS record not found: b.x.com"),
+        ));
+        assert_eq!(
+            Some("1 of 2 DNS record(s) failed: b.x.com: DNS record not found: b.x.com".to_string()),
+            failure_message(&report)
+        );
     }
 }
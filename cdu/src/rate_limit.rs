@@ -0,0 +1,144 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::debug;
+use ureq::{Middleware, MiddlewareNext, Request, Response};
+
+/// Remaining requests below which next batch waits for quota to reset
+const LOW_REMAINING: u64 = 5;
+
+/// Quota told by headers of Cloudflare response
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct Quota {
+    /// Requests remaining in current window
+    pub(crate) remaining: Option<u64>,
+    /// Time until current window resets
+    pub(crate) reset: Option<Duration>,
+    /// Time to wait before next request, from Retry-After
+    pub(crate) retry_after: Option<Duration>,
+}
+
+impl Quota {
+    /// Parses Retry-After in seconds, and Ratelimit e.g. `"default";r=50;t=30`,
+    /// or RateLimit-Remaining and RateLimit-Reset of earlier drafts
+    pub(crate) fn parse<'a, F>(header: F) -> Self
+    where
+        F: Fn(&str) -> Option<&'a str>,
+    {
+        let seconds = |v: &str| v.trim().parse::<u64>().ok().map(Duration::from_secs);
+        let mut quota = Quota {
+            remaining: header("ratelimit-remaining").and_then(|v| v.trim().parse().ok()),
+            reset: header("ratelimit-reset").and_then(seconds),
+            retry_after: header("retry-after").and_then(seconds),
+        };
+        if let Some(value) = header("ratelimit") {
+            for param in value.split([';', ',']) {
+                match param.trim().split_once('=') {
+                    Some(("r", r)) => quota.remaining = r.trim().parse().ok(),
+                    Some(("t", t)) => quota.reset = seconds(t),
+                    _ => {}
+                }
+            }
+        }
+        quota
+    }
+}
+
+/// Middleware of agent remembering quota of last response, shared across runs
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RateLimit {
+    last: Arc<Mutex<Option<(Quota, Instant)>>>,
+}
+
+impl RateLimit {
+    pub(crate) fn observe(&self, quota: Quota, at: Instant) {
+        if quota == Quota::default() {
+            return;
+        }
+        debug!(
+            "Cloudflare API quota remaining={:?},reset={:?},retry_after={:?}",
+            quota.remaining, quota.reset, quota.retry_after
+        );
+        *self.last.lock().unwrap() = Some((quota, at));
+    }
+
+    /// Time to wait before next batch of requests, when told to retry later or quota is nearly used up
+    pub(crate) fn delay(&self) -> Option<Duration> {
+        let (quota, at) = (*self.last.lock().unwrap())?;
+        let elapsed = at.elapsed();
+        let wait = |d: Duration| d.checked_sub(elapsed).filter(|d| !d.is_zero());
+        if let Some(delay) = quota.retry_after.and_then(wait) {
+            return Some(delay);
+        }
+        match (quota.remaining, quota.reset) {
+            (Some(remaining), Some(reset)) if remaining <= LOW_REMAINING => wait(reset),
+            _ => None,
+        }
+    }
+}
+
+impl Middleware for RateLimit {
+    fn handle(&self, request: Request, next: MiddlewareNext) -> Result<Response, ureq::Error> {
+        let response = next.handle(request)?;
+        self.observe(Quota::parse(|name| response.header(name)), Instant::now());
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_quota() {
+        let headers = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| pairs.iter().find(|(n, _)| *n == name).map(|(_, v)| *v)
+        };
+        assert_eq!(Quota::default(), Quota::parse(headers(&[])));
+        assert_eq!(
+            Quota {
+                remaining: Some(3),
+                reset: Some(Duration::from_secs(30)),
+                retry_after: None,
+            },
+            Quota::parse(headers(&[("ratelimit", r#""default";r=3;t=30"#)]))
+        );
+        assert_eq!(
+            Quota {
+                remaining: Some(100),
+                reset: Some(Duration::from_secs(60)),
+                retry_after: Some(Duration::from_secs(5)),
+            },
+            Quota::parse(headers(&[
+                ("ratelimit-remaining", "100"),
+                ("ratelimit-reset", "60"),
+                ("retry-after", "5"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn t_rate_limit_delay() {
+        let rate_limit = RateLimit::default();
+        assert_eq!(None, rate_limit.delay());
+
+        let quota = |remaining, retry_after| Quota {
+            remaining: Some(remaining),
+            reset: Some(Duration::from_secs(30)),
+            retry_after,
+        };
+        rate_limit.observe(quota(100, None), Instant::now());
+        assert_eq!(None, rate_limit.delay());
+
+        rate_limit.observe(quota(1, None), Instant::now());
+        let delay = rate_limit.delay().unwrap();
+        assert!(delay > Duration::from_secs(29) && delay <= Duration::from_secs(30));
+
+        rate_limit.observe(quota(100, Some(Duration::from_secs(5))), Instant::now());
+        assert!(rate_limit.delay().unwrap() <= Duration::from_secs(5));
+
+        let past = Instant::now() - Duration::from_secs(60);
+        rate_limit.observe(quota(1, Some(Duration::from_secs(5))), past);
+        assert_eq!(None, rate_limit.delay());
+    }
+}
@@ -0,0 +1,120 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+
+fn serialize_millis<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_u128(duration.as_millis())
+}
+
+/// Outcome of one DNS record
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// DNS record is updated to current IP address
+    Updated,
+    /// DNS record is skipped because IP address remains unchanged
+    Unchanged,
+    /// DNS record failed to update, other DNS records are still updated
+    Failed,
+}
+
+/// Report of one DNS record
+#[derive(Clone, Debug, Serialize)]
+pub struct RecordReport {
+    /// Zone name
+    pub zone: String,
+    /// DNS record name
+    pub name: String,
+    /// Outcome
+    pub outcome: Outcome,
+    /// Elapsed time updating the DNS record, in milliseconds when serialized
+    #[serde(rename = "elapsed_ms", serialize_with = "serialize_millis")]
+    pub elapsed: Duration,
+    /// Why DNS record failed to update
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Report of one run
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    /// Detected public IPv4 address
+    pub current_ip: Ipv4Addr,
+    /// Reports of DNS records
+    pub records: Vec<RecordReport>,
+    /// Elapsed time of the run, in milliseconds when serialized
+    #[serde(rename = "elapsed_ms", serialize_with = "serialize_millis")]
+    pub elapsed: Duration,
+}
+
+impl Report {
+    /// Reports of DNS records failed to update
+    pub fn failures(&self) -> Vec<&RecordReport> {
+        self.records
+            .iter()
+            .filter(|r| r.outcome == Outcome::Failed)
+            .collect()
+    }
+
+    /// Whether there are DNS records, and every one of them failed to update
+    pub fn all_failed(&self) -> bool {
+        !self.records.is_empty() && self.failures().len() == self.records.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_serialize() {
+        let report = Report {
+            current_ip: "127.0.0.1".parse().unwrap(),
+            records: vec![RecordReport {
+                zone: "x.com".into(),
+                name: "a.x.com".into(),
+                outcome: Outcome::Updated,
+                elapsed: Duration::from_millis(12),
+                error: None,
+            }],
+            elapsed: Duration::from_millis(34),
+        };
+        assert_eq!(
+            r#"{"current_ip":"127.0.0.1","records":[{"zone":"x.com","name":"a.x.com","outcome":"updated","elapsed_ms":12}],"elapsed_ms":34}"#,
+            serde_json::to_string(&report).unwrap()
+        );
+    }
+
+    #[test]
+    fn t_failures() {
+        let record = |name: &str, outcome| RecordReport {
+            zone: "x.com".into(),
+            name: name.into(),
+            outcome,
+            elapsed: Duration::ZERO,
+            error: (outcome == Outcome::Failed).then(|| "zone not found: x.com".to_string()),
+        };
+        let mut report = Report {
+            current_ip: "127.0.0.1".parse().unwrap(),
+            records: vec![
+                record("a.x.com", Outcome::Updated),
+                record("b.x.com", Outcome::Failed),
+            ],
+            elapsed: Duration::ZERO,
+        };
+        assert_eq!(1, report.failures().len());
+        assert!(!report.all_failed());
+        assert!(serde_json::to_string(&report.records[1])
+            .unwrap()
+            .contains(r#""outcome":"failed","elapsed_ms":0,"error":"zone not found: x.com""#));
+
+        report.records.remove(0);
+        assert!(report.all_failed());
+        report.records.clear();
+        assert!(!report.all_failed());
+    }
+}
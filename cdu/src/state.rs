@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::CduError;
+
+/// DNS record state of last successful update
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub(crate) struct RecordState {
+    pub(crate) zone: String,
+    pub(crate) content: Ipv4Addr,
+    pub(crate) updated_at: DateTime<Utc>,
+}
+
+/// State persisted across restarts
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct State {
+    pub(crate) last_ip: Option<Ipv4Addr>,
+    pub(crate) records: HashMap<String, RecordState>,
+}
+
+impl State {
+    /// Loads state from file, returns empty state when file does not exist
+    pub(crate) fn load<P>(path: P) -> Result<Self, CduError>
+    where
+        P: AsRef<Path>,
+    {
+        let content = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(CduError::State(e.into())),
+        };
+        serde_json::from_str(&content).map_err(|e| CduError::State(e.into()))
+    }
+
+    /// Saves state to file atomically
+    pub(crate) fn save<P>(&self, path: P) -> Result<(), CduError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let temp = path.with_extension("tmp");
+        let content = serde_json::to_vec_pretty(self).map_err(|e| CduError::State(e.into()))?;
+        fs::write(&temp, content).map_err(|e| CduError::State(e.into()))?;
+        fs::rename(&temp, path).map_err(|e| CduError::State(e.into()))?;
+        Ok(())
+    }
+
+    /// Whether every record in zones was updated to the IP address
+    pub(crate) fn is_up_to_date<T>(&self, zones: &[(T, Vec<String>)], ip: Ipv4Addr) -> bool
+    where
+        T: AsRef<str>,
+    {
+        zones.iter().all(|(zone, record_names)| {
+            record_names
+                .iter()
+                .all(|name| match self.records.get(name) {
+                    Some(r) => r.zone == zone.as_ref() && r.content == ip,
+                    None => false,
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("cdu-{}-{name}.json", std::process::id()))
+    }
+
+    #[test]
+    fn t_load_missing() {
+        let state = State::load(temp_path("missing")).unwrap();
+        assert!(state.last_ip.is_none());
+        assert!(state.records.is_empty());
+    }
+
+    #[test]
+    fn t_save_and_load() {
+        let path = temp_path("save");
+        let ip: Ipv4Addr = "127.0.0.1".parse().unwrap();
+        let mut state = State {
+            last_ip: Some(ip),
+            ..Default::default()
+        };
+        state.records.insert(
+            "a.x.com".into(),
+            RecordState {
+                zone: "x.com".into(),
+                content: ip,
+                updated_at: Utc::now(),
+            },
+        );
+        state.save(&path).unwrap();
+
+        let loaded = State::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Some(ip), loaded.last_ip);
+        assert_eq!(state.records, loaded.records);
+    }
+
+    #[test]
+    fn t_is_up_to_date() {
+        let ip: Ipv4Addr = "127.0.0.1".parse().unwrap();
+        let mut state = State::default();
+        state.records.insert(
+            "a.x.com".into(),
+            RecordState {
+                zone: "x.com".into(),
+                content: ip,
+                updated_at: Utc::now(),
+            },
+        );
+        let zones = vec![("x.com", vec!["a.x.com".to_string()])];
+        assert!(state.is_up_to_date(&zones, ip));
+        assert!(!state.is_up_to_date(&zones, "127.0.0.2".parse().unwrap()));
+
+        // new record added after last update
+        let zones = vec![("x.com", vec!["a.x.com".to_string(), "b.x.com".to_string()])];
+        assert!(!state.is_up_to_date(&zones, ip));
+    }
+}
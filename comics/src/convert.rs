@@ -0,0 +1,116 @@
+//! Conversion of images which client does not accept, e.g. WebP on older tablets,
+//! enabled with `convert` feature. AVIF and HEIC are served as is since there is no
+//! pure Rust decoder for them.
+
+use warp::http::header::{ACCEPT, VARY};
+use warp::http::HeaderMap;
+use warp::Reply;
+
+/// Content types which could be decoded and converted
+const CONVERTIBLE: [&str; 1] = ["image/webp"];
+
+/// Whether image is converted for some clients, so responses vary by Accept header
+pub(crate) fn is_convertible(content_type: &str) -> bool {
+    cfg!(feature = "convert") && CONVERTIBLE.contains(&content_type)
+}
+
+/// Whether client does not accept content type and image should be converted
+pub(crate) fn should_convert(headers: &HeaderMap, content_type: &str) -> bool {
+    if !is_convertible(content_type) {
+        return false;
+    }
+    // wildcards are sent by browsers regardless of decoders, only explicit type counts
+    let accepted = headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|t| t.split(';').next().unwrap_or_default().trim() == content_type);
+    !accepted
+}
+
+/// Adds `Vary: Accept` to response of convertible image
+pub(crate) fn with_vary<T>(reply: T, content_type: &str) -> warp::reply::Response
+where
+    T: Reply,
+{
+    let mut response = reply.into_response();
+    if is_convertible(content_type) {
+        response
+            .headers_mut()
+            .insert(VARY, warp::http::HeaderValue::from_static("accept"));
+    }
+    response
+}
+
+/// Content type of converted image
+pub(crate) fn content_type(converted: &[u8]) -> &'static str {
+    if converted.starts_with(b"\x89PNG") {
+        "image/png"
+    } else {
+        "image/jpeg"
+    }
+}
+
+/// Converts image to PNG if it has alpha channel, otherwise to JPEG
+#[cfg(feature = "convert")]
+pub(crate) fn convert(content: &[u8]) -> anyhow::Result<Vec<u8>> {
+    use image::{DynamicImage, ImageOutputFormat};
+    use std::io::Cursor;
+
+    let image = image::load_from_memory(content)?;
+    let mut converted = Cursor::new(vec![]);
+    if image.color().has_alpha() {
+        image.write_to(&mut converted, ImageOutputFormat::Png)?;
+    } else {
+        DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_to(&mut converted, ImageOutputFormat::Jpeg(85))?;
+    }
+    Ok(converted.into_inner())
+}
+
+/// Converts image to PNG if it has alpha channel, otherwise to JPEG
+#[cfg(not(feature = "convert"))]
+pub(crate) fn convert(_content: &[u8]) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("built without convert feature")
+}
+
+#[cfg(all(test, feature = "convert"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_should_convert() {
+        let mut headers = HeaderMap::new();
+        assert!(should_convert(&headers, "image/webp"));
+        assert!(!should_convert(&headers, "image/png"));
+
+        headers.insert(ACCEPT, "image/*,*/*;q=0.8".parse().unwrap());
+        assert!(should_convert(&headers, "image/webp"));
+
+        headers.insert(ACCEPT, "image/avif,image/webp;q=0.9,*/*".parse().unwrap());
+        assert!(!should_convert(&headers, "image/webp"));
+    }
+
+    #[test]
+    fn t_convert() {
+        use image::{DynamicImage, ImageOutputFormat, RgbImage, RgbaImage};
+        use std::io::Cursor;
+
+        // WebP encoder is not available in pure Rust, so round trip through formats we could encode
+        let mut png = Cursor::new(vec![]);
+        DynamicImage::ImageRgba8(RgbaImage::new(2, 2))
+            .write_to(&mut png, ImageOutputFormat::Png)
+            .unwrap();
+        let converted = convert(png.get_ref()).unwrap();
+        assert_eq!("image/png", content_type(&converted));
+
+        let mut png = Cursor::new(vec![]);
+        DynamicImage::ImageRgb8(RgbImage::new(2, 2))
+            .write_to(&mut png, ImageOutputFormat::Png)
+            .unwrap();
+        let converted = convert(png.get_ref()).unwrap();
+        assert_eq!("image/jpeg", content_type(&converted));
+        assert!(image::load_from_memory(&converted).is_ok());
+    }
+}
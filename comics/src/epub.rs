@@ -0,0 +1,198 @@
+//! Reading order of images in EPUB, which is a ZIP archive whose pages are XHTML documents
+//! listed in spine of package document. Fixed-layout comics usually have one image per page.
+
+use std::io::{Read, Seek};
+
+use zip::ZipArchive;
+
+use crate::{has_extension, IMAGE_EXTENSIONS};
+
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Option<String> {
+    let mut entry = archive.by_name(name).ok()?;
+    let mut content = String::new();
+    entry.read_to_string(&mut content).ok()?;
+    Some(content)
+}
+
+/// Contents of start tags with name e.g. `item` in `<item id="a" />`
+fn tags<'a>(xml: &'a str, name: &'a str) -> impl Iterator<Item = &'a str> {
+    xml.split('<').filter_map(move |s| {
+        let rest = s.strip_prefix(name)?;
+        if !rest.starts_with(|c: char| c.is_whitespace() || c == '/' || c == '>') {
+            return None;
+        }
+        Some(rest.split('>').next().unwrap_or_default())
+    })
+}
+
+/// Value of attribute in content of tag, quoted with either single or double quotes
+fn attr<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag;
+    while let Some(i) = rest.find(name) {
+        let preceded = rest[..i].ends_with(char::is_whitespace);
+        rest = &rest[i + name.len()..];
+        let value = match rest.trim_start().strip_prefix('=') {
+            Some(v) if preceded => v.trim_start(),
+            _ => continue,
+        };
+        let quote = value.chars().next().filter(|q| *q == '"' || *q == '\'')?;
+        let value = &value[1..];
+        return value.find(quote).map(|end| &value[..end]);
+    }
+    None
+}
+
+/// Resolves href relative to entry e.g. "../images/1.png" of "OEBPS/text/1.xhtml"
+fn resolve(base: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or_default();
+    let href = urlencoding::decode(href).map_or_else(|_| href.to_string(), |h| h.into_owned());
+    let mut segments = match base.rfind('/') {
+        Some(i) => base[..i].split('/').collect::<Vec<&str>>(),
+        None => vec![],
+    };
+    for segment in href.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            s => segments.push(s),
+        }
+    }
+    segments.join("/")
+}
+
+/// Images referred by document in order, e.g. `<img src>` in XHTML or `<image href>` in SVG
+fn images(document: &str) -> Vec<&str> {
+    let mut images = vec![];
+    for tag in document.split('<') {
+        let href = if tag.starts_with("img") {
+            attr(tag, "src")
+        } else if tag.starts_with("image") {
+            attr(tag, "xlink:href").or_else(|| attr(tag, "href"))
+        } else {
+            None
+        };
+        images.extend(href);
+    }
+    images
+}
+
+/// Images in order of spine, None if EPUB is malformed or refers no image
+pub(crate) fn reading_order<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Option<Vec<String>> {
+    let container = read_entry(archive, "META-INF/container.xml")?;
+    let package_path = tags(&container, "rootfile").find_map(|t| attr(t, "full-path"))?;
+    let package = read_entry(archive, package_path)?;
+
+    let items = tags(&package, "item")
+        .filter_map(|t| Some((attr(t, "id")?, attr(t, "href")?)))
+        .collect::<Vec<(&str, &str)>>();
+    let mut order = vec![];
+    for itemref in tags(&package, "itemref") {
+        let href = attr(itemref, "idref")
+            .and_then(|idref| items.iter().find(|(id, _)| *id == idref))
+            .map(|(_, href)| resolve(package_path, href));
+        let href = match href {
+            Some(h) => h,
+            None => continue,
+        };
+        if has_extension(&href, &IMAGE_EXTENSIONS) {
+            order.push(href);
+            continue;
+        }
+        if let Some(document) = read_entry(archive, &href) {
+            order.extend(images(&document).into_iter().map(|i| resolve(&href, i)));
+        }
+    }
+
+    let names = archive.file_names().collect::<Vec<&str>>();
+    let mut pages = vec![];
+    for name in order {
+        if names.contains(&name.as_str()) && !pages.contains(&name) {
+            pages.push(name);
+        }
+    }
+    (!pages.is_empty()).then_some(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{Cursor, Write as _};
+
+    const CONTAINER: &str = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#;
+
+    const PACKAGE: &str = r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <manifest>
+    <item id="p1" href="text/page%201.xhtml" media-type="application/xhtml+xml"/>
+    <item id="p2" href="text/page2.xhtml" media-type="application/xhtml+xml"/>
+    <item id="cover" href='images/cover.jpg' media-type="image/jpeg"/>
+    <item id="i1" href="images/1.png" media-type="image/png"/>
+    <item id="i10" href="images/10.png" media-type="image/png"/>
+  </manifest>
+  <spine>
+    <itemref idref="cover"/>
+    <itemref idref="p2"/>
+    <itemref idref="p1"/>
+    <itemref idref="missing"/>
+  </spine>
+</package>"#;
+
+    fn epub(entries: &[(&str, &str)]) -> ZipArchive<Cursor<Vec<u8>>> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(vec![]));
+        let options = zip::write::FileOptions::default();
+        for (name, content) in entries {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        let cursor = zip.finish().unwrap();
+        ZipArchive::new(cursor).unwrap()
+    }
+
+    #[test]
+    fn t_reading_order() {
+        assert_eq!(Some("b"), attr(r#" id="a" idref = 'b'"#, "idref"));
+        assert_eq!(Some("a"), attr(r#" idref="b" id="a""#, "id"));
+        assert_eq!(None, attr(r#" xlink:href="a""#, "href"));
+        assert_eq!(
+            "OEBPS/images/1.png",
+            resolve("OEBPS/text/1.xhtml", "../images/1.png#x")
+        );
+        assert_eq!("a b.png", resolve("c.opf", "a%20b.png"));
+
+        let mut archive = epub(&[
+            ("mimetype", "application/epub+zip"),
+            ("META-INF/container.xml", CONTAINER),
+            ("OEBPS/content.opf", PACKAGE),
+            (
+                "OEBPS/text/page 1.xhtml",
+                r#"<html><body><img src="../images/10.png" alt=""/></body></html>"#,
+            ),
+            (
+                "OEBPS/text/page2.xhtml",
+                r#"<svg><image width="1" xlink:href="../images/1.png"/></svg><img src="../images/cover.jpg"/>"#,
+            ),
+            ("OEBPS/images/cover.jpg", ""),
+            ("OEBPS/images/1.png", ""),
+            ("OEBPS/images/10.png", ""),
+        ]);
+        assert_eq!(
+            Some(vec![
+                "OEBPS/images/cover.jpg".to_string(),
+                "OEBPS/images/1.png".to_string(),
+                "OEBPS/images/10.png".to_string(),
+            ]),
+            reading_order(&mut archive)
+        );
+
+        let mut archive = epub(&[("OEBPS/images/1.png", "")]);
+        assert_eq!(None, reading_order(&mut archive));
+    }
+}
@@ -0,0 +1,240 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use warp::http::header::{
+    HeaderMap, ACCEPT_RANGES, CACHE_CONTROL, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE,
+    IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, RANGE,
+};
+use warp::http::{Response, StatusCode};
+use warp::Reply;
+
+/// Requested byte range of content
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum ByteRange {
+    /// Whole content
+    Full,
+    /// Inclusive start and end
+    Partial(u64, u64),
+    /// Range outside of content
+    Unsatisfiable,
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Weak validator built from modified time, size and an optional discriminator
+pub(crate) fn etag(modified: SystemTime, len: u64, discriminator: &str) -> String {
+    let secs = unix_secs(modified);
+    if discriminator.is_empty() {
+        format!("\"{secs:x}-{len:x}\"")
+    } else {
+        format!("\"{secs:x}-{len:x}-{discriminator}\"")
+    }
+}
+
+/// Whether client copy is still fresh according to If-None-Match or If-Modified-Since
+pub(crate) fn is_not_modified(headers: &HeaderMap, etag: &str, modified: SystemTime) -> bool {
+    if let Some(value) = headers.get(IF_NONE_MATCH).and_then(|v| v.to_str().ok()) {
+        return value.split(',').map(str::trim).any(|t| {
+            let t = t.strip_prefix("W/").unwrap_or(t);
+            t == "*" || t == etag
+        });
+    }
+    if let Some(value) = headers.get(IF_MODIFIED_SINCE).and_then(|v| v.to_str().ok()) {
+        if let Ok(since) = httpdate::parse_http_date(value) {
+            return unix_secs(modified) <= unix_secs(since);
+        }
+    }
+    false
+}
+
+/// Parses single byte range, multiple ranges are answered with full content
+pub(crate) fn parse_range(headers: &HeaderMap, len: u64, etag: &str) -> ByteRange {
+    let value = match headers.get(RANGE).and_then(|v| v.to_str().ok()) {
+        Some(v) => v,
+        None => return ByteRange::Full,
+    };
+    if let Some(if_range) = headers.get(IF_RANGE).and_then(|v| v.to_str().ok()) {
+        if if_range != etag {
+            return ByteRange::Full;
+        }
+    }
+    let spec = match value.strip_prefix("bytes=") {
+        Some(s) if !s.contains(',') => s.trim(),
+        _ => return ByteRange::Full,
+    };
+    let (start, end) = match spec.split_once('-') {
+        Some(r) => r,
+        None => return ByteRange::Full,
+    };
+    let range = match (start.parse::<u64>(), end.parse::<u64>()) {
+        // bytes=start-end
+        (Ok(start), Ok(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+        // bytes=start-
+        (Ok(start), Err(_)) if end.is_empty() => (start, len.saturating_sub(1)),
+        // bytes=-suffix
+        (Err(_), Ok(suffix)) if start.is_empty() && suffix > 0 => {
+            (len.saturating_sub(suffix), len.saturating_sub(1))
+        }
+        _ => return ByteRange::Full,
+    };
+    if len == 0 || range.0 >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial(range.0, range.1)
+}
+
+/// Adds validators and Cache-Control to response
+pub(crate) fn with_cache_headers<T>(
+    reply: T,
+    etag: &str,
+    modified: SystemTime,
+    max_age: u64,
+) -> warp::reply::Response
+where
+    T: Reply,
+{
+    let mut response = reply.into_response();
+    let headers = response.headers_mut();
+    if let Ok(v) = etag.parse() {
+        headers.insert(ETAG, v);
+    }
+    if let Ok(v) = httpdate::fmt_http_date(modified).parse() {
+        headers.insert(LAST_MODIFIED, v);
+    }
+    if let Ok(v) = format!("public, max-age={max_age}").parse() {
+        headers.insert(CACHE_CONTROL, v);
+    }
+    response
+}
+
+/// Responds in-memory content with conditional request and range support
+pub(crate) fn respond_bytes(
+    headers: &HeaderMap,
+    content: &[u8],
+    content_type: &str,
+    modified: SystemTime,
+    discriminator: &str,
+    max_age: u64,
+) -> warp::reply::Response {
+    let len = content.len() as u64;
+    let etag = etag(modified, len, discriminator);
+
+    if is_not_modified(headers, &etag, modified) {
+        let reply = warp::reply::with_status(warp::reply(), StatusCode::NOT_MODIFIED);
+        return with_cache_headers(reply, &etag, modified, max_age);
+    }
+
+    let builder = Response::builder()
+        .header(CONTENT_TYPE, content_type)
+        .header(ACCEPT_RANGES, "bytes");
+    let response = match parse_range(headers, len, &etag) {
+        ByteRange::Full => builder.body(content.to_vec()),
+        ByteRange::Partial(start, end) => builder
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(CONTENT_RANGE, format!("bytes {start}-{end}/{len}"))
+            .body(content[start as usize..=end as usize].to_vec()),
+        ByteRange::Unsatisfiable => builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(CONTENT_RANGE, format!("bytes */{len}"))
+            .body(vec![]),
+    };
+    with_cache_headers(response, &etag, modified, max_age)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (k, v) in pairs {
+            headers.insert(
+                warp::http::header::HeaderName::from_bytes(k.as_bytes()).unwrap(),
+                v.parse().unwrap(),
+            );
+        }
+        headers
+    }
+
+    #[test]
+    fn t_parse_range() {
+        let etag = "\"1-a\"";
+        assert_eq!(ByteRange::Full, parse_range(&headers(&[]), 10, etag));
+        let h = headers(&[("range", "bytes=0-3")]);
+        assert_eq!(ByteRange::Partial(0, 3), parse_range(&h, 10, etag));
+        let h = headers(&[("range", "bytes=5-")]);
+        assert_eq!(ByteRange::Partial(5, 9), parse_range(&h, 10, etag));
+        let h = headers(&[("range", "bytes=-4")]);
+        assert_eq!(ByteRange::Partial(6, 9), parse_range(&h, 10, etag));
+        let h = headers(&[("range", "bytes=8-100")]);
+        assert_eq!(ByteRange::Partial(8, 9), parse_range(&h, 10, etag));
+        let h = headers(&[("range", "bytes=10-")]);
+        assert_eq!(ByteRange::Unsatisfiable, parse_range(&h, 10, etag));
+        let h = headers(&[("range", "bytes=0-1,3-4")]);
+        assert_eq!(ByteRange::Full, parse_range(&h, 10, etag));
+        let h = headers(&[("range", "bytes=0-3"), ("if-range", "\"2-a\"")]);
+        assert_eq!(ByteRange::Full, parse_range(&h, 10, etag));
+        let h = headers(&[("range", "bytes=0-3"), ("if-range", etag)]);
+        assert_eq!(ByteRange::Partial(0, 3), parse_range(&h, 10, etag));
+    }
+
+    #[test]
+    fn t_is_not_modified() {
+        let modified = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let etag = etag(modified, 10, "");
+        assert!(!is_not_modified(&headers(&[]), &etag, modified));
+        assert!(is_not_modified(
+            &headers(&[("if-none-match", &etag)]),
+            &etag,
+            modified
+        ));
+        let weak = format!("W/{etag}");
+        assert!(is_not_modified(
+            &headers(&[("if-none-match", &weak)]),
+            &etag,
+            modified
+        ));
+        assert!(!is_not_modified(
+            &headers(&[("if-none-match", "\"x\"")]),
+            &etag,
+            modified
+        ));
+
+        let since = httpdate::fmt_http_date(modified);
+        assert!(is_not_modified(
+            &headers(&[("if-modified-since", &since)]),
+            &etag,
+            modified
+        ));
+        let earlier = httpdate::fmt_http_date(modified - Duration::from_secs(1));
+        assert!(!is_not_modified(
+            &headers(&[("if-modified-since", &earlier)]),
+            &etag,
+            modified
+        ));
+    }
+
+    #[test]
+    fn t_respond_bytes() {
+        let modified = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let content = b"0123456789";
+
+        let response = respond_bytes(&headers(&[]), content, "image/png", modified, "0", 60);
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("public, max-age=60", response.headers()[CACHE_CONTROL]);
+        assert_eq!("bytes", response.headers()[ACCEPT_RANGES]);
+        let etag = response.headers()[ETAG].to_str().unwrap().to_string();
+
+        let h = headers(&[("if-none-match", &etag)]);
+        let response = respond_bytes(&h, content, "image/png", modified, "0", 60);
+        assert_eq!(StatusCode::NOT_MODIFIED, response.status());
+
+        let h = headers(&[("range", "bytes=2-4")]);
+        let response = respond_bytes(&h, content, "image/png", modified, "0", 60);
+        assert_eq!(StatusCode::PARTIAL_CONTENT, response.status());
+        assert_eq!("bytes 2-4/10", response.headers()[CONTENT_RANGE]);
+    }
+}
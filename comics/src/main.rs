@@ -13,33 +13,109 @@
 //! comics is a simple comics server
 
 use std::{
-    fs, io,
+    collections::HashSet,
+    fs,
+    io::{self, Read as _},
     net::SocketAddr,
+    num::NonZeroUsize,
     ops::Deref,
     path::{Path, PathBuf},
     sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
 };
 
 use askama::Template;
-use clap::Parser;
-use log::{debug, error, info};
+use clap::{Parser, ValueEnum};
+use log::{debug, error, info, warn};
+use lru::LruCache;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher as _};
+use once_cell::sync::OnceCell;
 use pathdiff::diff_paths;
+use serde::Deserialize;
 use warp::{
+    filters::BoxedFilter,
+    http::header::{CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE},
+    http::HeaderMap,
     hyper::{StatusCode, Uri},
-    Filter,
+    Filter, Reply,
 };
 
+mod convert;
+mod epub;
+mod http;
+mod marks;
+#[cfg(feature = "pdf")]
+mod pdf;
+mod prefs;
+mod stats;
+mod validate;
+
+use crate::marks::{MarkForm, MarkStore, Marks};
+use crate::prefs::{Fit, Layout, Prefs, PrefsForm};
+use crate::stats::Stats;
+
+/// Comic listed on index with its marks
+struct Entry<'a> {
+    comic: &'a Comic,
+    favorite: bool,
+    hidden: bool,
+}
+
 #[derive(Template)]
 #[template(path = "index.html")]
 struct IndexTemplate<'a> {
-    comics: &'a Vec<Comic>,
+    comics: Vec<Entry<'a>>,
+    total: usize,
+    q: &'a str,
+    favorites: bool,
+    show_hidden: bool,
     updated: String,
 }
 
+#[derive(Template)]
+#[template(path = "stats.html")]
+struct StatsTemplate<'a> {
+    stats: &'a Stats,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct IndexQuery {
+    q: Option<String>,
+    /// Only list favorite comics
+    #[serde(default)]
+    favorites: bool,
+    /// List hidden comics as well
+    #[serde(default)]
+    show_hidden: bool,
+}
+
 #[derive(Template)]
 #[template(path = "comic.html")]
 struct ComicTemplate<'a> {
     comic: &'a Comic,
+    favorite: bool,
+    hidden: bool,
+}
+
+/// Adjacent page in reader
+struct Link {
+    href: String,
+    src: String,
+}
+
+#[derive(Template)]
+#[template(path = "page.html")]
+struct PageTemplate<'a> {
+    comic: &'a Comic,
+    page: &'a Page,
+    /// Page shown next to first one in double layout
+    second: Option<&'a Page>,
+    prefs: Prefs,
+    href: String,
+    number: usize,
+    total: usize,
+    prev: Option<Link>,
+    next: Option<Link>,
 }
 
 #[derive(Parser)]
@@ -48,11 +124,59 @@ struct Opts {
     /// Bind host and port
     #[arg(short, long, default_value = "127.0.0.1:3000")]
     bind: String,
-    /// Data directory
-    #[arg(short, long, default_value = "./data")]
-    data_dir: String,
+    /// Data directory. Could be repeated or separated with comma to merge comics from several directories
+    #[arg(
+        short,
+        long = "data-dir",
+        default_value = "./data",
+        value_delimiter = ','
+    )]
+    data_dirs: Vec<PathBuf>,
+    /// Sort comics on index page by name or modified time (newest first)
+    #[arg(long, value_enum, default_value = "name")]
+    sort: SortBy,
+    /// Rescan data directory periodically in seconds, in case filesystem events are missed. 0 to disable
+    #[arg(long, default_value = "0")]
+    rescan_interval: u64,
+    /// Number of pages extracted from archives kept in memory
+    #[arg(long, default_value = "64")]
+    archive_cache_size: NonZeroUsize,
+    /// Cache-Control max-age of images in seconds
+    #[arg(long, default_value = "86400")]
+    max_age: u64,
+    /// Report empty directories, unreadable files, unsupported formats and duplicate names
+    /// in data directories, then exit without starting server
+    #[arg(long)]
+    validate: bool,
+    /// Persist favorite and hidden comics to file, otherwise they are kept in memory only
+    #[arg(long)]
+    marks_file: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+enum SortBy {
+    /// Name in natural order
+    Name,
+    /// Modified time, newest first
+    Mtime,
 }
 
+/// File extensions of archives treated as comics, RAR archives i.e. CBR are not supported
+#[cfg(not(feature = "pdf"))]
+const ARCHIVE_EXTENSIONS: [&str; 3] = ["cbz", "epub", "zip"];
+
+/// File extensions of archives treated as comics, PDF pages are rendered to images
+#[cfg(feature = "pdf")]
+const ARCHIVE_EXTENSIONS: [&str; 4] = ["cbz", "epub", "pdf", "zip"];
+
+/// File extensions of images treated as pages in archives
+const IMAGE_EXTENSIONS: [&str; 7] = ["avif", "bmp", "gif", "jpeg", "jpg", "png", "webp"];
+
+/// Period without filesystem events before library is reloaded
+const WATCH_DEBOUNCE: Duration = Duration::from_secs(2);
+
+type PageCache = Mutex<LruCache<(PathBuf, String), Arc<Vec<u8>>>>;
+
 mod filters {
     /// Custom filter to encode string for URL
     #[allow(dead_code)]
@@ -64,13 +188,80 @@ mod filters {
 #[derive(Debug)]
 struct Page {
     name: String,
+    src: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Source {
+    /// Pages are files in a directory, served relative to data directory of index
+    Directory {
+        dir: PathBuf,
+        data_dir: PathBuf,
+        index: usize,
+    },
+    /// Pages are entries of an archive
+    Archive(PathBuf),
+}
+
+impl Source {
+    /// Lists pages, none when source could not be read
+    fn list(&self, name: &str) -> Listing {
+        let listing = match self {
+            Source::Directory {
+                dir,
+                data_dir,
+                index,
+            } => list_directory(*index, data_dir, dir),
+            Source::Archive(path) => Ok(list_archive(path, name)),
+        };
+        match listing {
+            Ok(listing) => {
+                debug!("{} page(s) of comic {name} listed", listing.pages.len());
+                listing
+            }
+            Err(e) => {
+                warn!("failed to list pages of comic {name}: {e}");
+                Listing::default()
+            }
+        }
+    }
+}
+
+/// Pages of comic
+#[derive(Debug, Default)]
+struct Listing {
+    pages: Vec<Page>,
+    /// Total size of pages in directory, or size of archive in bytes
+    size: u64,
 }
 
 #[derive(Debug)]
 struct Comic {
-    cover: PathBuf,
     name: String,
-    pages: Vec<Page>,
+    source: Source,
+    modified: SystemTime,
+    cover_src: String,
+    /// Listed on first access or by background scan, so only comics are enumerated on startup
+    listing: OnceCell<Listing>,
+}
+
+impl Comic {
+    fn cover_src(&self) -> &str {
+        &self.cover_src
+    }
+
+    fn listing(&self) -> &Listing {
+        self.listing.get_or_init(|| self.source.list(&self.name))
+    }
+
+    fn pages(&self) -> &[Page] {
+        &self.listing().pages
+    }
+
+    /// Pages if listed already, so index does not wait for slow storage
+    fn listed(&self) -> Option<&Listing> {
+        self.listing.get()
+    }
 }
 
 #[derive(Debug)]
@@ -79,74 +270,249 @@ struct Comics {
     updated: chrono::DateTime<chrono::Local>,
 }
 
-fn list_comics<T>(data_dir: T) -> io::Result<Comics>
+fn has_extension<T>(path: T, extensions: &[&str]) -> bool
 where
     T: AsRef<Path>,
 {
-    let data_dir = data_dir.as_ref();
+    match path.as_ref().extension() {
+        Some(ext) => {
+            let ext = ext.to_string_lossy().to_lowercase();
+            extensions.contains(&ext.as_str())
+        }
+        None => false,
+    }
+}
 
-    let mut comics = vec![];
+/// URL of file relative to data directory, data directories are told apart by index
+fn static_src<T>(index: usize, path: T) -> String
+where
+    T: AsRef<Path>,
+{
+    let segments = path
+        .as_ref()
+        .iter()
+        .map(|s| urlencoding::encode(&s.to_string_lossy()).into_owned())
+        .collect::<Vec<String>>();
+    format!("/static/{index}/{}", segments.join("/"))
+}
 
-    for entry in fs::read_dir(data_dir)? {
-        let dir = entry?;
-        let metadata = dir.metadata()?;
+/// URL of page in archive, index starts from 0
+fn archive_src(name: &str, index: usize) -> String {
+    let encoded = urlencoding::encode(name);
+    format!("/archive/{encoded}/{index}")
+}
 
-        if !metadata.is_dir() {
+fn list_directory(index: usize, data_dir: &Path, dir: &Path) -> io::Result<Listing> {
+    let mut pages = vec![];
+    let mut size = 0;
+    for file in fs::read_dir(dir)? {
+        let file = file?;
+        let metadata = file.metadata()?;
+        if !metadata.is_file() {
             continue;
         }
-
-        let mut pages = vec![];
-        for file in fs::read_dir(dir.path())? {
-            let file = file?;
-            let metadata = file.metadata()?;
-            if !metadata.is_file() {
-                continue;
-            }
-            if metadata.is_symlink() {
-                continue;
-            }
-            let path = match diff_paths(&file.path(), data_dir) {
-                Some(p) => p,
-                None => continue,
-            };
-            pages.push(path);
+        if metadata.is_symlink() {
+            continue;
         }
+        let path = match diff_paths(file.path(), data_dir) {
+            Some(p) => p,
+            None => continue,
+        };
+        size += metadata.len();
+        pages.push(path);
+    }
+
+    pages.sort_by(|a, b| natord::compare(&a.to_string_lossy(), &b.to_string_lossy()));
 
-        pages.sort_by(|a, b| {
-            a.to_string_lossy()
-                .partial_cmp(&b.to_string_lossy())
-                .unwrap()
+    let pages = pages
+        .iter()
+        .map(|p| Page {
+            name: p.to_string_lossy().to_string(),
+            src: static_src(index, p),
+        })
+        .collect::<Vec<Page>>();
+    Ok(Listing { pages, size })
+}
+
+/// Comic in directory with first file in natural order as cover, other files are not
+/// inspected until pages are listed
+fn load_directory(
+    index: usize,
+    data_dir: &Path,
+    dir: &Path,
+    modified: SystemTime,
+) -> io::Result<Option<Comic>> {
+    let mut cover: Option<PathBuf> = None;
+    for file in fs::read_dir(dir)? {
+        let file = file?;
+        // told by directory entry on most platforms without another system call,
+        // symbolic links are not files here as in listing
+        if !file.file_type()?.is_file() {
+            continue;
+        }
+        let path = file.path();
+        let first = cover.as_ref().map_or(true, |c| {
+            natord::compare(&path.to_string_lossy(), &c.to_string_lossy()).is_lt()
         });
+        if first {
+            cover = Some(path);
+        }
+    }
 
-        let cover = match pages.first() {
-            Some(c) => c,
-            None => continue,
-        };
+    let cover = match cover.and_then(|c| diff_paths(c, data_dir)) {
+        Some(c) => c,
+        None => return Ok(None),
+    };
 
-        let name = dir.path();
-        let name = match name.file_name() {
-            Some(s) => s.to_string_lossy(),
-            None => continue,
-        };
+    let name = match dir.file_name() {
+        Some(s) => s.to_string_lossy().to_string(),
+        None => return Ok(None),
+    };
 
-        debug!("load comic {name}");
+    Ok(Some(Comic {
+        name,
+        source: Source::Directory {
+            dir: dir.to_path_buf(),
+            data_dir: data_dir.to_path_buf(),
+            index,
+        },
+        modified,
+        cover_src: static_src(index, cover),
+        listing: OnceCell::new(),
+    }))
+}
 
-        let pages = pages
-            .iter()
-            .map(|p| Page {
-                name: p.to_string_lossy().to_string(),
-            })
-            .collect::<Vec<Page>>();
-
-        let comic = Comic {
-            cover: cover.to_path_buf(),
-            name: name.into(),
-            pages,
+fn list_archive_entries(path: &Path) -> zip::result::ZipResult<Vec<String>> {
+    #[cfg(feature = "pdf")]
+    if has_extension(path, &["pdf"]) {
+        return Ok(pdf::page_names(path)?);
+    }
+    let file = fs::File::open(path)?;
+    let mut archive = zip::ZipArchive::new(file)?;
+    // images in EPUB are ordered by its pages, and in natural order as other archives otherwise
+    if has_extension(path, &["epub"]) {
+        if let Some(names) = epub::reading_order(&mut archive) {
+            return Ok(names);
+        }
+    }
+    let mut names = archive
+        .file_names()
+        .filter(|n| !n.ends_with('/') && has_extension(n, &IMAGE_EXTENSIONS))
+        .map(String::from)
+        .collect::<Vec<String>>();
+    names.sort_by(|a, b| natord::compare(a, b));
+    Ok(names)
+}
+
+/// Lists pages of archive, none when archive could not be read
+fn list_archive(path: &Path, name: &str) -> Listing {
+    let entries = match list_archive_entries(path) {
+        Ok(e) => e,
+        Err(e) => {
+            warn!("skip {path:?}, failed to read archive: {e}");
+            return Listing::default();
+        }
+    };
+
+    let pages = entries
+        .into_iter()
+        .enumerate()
+        .map(|(i, entry)| Page {
+            name: entry,
+            src: archive_src(name, i),
+        })
+        .collect::<Vec<Page>>();
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or_default();
+    Listing { pages, size }
+}
+
+/// Comic in archive, which is not opened until pages are listed
+fn load_archive(path: &Path, modified: SystemTime) -> Option<Comic> {
+    let name = path.file_stem()?.to_string_lossy().to_string();
+    Some(Comic {
+        cover_src: archive_src(&name, 0),
+        name,
+        source: Source::Archive(path.to_path_buf()),
+        modified,
+        listing: OnceCell::new(),
+    })
+}
+
+fn read_archive_page(path: &Path, name: &str) -> zip::result::ZipResult<Vec<u8>> {
+    #[cfg(feature = "pdf")]
+    if has_extension(path, &["pdf"]) {
+        return Ok(pdf::render_page(path, name)?);
+    }
+    let file = fs::File::open(path)?;
+    let mut archive = zip::ZipArchive::new(file)?;
+    let mut entry = archive.by_name(name)?;
+    let mut buffer = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+fn list_data_dir(index: usize, data_dir: &Path) -> io::Result<Vec<Comic>> {
+    let mut comics = vec![];
+
+    for entry in fs::read_dir(data_dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let path = entry.path();
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+
+        let comic = if metadata.is_dir() {
+            load_directory(index, data_dir, &path, modified)?
+        } else if metadata.is_file() && has_extension(&path, &ARCHIVE_EXTENSIONS) {
+            load_archive(&path, modified)
+        } else {
+            None
         };
-        comics.push(comic);
+
+        if let Some(comic) = comic {
+            let name = &comic.name;
+            debug!("load comic {name}");
+            comics.push(comic);
+        }
+    }
+
+    comics.sort_by(|a, b| natord::compare(&a.name, &b.name));
+    Ok(comics)
+}
+
+/// Suffixes name taken by comic in preceding data directory with number e.g. "name (2)"
+fn unique_name(name: &str, names: &HashSet<String>) -> String {
+    if !names.contains(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|n| format!("{name} ({n})"))
+        .find(|n| !names.contains(n))
+        .unwrap_or_else(|| name.to_string())
+}
+
+fn list_comics<T>(data_dirs: &[T]) -> io::Result<Comics>
+where
+    T: AsRef<Path>,
+{
+    let mut comics = vec![];
+    let mut names = HashSet::new();
+
+    for (index, data_dir) in data_dirs.iter().enumerate() {
+        for mut comic in list_data_dir(index, data_dir.as_ref())? {
+            let name = unique_name(&comic.name, &names);
+            if name != comic.name {
+                warn!("comic {} exists, rename to {name}", comic.name);
+                if let Source::Archive(_) = comic.source {
+                    comic.cover_src = archive_src(&name, 0);
+                }
+                comic.name = name.clone();
+            }
+            names.insert(name);
+            comics.push(comic);
+        }
     }
 
-    comics.sort_by(|a, b| a.name.partial_cmp(&b.name).unwrap());
+    comics.sort_by(|a, b| natord::compare(&a.name, &b.name));
 
     let count = comics.len();
     info!("{count} comic(s) loaded");
@@ -158,29 +524,612 @@ where
     Ok(comics)
 }
 
+fn reader_href(comic: &Comic, number: usize) -> String {
+    let name = urlencoding::encode(&comic.name);
+    format!("/comic/{name}/page/{number}")
+}
+
+/// Renders page in reader, number starts from 1
+fn render_page(comic: &Comic, number: usize, prefs: Prefs) -> Option<askama::Result<String>> {
+    let pages = comic.pages();
+    let page = pages.get(number.checked_sub(1)?)?;
+    let step = prefs.step();
+    let second = match prefs.layout {
+        Layout::Single => None,
+        Layout::Double => pages.get(number),
+    };
+    let link = |n: usize| {
+        pages.get(n.checked_sub(1)?).map(|p| Link {
+            href: reader_href(comic, n),
+            src: p.src.clone(),
+        })
+    };
+    let tpl = PageTemplate {
+        comic,
+        page,
+        second,
+        prefs,
+        href: reader_href(comic, number),
+        number,
+        total: pages.len(),
+        prev: link(number.saturating_sub(step).max(1)).filter(|_| number > 1),
+        next: link(number + step),
+    };
+    Some(tpl.render())
+}
+
+fn sort_comics(comics: &[Comic], sort: SortBy) -> Vec<&Comic> {
+    let mut sorted = comics.iter().collect::<Vec<&Comic>>();
+    if sort == SortBy::Mtime {
+        // comics are sorted by name already, stable sort keeps the order of same modified time
+        sorted.sort_by_key(|c| std::cmp::Reverse(c.modified));
+    }
+    sorted
+}
+
+/// Keeps comics whose names contain the query, case-insensitively,
+/// and drops comics found without pages e.g. unsupported archives
+fn filter_comics<'a>(comics: Vec<&'a Comic>, q: &str) -> Vec<&'a Comic> {
+    let q = q.trim().to_lowercase();
+    comics
+        .into_iter()
+        .filter(|c| c.listed().map_or(true, |l| !l.pages.is_empty()))
+        .filter(|c| q.is_empty() || c.name.to_lowercase().contains(&q))
+        .collect()
+}
+
+/// Attaches marks to comics, hidden ones are excluded unless shown,
+/// and only favorite ones are kept when favorites filter is on
+fn mark_comics<'a>(
+    comics: Vec<&'a Comic>,
+    marks: &Marks,
+    favorites: bool,
+    show_hidden: bool,
+) -> Vec<Entry<'a>> {
+    comics
+        .into_iter()
+        .map(|comic| Entry {
+            comic,
+            favorite: marks.is_favorite(&comic.name),
+            hidden: marks.is_hidden(&comic.name),
+        })
+        .filter(|e| (show_hidden || !e.hidden) && (!favorites || e.favorite))
+        .collect()
+}
+
+fn reload_comics<T>(data_dirs: &[T], comics: &Arc<Mutex<Comics>>)
+where
+    T: AsRef<Path>,
+{
+    match list_comics(data_dirs) {
+        Ok(new_comics) => {
+            *comics.lock().unwrap() = new_comics;
+            scan_in_background(comics.clone());
+        }
+        Err(e) => error!("{e}"),
+    }
+}
+
+/// Lists pages of comic without holding the lock, since listing reads slow storage.
+/// Returns false if there is no such comic
+fn list_unlocked(comics: &Mutex<Comics>, name: &str) -> bool {
+    let source = {
+        let comics = comics.lock().unwrap();
+        match comics.comics.iter().find(|c| c.name == name) {
+            Some(comic) if comic.listed().is_some() => return true,
+            Some(comic) => comic.source.clone(),
+            None => return false,
+        }
+    };
+    let listing = source.list(name);
+    let comics = comics.lock().unwrap();
+    match comics.comics.iter().find(|c| c.name == name) {
+        Some(comic) => {
+            // library might be reloaded meanwhile
+            if comic.source == source {
+                let _ = comic.listing.set(listing);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Lists pages of comics one by one, lock is released between comics so requests
+/// are not blocked for long. Stops once library is reloaded
+fn scan_pages(comics: &Mutex<Comics>) {
+    let updated = comics.lock().unwrap().updated;
+    let mut index = 0;
+    loop {
+        let name = {
+            let comics = comics.lock().unwrap();
+            if comics.updated != updated {
+                debug!("library reloaded, stop scanning pages");
+                return;
+            }
+            match comics.comics.get(index) {
+                Some(comic) => comic.name.clone(),
+                None => break,
+            }
+        };
+        list_unlocked(comics, &name);
+        index += 1;
+    }
+    info!("pages of {index} comic(s) listed");
+}
+
+fn scan_in_background(comics: Arc<Mutex<Comics>>) {
+    std::thread::spawn(move || scan_pages(&comics));
+}
+
+fn watch_data_dirs(
+    data_dirs: Vec<PathBuf>,
+    comics: Arc<Mutex<Comics>>,
+    debounce: Duration,
+) -> notify::Result<RecommendedWatcher> {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher =
+        notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+            Ok(event) if !event.kind.is_access() => {
+                let _ = tx.send(());
+            }
+            Ok(_) => {}
+            Err(e) => error!("{e}"),
+        })?;
+    for data_dir in &data_dirs {
+        watcher.watch(data_dir, RecursiveMode::Recursive)?;
+    }
+
+    tokio::spawn(async move {
+        while rx.recv().await.is_some() {
+            // reload once no more event arrives within debounce period
+            loop {
+                match tokio::time::timeout(debounce, rx.recv()).await {
+                    Ok(Some(_)) => continue,
+                    Ok(None) => return,
+                    Err(_) => break,
+                }
+            }
+            info!("data directory changed, reload comics");
+            let data_dirs = data_dirs.clone();
+            let comics = comics.clone();
+            let reload = tokio::task::spawn_blocking(move || reload_comics(&data_dirs, &comics));
+            if let Err(e) = reload.await {
+                error!("{e}");
+            }
+        }
+    });
+
+    Ok(watcher)
+}
+
+fn rescan_periodically(data_dirs: Vec<PathBuf>, comics: Arc<Mutex<Comics>>, period: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        interval.tick().await; // first tick completes immediately
+        loop {
+            interval.tick().await;
+            debug!("rescan data directory");
+            let data_dirs = data_dirs.clone();
+            let comics = comics.clone();
+            let reload = tokio::task::spawn_blocking(move || reload_comics(&data_dirs, &comics));
+            if let Err(e) = reload.await {
+                error!("{e}");
+            }
+        }
+    });
+}
+
+/// Resolves when SIGINT or SIGTERM is received, so in-flight requests could finish
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let (mut int, mut term) = match (
+            signal(SignalKind::interrupt()),
+            signal(SignalKind::terminate()),
+        ) {
+            (Ok(int), Ok(term)) => (int, term),
+            (Err(e), _) | (_, Err(e)) => {
+                error!("failed to listen to signals: {e}");
+                return std::future::pending().await;
+            }
+        };
+        tokio::select! {
+            _ = int.recv() => info!("SIGINT received, shut down gracefully"),
+            _ = term.recv() => info!("SIGTERM received, shut down gracefully"),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("failed to listen to ctrl-c: {e}");
+            return std::future::pending().await;
+        }
+        info!("ctrl-c received, shut down gracefully");
+    }
+}
+
+/// Reloads library and clears archive page cache on SIGHUP
+#[cfg(unix)]
+fn reload_on_hangup(
+    data_dirs: Vec<PathBuf>,
+    comics: Arc<Mutex<Comics>>,
+    cache: Arc<PageCache>,
+) -> io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            info!("SIGHUP received, reload data directory");
+            cache.lock().unwrap().clear();
+            let data_dirs = data_dirs.clone();
+            let comics = comics.clone();
+            let reload = tokio::task::spawn_blocking(move || reload_comics(&data_dirs, &comics));
+            if let Err(e) = reload.await {
+                error!("{e}");
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Responds image converted to format client accepts, or None to serve image as is.
+/// Converted images share cache with archive pages
+fn respond_converted<F>(
+    cache: &PageCache,
+    key: (PathBuf, String),
+    headers: &HeaderMap,
+    content_type: &str,
+    modified: SystemTime,
+    max_age: u64,
+    read: F,
+) -> Option<warp::reply::Response>
+where
+    F: FnOnce() -> anyhow::Result<Arc<Vec<u8>>>,
+{
+    if !convert::should_convert(headers, content_type) {
+        return None;
+    }
+
+    let key = (key.0, format!("{}#converted", key.1));
+    let cached = cache.lock().unwrap().get(&key).cloned();
+    let converted = match cached {
+        Some(c) => c,
+        None => match read().and_then(|c| convert::convert(&c)) {
+            Ok(c) => {
+                let c = Arc::new(c);
+                cache.lock().unwrap().put(key.clone(), c.clone());
+                c
+            }
+            Err(e) => {
+                warn!("failed to convert {key:?}, serve as is: {e}");
+                return None;
+            }
+        },
+    };
+
+    let response = http::respond_bytes(
+        headers,
+        &converted,
+        convert::content_type(&converted),
+        modified,
+        &key.1,
+        max_age,
+    );
+    Some(convert::with_vary(response, content_type))
+}
+
+/// Adds ETag and Cache-Control to static file, warp already handles Last-Modified and Range
+fn static_file(
+    cache: &PageCache,
+    headers: &HeaderMap,
+    file: warp::fs::File,
+    max_age: u64,
+) -> warp::reply::Response {
+    let metadata = match fs::metadata(file.path()) {
+        Ok(m) => m,
+        Err(_) => return file.into_response(),
+    };
+    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+    let mime = mime_guess::from_path(file.path()).first_or_octet_stream();
+    let key = (file.path().to_path_buf(), String::new());
+    let read = || Ok(Arc::new(fs::read(file.path())?));
+    if let Some(response) =
+        respond_converted(cache, key, headers, mime.as_ref(), modified, max_age, read)
+    {
+        return response;
+    }
+
+    let etag = http::etag(modified, metadata.len(), "");
+    if http::is_not_modified(headers, &etag, modified) {
+        let reply = warp::reply::with_status(warp::reply(), StatusCode::NOT_MODIFIED);
+        let response = http::with_cache_headers(reply, &etag, modified, max_age);
+        return convert::with_vary(response, mime.as_ref());
+    }
+    let response = http::with_cache_headers(file, &etag, modified, max_age);
+    convert::with_vary(response, mime.as_ref())
+}
+
+/// Reads page of archive, lock of comics is only held to look up the page
+fn archive_page(
+    comics: &Mutex<Comics>,
+    cache: &PageCache,
+    name: &str,
+    index: usize,
+    headers: &HeaderMap,
+    max_age: u64,
+) -> warp::reply::Response {
+    let not_found = || warp::reply::with_status("not found", StatusCode::NOT_FOUND).into_response();
+
+    if !list_unlocked(comics, name) {
+        return not_found();
+    }
+    let (path, page_name, modified) = {
+        let comics = comics.lock().unwrap();
+        let comic = match comics.comics.iter().find(|c| c.name == name) {
+            Some(c) => c,
+            None => return not_found(),
+        };
+        let path = match comic.source {
+            Source::Archive(ref p) => p.clone(),
+            Source::Directory { .. } => return not_found(),
+        };
+        match comic.pages().get(index) {
+            Some(p) => (path, p.name.clone(), comic.modified),
+            None => return not_found(),
+        }
+    };
+
+    let key = (path.clone(), page_name.clone());
+    let cached = cache.lock().unwrap().get(&key).cloned();
+    let content = match cached {
+        Some(c) => c,
+        None => match read_archive_page(&path, &page_name) {
+            Ok(c) => {
+                let c = Arc::new(c);
+                cache.lock().unwrap().put(key.clone(), c.clone());
+                c
+            }
+            Err(e) => {
+                error!("{e}");
+                return warp::reply::with_status("", StatusCode::INTERNAL_SERVER_ERROR)
+                    .into_response();
+            }
+        },
+    };
+
+    let mime = mime_guess::from_path(&page_name).first_or_octet_stream();
+    let discriminator = index.to_string();
+    let read = || Ok(content.clone());
+    if let Some(response) =
+        respond_converted(cache, key, headers, mime.as_ref(), modified, max_age, read)
+    {
+        return response;
+    }
+
+    let response = http::respond_bytes(
+        headers,
+        &content,
+        mime.as_ref(),
+        modified,
+        &discriminator,
+        max_age,
+    );
+    convert::with_vary(response, mime.as_ref())
+}
+
+/// Zips files in memory without compression since images are compressed already
+fn zip_files(files: &[PathBuf]) -> zip::result::ZipResult<Vec<u8>> {
+    use std::io::Write as _;
+
+    let mut writer = zip::ZipWriter::new(io::Cursor::new(vec![]));
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for file in files {
+        let name = match file.file_name() {
+            Some(n) => n.to_string_lossy(),
+            None => continue,
+        };
+        writer.start_file(name, options)?;
+        writer.write_all(&fs::read(file)?)?;
+    }
+    Ok(writer.finish()?.into_inner())
+}
+
+/// Streams file in chunks instead of reading it into memory
+async fn stream_file(path: &Path) -> io::Result<(u64, warp::hyper::Body)> {
+    use tokio::io::AsyncReadExt as _;
+
+    let mut file = tokio::fs::File::open(path).await?;
+    let len = file.metadata().await?.len();
+    let (mut sender, body) = warp::hyper::Body::channel();
+    let path = path.to_path_buf();
+    tokio::spawn(async move {
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            match file.read(&mut buffer).await {
+                Ok(0) => break,
+                Ok(n) => {
+                    let chunk = warp::hyper::body::Bytes::copy_from_slice(&buffer[..n]);
+                    // client is gone
+                    if sender.send_data(chunk).await.is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    error!("failed to read {path:?}: {e}");
+                    sender.abort();
+                    break;
+                }
+            }
+        }
+    });
+    Ok((len, body))
+}
+
+/// Original archive of comic, or pages in directory zipped
+async fn download(comics: Arc<Mutex<Comics>>, name: &str) -> warp::reply::Response {
+    let not_found = || warp::reply::with_status("not found", StatusCode::NOT_FOUND).into_response();
+    let internal_error = |e: &dyn std::fmt::Display| {
+        error!("failed to download {name}: {e}");
+        warp::reply::with_status("", StatusCode::INTERNAL_SERVER_ERROR).into_response()
+    };
+
+    let listed = {
+        let (comics, name) = (comics.clone(), name.to_string());
+        tokio::task::spawn_blocking(move || list_unlocked(&comics, &name)).await
+    };
+    match listed {
+        Ok(true) => {}
+        Ok(false) => return not_found(),
+        Err(e) => return internal_error(&e),
+    }
+    let source = {
+        let comics = comics.lock().unwrap();
+        match comics.comics.iter().find(|c| c.name == name) {
+            Some(comic) => match comic.source {
+                Source::Archive(ref path) => Ok(path.clone()),
+                Source::Directory { ref dir, .. } => Err(comic
+                    .pages()
+                    .iter()
+                    .filter_map(|p| Path::new(&p.name).file_name().map(|f| dir.join(f)))
+                    .collect::<Vec<PathBuf>>()),
+            },
+            None => return not_found(),
+        }
+    };
+
+    let (filename, len, body) = match source {
+        Ok(path) => {
+            let filename = match path.file_name() {
+                Some(f) => f.to_string_lossy().to_string(),
+                None => format!("{name}.zip"),
+            };
+            match stream_file(&path).await {
+                Ok((len, body)) => (filename, len, body),
+                Err(e) => return internal_error(&e),
+            }
+        }
+        Err(files) => match tokio::task::spawn_blocking(move || zip_files(&files)).await {
+            Ok(Ok(zipped)) => (
+                format!("{name}.zip"),
+                zipped.len() as u64,
+                warp::hyper::Body::from(zipped),
+            ),
+            Ok(Err(e)) => return internal_error(&e),
+            Err(e) => return internal_error(&e),
+        },
+    };
+
+    let disposition = format!(
+        "attachment; filename*=UTF-8''{}",
+        urlencoding::encode(&filename)
+    );
+    warp::http::Response::builder()
+        .header(CONTENT_TYPE, "application/zip")
+        .header(CONTENT_LENGTH, len)
+        .header(CONTENT_DISPOSITION, disposition)
+        .body(body)
+        .unwrap_or_else(|e| internal_error(&e))
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     pretty_env_logger::init();
 
     let opts = Arc::new(Opts::parse());
 
-    let opts_c = opts.clone();
-    let comics = Arc::new(Mutex::new(list_comics(&opts_c.data_dir)?));
+    if opts.validate {
+        let problems = validate::validate(&opts.data_dirs);
+        for problem in &problems {
+            println!("{problem}");
+        }
+        if !problems.is_empty() {
+            anyhow::bail!("{} problem(s) found in data directories", problems.len());
+        }
+        println!("no problem found in data directories");
+        return Ok(());
+    }
+
+    let comics = Arc::new(Mutex::new(list_comics(&opts.data_dirs)?));
+    scan_in_background(comics.clone());
+
+    let data_dirs = opts.data_dirs.clone();
+    let _watcher = match watch_data_dirs(data_dirs.clone(), comics.clone(), WATCH_DEBOUNCE) {
+        Ok(w) => Some(w),
+        Err(e) => {
+            warn!("failed to watch data directory, refresh manually: {e}");
+            None
+        }
+    };
+    if opts.rescan_interval > 0 {
+        let period = Duration::from_secs(opts.rescan_interval);
+        rescan_periodically(data_dirs.clone(), comics.clone(), period);
+    }
+
+    let cache: Arc<PageCache> = Arc::new(Mutex::new(LruCache::new(opts.archive_cache_size)));
+    #[cfg(unix)]
+    if let Err(e) = reload_on_hangup(data_dirs.clone(), comics.clone(), cache.clone()) {
+        warn!("failed to listen to SIGHUP: {e}");
+    }
+
     let comics_m = warp::any().map(move || comics.clone());
 
     let opts_c = opts.clone();
     let opts_m = warp::any().map(move || opts_c.clone());
 
+    let cache_m = warp::any().map(move || cache.clone());
+
+    let store = Arc::new(MarkStore::open(opts.marks_file.clone())?);
+    let store_m = warp::any().map(move || store.clone());
+
     let index_route = warp::path::end()
+        .and(warp::query::<IndexQuery>())
+        .and(opts_m.clone())
+        .and(comics_m.clone())
+        .and(store_m.clone())
+        .map(
+            |query: IndexQuery,
+             opts: Arc<Opts>,
+             comics: Arc<Mutex<Comics>>,
+             store: Arc<MarkStore>| {
+                let comics = comics.lock().unwrap();
+                let comics = comics.deref();
+                let q = query.q.as_deref().unwrap_or_default();
+                let filtered = filter_comics(sort_comics(&comics.comics, opts.sort), q);
+                let tpl = IndexTemplate {
+                    comics: mark_comics(
+                        filtered,
+                        &store.marks(),
+                        query.favorites,
+                        query.show_hidden,
+                    ),
+                    total: comics.comics.len(),
+                    q,
+                    favorites: query.favorites,
+                    show_hidden: query.show_hidden,
+                    updated: comics.updated.to_rfc3339(),
+                };
+                let html = match tpl.render() {
+                    Ok(s) => s,
+                    Err(e) => {
+                        error!("{e}");
+                        "failed to render template".to_string()
+                    }
+                };
+                warp::reply::html(html)
+            },
+        );
+
+    let stats_route = warp::path("stats")
+        .and(warp::path::end())
         .and(comics_m.clone())
         .map(|comics: Arc<Mutex<Comics>>| {
-            let comics = comics.lock().unwrap();
-            let comics = comics.deref();
-            let tpl = IndexTemplate {
-                comics: &comics.comics,
-                updated: comics.updated.to_rfc3339(),
-            };
-            let html = match tpl.render() {
+            let stats = Stats::new(&comics.lock().unwrap());
+            let html = match (StatsTemplate { stats: &stats }).render() {
                 Ok(s) => s,
                 Err(e) => {
                     error!("{e}");
@@ -190,70 +1139,247 @@ async fn main() -> anyhow::Result<()> {
             warp::reply::html(html)
         });
 
+    let stats_json_route = warp::path("stats.json")
+        .and(warp::path::end())
+        .and(comics_m.clone())
+        .map(|comics: Arc<Mutex<Comics>>| warp::reply::json(&Stats::new(&comics.lock().unwrap())));
+
     let refresh_route = warp::path("refresh")
         .and(opts_m.clone())
         .and(comics_m.clone())
         .map(|opts: Arc<Opts>, comics: Arc<Mutex<Comics>>| {
-            let mut comics = comics.lock().unwrap();
-            let new_comics = match list_comics(&opts.data_dir) {
-                Err(e) => {
-                    error!("{e}");
-                    return warp::redirect(Uri::from_static("/"));
-                }
-                Ok(cs) => cs,
-            };
-            *comics = new_comics;
+            reload_comics(&opts.data_dirs, &comics);
             warp::redirect(Uri::from_static("/"))
         });
 
-    let comic_route = warp::path!("comic" / String).and(comics_m.clone()).map(
-        |path: String, comics: Arc<Mutex<Comics>>| {
-            let comics = comics.lock().unwrap();
-            let path = match urlencoding::decode(path.as_str()) {
-                Err(e) => {
-                    error!("{e}");
-                    return warp::reply::with_status(
-                        warp::reply::html("".into()),
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                    );
-                }
-                Ok(p) => p,
-            };
-            let comic = match comics.comics.iter().find(|c| c.name == path) {
-                Some(comic) => comic,
-                None => {
-                    return warp::reply::with_status(
+    let comic_route = warp::path!("comic" / String)
+        .and(comics_m.clone())
+        .and(store_m.clone())
+        .then(
+            |path: String, comics: Arc<Mutex<Comics>>, store: Arc<MarkStore>| async move {
+                let path = match urlencoding::decode(path.as_str()) {
+                    Err(e) => {
+                        error!("{e}");
+                        return warp::reply::with_status(
+                            warp::reply::html("".into()),
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                        );
+                    }
+                    Ok(p) => p.into_owned(),
+                };
+                let rendered = tokio::task::spawn_blocking(move || {
+                    if !list_unlocked(&comics, &path) {
+                        return None;
+                    }
+                    let comics = comics.lock().unwrap();
+                    let comic = comics.comics.iter().find(|c| c.name == path)?;
+                    let marks = store.marks();
+                    let tpl = ComicTemplate {
+                        comic,
+                        favorite: marks.is_favorite(&comic.name),
+                        hidden: marks.is_hidden(&comic.name),
+                    };
+                    Some(tpl.render())
+                })
+                .await;
+                match rendered {
+                    Ok(Some(Ok(s))) => {
+                        warp::reply::with_status(warp::reply::html(s), StatusCode::OK)
+                    }
+                    Ok(Some(Err(e))) => {
+                        error!("{e}");
+                        warp::reply::with_status(
+                            warp::reply::html("".into()),
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                        )
+                    }
+                    Err(e) => {
+                        error!("{e}");
+                        warp::reply::with_status(
+                            warp::reply::html("".into()),
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                        )
+                    }
+                    Ok(None) => warp::reply::with_status(
                         warp::reply::html("not found".into()),
                         StatusCode::NOT_FOUND,
-                    )
+                    ),
                 }
-            };
-            let tpl = ComicTemplate { comic };
-            match tpl.render() {
-                Ok(s) => warp::reply::with_status(warp::reply::html(s), StatusCode::OK),
+            },
+        );
+
+    let mark_route = warp::path!("comic" / String / "mark")
+        .and(warp::post())
+        .and(warp::body::form())
+        .and(comics_m.clone())
+        .and(store_m.clone())
+        .map(
+            |name: String, form: MarkForm, comics: Arc<Mutex<Comics>>, store: Arc<MarkStore>| {
+                let name = match urlencoding::decode(&name) {
+                    Ok(n) => n,
+                    Err(e) => {
+                        error!("{e}");
+                        return warp::reply::with_status("", StatusCode::BAD_REQUEST)
+                            .into_response();
+                    }
+                };
+                if !comics.lock().unwrap().comics.iter().any(|c| c.name == name) {
+                    return warp::reply::with_status("not found", StatusCode::NOT_FOUND)
+                        .into_response();
+                }
+                form.reply(&store, &name)
+            },
+        );
+
+    let page_route =
+        warp::path!("comic" / String / "page" / usize)
+            .and(comics_m.clone())
+            .and(warp::cookie::optional::<String>(prefs::COOKIE_NAME))
+            .then(
+                |name: String,
+                 number: usize,
+                 comics: Arc<Mutex<Comics>>,
+                 cookie: Option<String>| async move {
+                    let name = match urlencoding::decode(&name) {
+                        Ok(n) => n.into_owned(),
+                        Err(e) => {
+                            error!("{e}");
+                            return warp::reply::with_status(
+                                warp::reply::html("".into()),
+                                StatusCode::BAD_REQUEST,
+                            );
+                        }
+                    };
+                    let prefs = Prefs::from_cookie(cookie.as_deref());
+                    let rendered = tokio::task::spawn_blocking(move || {
+                        if !list_unlocked(&comics, &name) {
+                            return None;
+                        }
+                        let comics = comics.lock().unwrap();
+                        let comic = comics.comics.iter().find(|c| c.name == name)?;
+                        render_page(comic, number, prefs)
+                    })
+                    .await;
+                    match rendered {
+                        Ok(Some(Ok(s))) => {
+                            warp::reply::with_status(warp::reply::html(s), StatusCode::OK)
+                        }
+                        Ok(Some(Err(e))) => {
+                            error!("{e}");
+                            warp::reply::with_status(
+                                warp::reply::html("".into()),
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                            )
+                        }
+                        Err(e) => {
+                            error!("{e}");
+                            warp::reply::with_status(
+                                warp::reply::html("".into()),
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                            )
+                        }
+                        Ok(None) => warp::reply::with_status(
+                            warp::reply::html("not found".into()),
+                            StatusCode::NOT_FOUND,
+                        ),
+                    }
+                },
+            );
+
+    let max_age = opts.max_age;
+    let archive_route = warp::path!("archive" / String / usize)
+        .and(comics_m.clone())
+        .and(cache_m.clone())
+        .and(warp::header::headers_cloned())
+        .then(
+            move |name: String,
+                  index: usize,
+                  comics: Arc<Mutex<Comics>>,
+                  cache: Arc<PageCache>,
+                  headers: HeaderMap| async move {
+                let name = match urlencoding::decode(&name) {
+                    Ok(n) => n,
+                    Err(e) => {
+                        error!("{e}");
+                        return warp::reply::with_status("", StatusCode::BAD_REQUEST)
+                            .into_response();
+                    }
+                };
+                let name = name.into_owned();
+                let page = tokio::task::spawn_blocking(move || {
+                    archive_page(&comics, &cache, &name, index, &headers, max_age)
+                });
+                match page.await {
+                    Ok(response) => response,
+                    Err(e) => {
+                        error!("{e}");
+                        warp::reply::with_status("", StatusCode::INTERNAL_SERVER_ERROR)
+                            .into_response()
+                    }
+                }
+            },
+        );
+
+    let prefs_route = warp::path("prefs")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::form())
+        .map(|form: PrefsForm| form.reply());
+
+    let download_route = warp::path!("download" / String).and(comics_m.clone()).then(
+        |name: String, comics: Arc<Mutex<Comics>>| async move {
+            match urlencoding::decode(&name) {
+                Ok(name) => download(comics, &name).await,
                 Err(e) => {
                     error!("{e}");
-                    warp::reply::with_status(
-                        warp::reply::html("".into()),
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                    )
+                    warp::reply::with_status("", StatusCode::BAD_REQUEST).into_response()
                 }
             }
         },
     );
 
-    let data_dir = opts.data_dir.clone();
-    let static_route = warp::path("static").and(warp::fs::dir(data_dir));
+    let mut static_route: Option<BoxedFilter<(warp::reply::Response,)>> = None;
+    for (index, data_dir) in data_dirs.into_iter().enumerate() {
+        let route = warp::path("static")
+            .and(warp::path(index.to_string()))
+            .and(cache_m.clone())
+            .and(warp::header::headers_cloned())
+            .and(warp::fs::dir(data_dir))
+            .map(
+                move |cache: Arc<PageCache>, headers: HeaderMap, file: warp::fs::File| {
+                    static_file(&cache, &headers, file, max_age)
+                },
+            )
+            .boxed();
+        static_route = Some(match static_route {
+            Some(r) => r.or(route).unify().boxed(),
+            None => route,
+        });
+    }
+    let static_route = match static_route {
+        Some(r) => r,
+        None => anyhow::bail!("no data directory specified"),
+    };
 
     let log = warp::log("comics::server");
     let router = index_route
         .or(comic_route)
+        .or(page_route)
         .or(static_route)
+        .or(archive_route)
+        .or(download_route)
         .or(refresh_route)
+        .or(prefs_route)
+        .or(mark_route)
+        .or(stats_route)
+        .or(stats_json_route)
         .with(log);
 
     let bind: SocketAddr = opts.bind.parse()?;
-    warp::serve(router).run(bind).await;
+    let (addr, server) =
+        warp::serve(router).try_bind_with_graceful_shutdown(bind, shutdown_signal())?;
+    info!("listen on {addr}");
+    server.await;
 
     Ok(())
 }
@@ -262,6 +1388,8 @@ async fn main() -> anyhow::Result<()> {
 mod tests {
     use super::*;
 
+    use warp::http::header::{CONTENT_TYPE, ETAG, IF_NONE_MATCH};
+
     fn join_path<T>(segments: &[T]) -> PathBuf
     where
         T: AsRef<Path>,
@@ -269,20 +1397,343 @@ mod tests {
         segments.iter().collect()
     }
 
+    fn cover(comic: &Comic) -> PathBuf {
+        PathBuf::from(&comic.pages().first().unwrap().name)
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("comics-{}-{name}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_archive(path: &Path, entries: &[&str]) {
+        use std::io::Write as _;
+
+        let file = fs::File::create(path).unwrap();
+        let mut zip = zip::ZipWriter::new(file);
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for entry in entries {
+            zip.start_file(*entry, options).unwrap();
+            zip.write_all(entry.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
     #[test]
     fn t_list_comics() {
-        let comics = list_comics("./data").unwrap();
+        let comics = list_comics(&["./data"]).unwrap();
 
         let comics = comics.comics;
         assert_eq!(3, comics.len());
 
-        let comic = comics.get(0).unwrap();
-        assert_eq!(join_path(&vec!["comic+01", "001.png"]), comic.cover);
+        let comic = comics.first().unwrap();
+        assert_eq!(join_path(&["comic+01", "001.png"]), cover(comic));
+        assert_eq!("/static/0/comic%2B01/001.png", comic.cover_src());
+        assert_eq!(95, comic.listing().size);
 
         let comic = comics.get(1).unwrap();
-        assert_eq!(join_path(&vec!["comic01", "001.png"]), comic.cover);
+        assert_eq!(join_path(&["comic01", "001.png"]), cover(comic));
+        assert_eq!(190, comic.listing().size);
 
         let comic = comics.get(2).unwrap();
-        assert_eq!(join_path(&vec!["comic02", "002.png"]), comic.cover);
+        assert_eq!(join_path(&["comic02", "002.png"]), cover(comic));
+    }
+
+    #[test]
+    fn t_render_page() {
+        let comics = list_comics(&["./data"]).unwrap();
+        let comic = comics.comics.get(1).unwrap();
+        assert_eq!("comic01", comic.name);
+
+        let html = render_page(comic, 1, Prefs::default()).unwrap().unwrap();
+        assert!(html.contains(r#"<img src="/static/0/comic01/001.png" />"#));
+        assert!(
+            html.contains(r#"<link rel="preload" as="image" href="/static/0/comic01/002.png">"#)
+        );
+        assert!(html.contains(r#"<a id="next" href="/comic/comic01/page/2">"#));
+        assert!(!html.contains(r#"id="prev""#));
+
+        let html = render_page(comic, 2, Prefs::default()).unwrap().unwrap();
+        assert!(html.contains(r#"<a id="prev" href="/comic/comic01/page/1">"#));
+        assert!(!html.contains(r#"id="next""#));
+
+        assert!(render_page(comic, 0, Prefs::default()).is_none());
+        assert!(render_page(comic, 3, Prefs::default()).is_none());
+
+        let prefs = Prefs {
+            fit: Fit::Height,
+            layout: Layout::Double,
+        };
+        let html = render_page(comic, 1, prefs).unwrap().unwrap();
+        assert!(html.contains(r#"<body class="fit-height double">"#));
+        assert!(html.contains(r#"<img src="/static/0/comic01/002.png" />"#));
+        assert!(html.contains(r#"<option value="fit-height" selected>"#));
+        assert!(!html.contains(r#"id="next""#));
+        let html = render_page(comic, 2, prefs).unwrap().unwrap();
+        assert!(html.contains(r#"<a id="prev" href="/comic/comic01/page/1">"#));
+    }
+
+    #[test]
+    fn t_filter_comics() {
+        let comics = list_comics(&["./data"]).unwrap();
+        let all = sort_comics(&comics.comics, SortBy::Name);
+
+        let names = |comics: Vec<&Comic>| comics.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+        assert_eq!(3, filter_comics(all.clone(), "").len());
+        assert_eq!(3, filter_comics(all.clone(), " ").len());
+        assert_eq!(
+            vec!["comic02"],
+            names(filter_comics(all.clone(), "COMIC02"))
+        );
+        assert_eq!(vec!["comic+01"], names(filter_comics(all.clone(), "+")));
+        assert!(filter_comics(all, "missing").is_empty());
+    }
+
+    #[test]
+    fn t_mark_comics() {
+        let comics = list_comics(&["./data"]).unwrap();
+        let all = sort_comics(&comics.comics, SortBy::Name);
+        let store = MarkStore::open(None).unwrap();
+        store.set(marks::Mark::Favorite, "comic01", true).unwrap();
+        store.set(marks::Mark::Favorite, "comic02", true).unwrap();
+        store.set(marks::Mark::Hidden, "comic02", true).unwrap();
+        let marks = store.marks();
+
+        let names = |entries: Vec<Entry<'_>>| {
+            entries
+                .iter()
+                .map(|e| e.comic.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec!["comic+01", "comic01"],
+            names(mark_comics(all.clone(), &marks, false, false))
+        );
+        assert_eq!(3, mark_comics(all.clone(), &marks, false, true).len());
+        assert_eq!(
+            vec!["comic01"],
+            names(mark_comics(all.clone(), &marks, true, false))
+        );
+        assert_eq!(
+            vec!["comic01", "comic02"],
+            names(mark_comics(all, &marks, true, true))
+        );
+    }
+
+    #[test]
+    fn t_natural_sort() {
+        let dir = temp_dir("natural");
+        for (comic, page) in [("comic10", "page1.png"), ("comic2", "page10.png")] {
+            fs::create_dir(dir.join(comic)).unwrap();
+            fs::write(dir.join(comic).join(page), b"").unwrap();
+        }
+        fs::write(dir.join("comic2").join("page2.png"), b"").unwrap();
+
+        let comics = list_comics(&[&dir]).unwrap();
+        // pages are listed on first access
+        comics.comics[0].pages();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let names = comics
+            .comics
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(vec!["comic2", "comic10"], names);
+
+        let pages = comics.comics[0]
+            .pages()
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect::<Vec<&str>>();
+        let expected = vec![
+            join_path(&["comic2", "page2.png"]),
+            join_path(&["comic2", "page10.png"]),
+        ];
+        assert_eq!(
+            expected,
+            pages.iter().map(PathBuf::from).collect::<Vec<PathBuf>>()
+        );
+    }
+
+    #[test]
+    fn t_sort_comics_by_mtime() {
+        let comic = |name: &str, secs: u64| Comic {
+            name: name.to_string(),
+            source: Source::Archive(PathBuf::new()),
+            modified: SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+            cover_src: String::new(),
+            listing: OnceCell::new(),
+        };
+        let comics = vec![comic("a", 1), comic("b", 3), comic("c", 2)];
+
+        let sorted = sort_comics(&comics, SortBy::Name);
+        let names = sorted
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(vec!["a", "b", "c"], names);
+
+        let sorted = sort_comics(&comics, SortBy::Mtime);
+        let names = sorted
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(vec!["b", "c", "a"], names);
+    }
+
+    #[tokio::test]
+    async fn t_watch_data_dirs() {
+        let dir = temp_dir("watch");
+        let comics = Arc::new(Mutex::new(list_comics(&[&dir]).unwrap()));
+        assert!(comics.lock().unwrap().comics.is_empty());
+
+        let debounce = Duration::from_millis(100);
+        let _watcher = watch_data_dirs(vec![dir.clone()], comics.clone(), debounce).unwrap();
+        fs::create_dir(dir.join("comic01")).unwrap();
+        fs::write(dir.join("comic01").join("001.png"), b"").unwrap();
+
+        let mut loaded = 0;
+        for _ in 0..50 {
+            tokio::time::sleep(debounce).await;
+            loaded = comics.lock().unwrap().comics.len();
+            if loaded > 0 {
+                break;
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(1, loaded);
+    }
+
+    #[test]
+    fn t_list_archives() {
+        let dir = temp_dir("archives");
+        write_archive(
+            &dir.join("archive01.cbz"),
+            &["10.png", "2.png", "1.png", "notes.txt"],
+        );
+        fs::write(dir.join("archive02.cbz"), b"not a zip").unwrap();
+        // RAR archive is not supported
+        fs::write(dir.join("archive03.cbr"), b"Rar!").unwrap();
+
+        let comics = Mutex::new(list_comics(&[&dir]).unwrap());
+        let listed = |comics: &Mutex<Comics>| {
+            let comics = comics.lock().unwrap();
+            comics
+                .comics
+                .iter()
+                .filter(|c| c.listed().is_some())
+                .count()
+        };
+        assert_eq!(0, listed(&comics));
+        let cache = Mutex::new(LruCache::new(NonZeroUsize::new(1).unwrap()));
+        let headers = HeaderMap::new();
+        let response = archive_page(&comics, &cache, "archive01", 1, &headers, 60);
+        let missing = archive_page(&comics, &cache, "archive01", 3, &headers, 60);
+        let mut conditional = HeaderMap::new();
+        conditional.insert(IF_NONE_MATCH, response.headers()[ETAG].clone());
+        let not_modified = archive_page(&comics, &cache, "archive01", 1, &conditional, 60);
+        assert_eq!(1, listed(&comics));
+        scan_pages(&comics);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let comics = comics.into_inner().unwrap().comics;
+        // broken archive turns out to have no page, and is left out of index
+        assert_eq!(2, comics.len());
+        assert!(comics[1].listed().unwrap().pages.is_empty());
+        let all = sort_comics(&comics, SortBy::Name);
+        assert_eq!(1, filter_comics(all, "").len());
+
+        let comic = comics.first().unwrap();
+        assert_eq!("archive01", comic.name);
+        assert_eq!(join_path(&["1.png"]), cover(comic));
+        let pages = comic
+            .pages()
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(vec!["1.png", "2.png", "10.png"], pages);
+        assert_eq!("/archive/archive01/0", comic.cover_src());
+
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("image/png", response.headers()[CONTENT_TYPE]);
+        assert_eq!(StatusCode::NOT_FOUND, missing.status());
+        assert_eq!(StatusCode::NOT_MODIFIED, not_modified.status());
+    }
+
+    #[test]
+    fn t_list_comics_in_data_dirs() {
+        let dirs = vec![temp_dir("data-dir-0"), temp_dir("data-dir-1")];
+        for dir in &dirs {
+            let comic = dir.join("comic01");
+            fs::create_dir_all(&comic).unwrap();
+            fs::write(comic.join("001.png"), b"").unwrap();
+        }
+        let comics = list_comics(&dirs);
+        for dir in &dirs {
+            fs::remove_dir_all(dir).unwrap();
+        }
+
+        let comics = comics.unwrap().comics;
+        let names = comics
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(vec!["comic01", "comic01 (2)"], names);
+        assert_eq!("/static/0/comic01/001.png", comics[0].cover_src());
+        assert_eq!("/static/1/comic01/001.png", comics[1].cover_src());
+    }
+
+    #[test]
+    fn t_unique_name() {
+        let mut names = HashSet::new();
+        assert_eq!("a", unique_name("a", &names));
+        names.insert("a".to_string());
+        assert_eq!("a (2)", unique_name("a", &names));
+        names.insert("a (2)".to_string());
+        assert_eq!("a (3)", unique_name("a", &names));
+    }
+
+    #[tokio::test]
+    async fn t_download() {
+        use warp::hyper::body::to_bytes;
+
+        let dir = temp_dir("download");
+        fs::create_dir_all(dir.join("comic01")).unwrap();
+        fs::write(dir.join("comic01").join("001.png"), b"1").unwrap();
+        fs::write(dir.join("comic01").join("002.png"), b"2").unwrap();
+        write_archive(&dir.join("archive01.cbz"), &["1.png"]);
+        let archive = fs::read(dir.join("archive01.cbz")).unwrap();
+        let comics = Arc::new(Mutex::new(list_comics(&[&dir]).unwrap()));
+
+        let response = download(comics.clone(), "comic01").await;
+        let disposition = response.headers()[CONTENT_DISPOSITION].clone();
+        let zipped = to_bytes(response.into_body()).await.unwrap();
+        let mut zip = zip::ZipArchive::new(io::Cursor::new(zipped.to_vec())).unwrap();
+        let names = zip
+            .file_names()
+            .map(String::from)
+            .collect::<HashSet<String>>();
+        let mut content = String::new();
+        zip.by_name("002.png")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+
+        let response = download(comics.clone(), "archive01").await;
+        let original = to_bytes(response.into_body()).await.unwrap();
+        let missing = download(comics, "comic02").await;
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!("attachment; filename*=UTF-8''comic01.zip", disposition);
+        assert_eq!(
+            HashSet::from(["001.png".to_string(), "002.png".to_string()]),
+            names
+        );
+        assert_eq!("2", content);
+        assert_eq!(archive, original.to_vec());
+        assert_eq!(StatusCode::NOT_FOUND, missing.status());
     }
 }
@@ -0,0 +1,163 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use warp::http::header::LOCATION;
+use warp::http::StatusCode;
+use warp::Reply as _;
+
+use crate::prefs::local_path;
+
+/// Mark of comic set by reader
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Mark {
+    /// Listed when favorites filter is on
+    Favorite,
+    /// Excluded from index unless hidden comics are shown
+    Hidden,
+}
+
+/// Names of marked comics
+#[derive(Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub(crate) struct Marks {
+    #[serde(default)]
+    favorites: BTreeSet<String>,
+    #[serde(default)]
+    hidden: BTreeSet<String>,
+}
+
+impl Marks {
+    /// Loads marks from file, returns no marks when file does not exist
+    fn load(path: &Path) -> io::Result<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+        serde_json::from_str(&content).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+
+    /// Saves marks to file atomically
+    fn save(&self, path: &Path) -> io::Result<()> {
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&temp, path)
+    }
+
+    fn set_of(&mut self, mark: Mark) -> &mut BTreeSet<String> {
+        match mark {
+            Mark::Favorite => &mut self.favorites,
+            Mark::Hidden => &mut self.hidden,
+        }
+    }
+
+    pub(crate) fn is_favorite(&self, name: &str) -> bool {
+        self.favorites.contains(name)
+    }
+
+    pub(crate) fn is_hidden(&self, name: &str) -> bool {
+        self.hidden.contains(name)
+    }
+
+    fn set(&mut self, mark: Mark, name: &str, on: bool) {
+        let set = self.set_of(mark);
+        if on {
+            set.insert(name.to_string());
+        } else {
+            set.remove(name);
+        }
+    }
+}
+
+/// Marks shared by routes, persisted to file if given
+#[derive(Debug)]
+pub(crate) struct MarkStore {
+    path: Option<PathBuf>,
+    marks: Mutex<Marks>,
+}
+
+impl MarkStore {
+    pub(crate) fn open(path: Option<PathBuf>) -> io::Result<Self> {
+        let marks = match path {
+            Some(ref path) => Marks::load(path)?,
+            None => Marks::default(),
+        };
+        Ok(MarkStore {
+            path,
+            marks: Mutex::new(marks),
+        })
+    }
+
+    pub(crate) fn marks(&self) -> std::sync::MutexGuard<'_, Marks> {
+        self.marks.lock().unwrap()
+    }
+
+    /// Sets or clears mark of comic, and saves marks to file
+    pub(crate) fn set(&self, mark: Mark, name: &str, on: bool) -> io::Result<()> {
+        let mut marks = self.marks();
+        marks.set(mark, name, on);
+        if let Some(ref path) = self.path {
+            marks.save(path)?;
+            debug!("marks saved to {path:?}");
+        }
+        Ok(())
+    }
+}
+
+/// Form posted to /comic/{name}/mark, back is path of page to return to
+#[derive(Debug, Deserialize)]
+pub(crate) struct MarkForm {
+    pub(crate) mark: Mark,
+    pub(crate) on: bool,
+    pub(crate) back: Option<String>,
+}
+
+impl MarkForm {
+    /// Sets mark of comic and redirects back
+    pub(crate) fn reply(&self, store: &MarkStore, name: &str) -> warp::reply::Response {
+        if let Err(e) = store.set(self.mark, name, self.on) {
+            error!("failed to save marks: {e}");
+            return warp::reply::with_status("", StatusCode::INTERNAL_SERVER_ERROR).into_response();
+        }
+        let reply = warp::reply::with_status(warp::reply(), StatusCode::SEE_OTHER);
+        warp::reply::with_header(reply, LOCATION, local_path(self.back.as_deref())).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_mark_store() {
+        let path = std::env::temp_dir().join(format!("comics-{}-marks.json", std::process::id()));
+        let store = MarkStore::open(Some(path.clone())).unwrap();
+        store.set(Mark::Favorite, "a", true).unwrap();
+        store.set(Mark::Hidden, "b", true).unwrap();
+        store.set(Mark::Hidden, "c", true).unwrap();
+        store.set(Mark::Hidden, "c", false).unwrap();
+
+        let reopened = MarkStore::open(Some(path.clone())).unwrap();
+        fs::remove_file(&path).unwrap();
+        let marks = reopened.marks();
+        assert!(marks.is_favorite("a"));
+        assert!(!marks.is_favorite("b"));
+        assert!(marks.is_hidden("b"));
+        assert!(!marks.is_hidden("c"));
+
+        let form = MarkForm {
+            mark: Mark::Favorite,
+            on: false,
+            back: Some("//x.com".into()),
+        };
+        let store = MarkStore::open(None).unwrap();
+        let response = form.reply(&store, "a");
+        assert_eq!(StatusCode::SEE_OTHER, response.status());
+        assert_eq!("/", response.headers()[LOCATION]);
+    }
+}
@@ -0,0 +1,78 @@
+//! Pages of PDF rendered to PNG with `pdfinfo` and `pdftoppm` of poppler,
+//! enabled with `pdf` feature. Each page is rendered on request and cached like archive entries.
+
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// Resolution of rendered pages in DPI
+const RESOLUTION: &str = "150";
+
+fn run(command: &mut Command) -> io::Result<Vec<u8>> {
+    let output = command.output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            stderr.trim().to_string(),
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Name of page, which tells content type and page number
+fn page_name(number: usize) -> String {
+    format!("page-{number:04}.png")
+}
+
+fn page_number(name: &str) -> Option<usize> {
+    name.strip_prefix("page-")?
+        .strip_suffix(".png")?
+        .parse()
+        .ok()
+}
+
+/// Number of pages told by `pdfinfo` e.g. "Pages:          12"
+fn parse_pages(info: &str) -> Option<usize> {
+    info.lines()
+        .find_map(|l| l.strip_prefix("Pages:"))
+        .and_then(|p| p.trim().parse().ok())
+}
+
+/// Names of pages of PDF in order
+pub(crate) fn page_names(path: &Path) -> io::Result<Vec<String>> {
+    let info = run(Command::new("pdfinfo").arg(path))?;
+    let pages = parse_pages(&String::from_utf8_lossy(&info))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "number of pages not found"))?;
+    Ok((1..=pages).map(page_name).collect())
+}
+
+/// Renders page of PDF to PNG
+pub(crate) fn render_page(path: &Path, name: &str) -> io::Result<Vec<u8>> {
+    let number = match page_number(name) {
+        Some(n) => n.to_string(),
+        None => return Err(io::Error::new(io::ErrorKind::NotFound, name.to_string())),
+    };
+    // PNG is written to standard output without root of output file
+    run(Command::new("pdftoppm")
+        .args(["-png", "-singlefile", "-r", RESOLUTION])
+        .args(["-f", number.as_str(), "-l", number.as_str()])
+        .arg(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_page_number() {
+        assert_eq!("page-0012.png", page_name(12));
+        assert_eq!(Some(12), page_number(&page_name(12)));
+        assert_eq!(None, page_number("cover.png"));
+        assert_eq!(
+            Some(3),
+            parse_pages("Title:          x\nPages:          3\n")
+        );
+        assert_eq!(None, parse_pages("Title:          x\n"));
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use warp::http::header::{LOCATION, SET_COOKIE};
+use warp::http::StatusCode;
+use warp::Reply as _;
+
+/// Name of cookie keeping display preferences
+pub(crate) const COOKIE_NAME: &str = "prefs";
+
+/// Cookie outlives browser sessions for a year
+const COOKIE_MAX_AGE: u64 = 365 * 24 * 60 * 60;
+
+/// How page fits in reader
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Fit {
+    /// Shrink page to width of window
+    #[default]
+    #[serde(rename = "fit-width")]
+    Width,
+    /// Shrink page to height of window
+    #[serde(rename = "fit-height")]
+    Height,
+    /// Page in its original size
+    Original,
+}
+
+/// Pages shown at once in reader
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Layout {
+    /// One page
+    #[default]
+    Single,
+    /// Two pages side by side
+    Double,
+}
+
+impl fmt::Display for Fit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Fit::Width => "fit-width",
+            Fit::Height => "fit-height",
+            Fit::Original => "original",
+        })
+    }
+}
+
+impl FromStr for Fit {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fit-width" => Ok(Fit::Width),
+            "fit-height" => Ok(Fit::Height),
+            "original" => Ok(Fit::Original),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Layout::Single => "single",
+            Layout::Double => "double",
+        })
+    }
+}
+
+impl FromStr for Layout {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "single" => Ok(Layout::Single),
+            "double" => Ok(Layout::Double),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Display preferences of client, kept in cookie so reader remembers them
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+pub(crate) struct Prefs {
+    #[serde(default)]
+    pub(crate) fit: Fit,
+    #[serde(default)]
+    pub(crate) layout: Layout,
+}
+
+impl Prefs {
+    /// Parses cookie value e.g. "fit-height.double", unknown or missing values fall back to defaults
+    pub(crate) fn from_cookie(value: Option<&str>) -> Self {
+        let mut prefs = Prefs::default();
+        for part in value.unwrap_or_default().split('.') {
+            if let Ok(fit) = part.parse() {
+                prefs.fit = fit;
+            } else if let Ok(layout) = part.parse() {
+                prefs.layout = layout;
+            }
+        }
+        prefs
+    }
+
+    /// Value of Set-Cookie header
+    pub(crate) fn to_cookie(self) -> String {
+        format!(
+            "{COOKIE_NAME}={}.{}; Path=/; Max-Age={COOKIE_MAX_AGE}; SameSite=Lax",
+            self.fit, self.layout
+        )
+    }
+
+    /// Pages advanced by prev and next links
+    pub(crate) fn step(&self) -> usize {
+        match self.layout {
+            Layout::Single => 1,
+            Layout::Double => 2,
+        }
+    }
+}
+
+/// Form posted to /prefs, back is path of page to return to
+#[derive(Debug, Deserialize)]
+pub(crate) struct PrefsForm {
+    #[serde(flatten)]
+    pub(crate) prefs: Prefs,
+    pub(crate) back: Option<String>,
+}
+
+/// Local path to redirect to, anything else e.g. "//x.com" goes back to index
+pub(crate) fn local_path(back: Option<&str>) -> &str {
+    match back {
+        Some(b) if b.starts_with('/') && !b.starts_with("//") && !b.contains('\\') => b,
+        _ => "/",
+    }
+}
+
+impl PrefsForm {
+    /// Local path to redirect to, see [`local_path`]
+    pub(crate) fn back(&self) -> &str {
+        local_path(self.back.as_deref())
+    }
+
+    /// Saves preferences in cookie and redirects back
+    pub(crate) fn reply(&self) -> warp::reply::Response {
+        let reply = warp::reply::with_status(warp::reply(), StatusCode::SEE_OTHER);
+        let reply = warp::reply::with_header(reply, LOCATION, self.back());
+        warp::reply::with_header(reply, SET_COOKIE, self.prefs.to_cookie()).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_prefs() {
+        assert_eq!(Prefs::default(), Prefs::from_cookie(None));
+        assert_eq!(Prefs::default(), Prefs::from_cookie(Some("unknown")));
+
+        let prefs = Prefs {
+            fit: Fit::Height,
+            layout: Layout::Double,
+        };
+        let cookie = prefs.to_cookie();
+        assert!(cookie.starts_with("prefs=fit-height.double; Path=/;"));
+        let value = cookie
+            .split(';')
+            .next()
+            .unwrap()
+            .trim_start_matches("prefs=");
+        assert_eq!(prefs, Prefs::from_cookie(Some(value)));
+        assert_eq!(2, prefs.step());
+
+        let form = |back: Option<&str>| PrefsForm {
+            prefs,
+            back: back.map(String::from),
+        };
+        assert_eq!("/comic/a/page/2", form(Some("/comic/a/page/2")).back());
+        assert_eq!("/", form(Some("//x.com")).back());
+        assert_eq!("/", form(Some("https://x.com")).back());
+        assert_eq!("/", form(None).back());
+
+        let response = form(Some("/comic/a/page/2")).reply();
+        assert_eq!(StatusCode::SEE_OTHER, response.status());
+        assert_eq!("/comic/a/page/2", response.headers()[LOCATION]);
+        assert_eq!(prefs.to_cookie(), response.headers()[SET_COOKIE]);
+    }
+
+    #[tokio::test]
+    async fn t_prefs_form() {
+        let form = warp::test::request()
+            .method("POST")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body("fit=original&layout=double&back=%2Fcomic%2Fa%2Fpage%2F3")
+            .filter(&warp::body::form::<PrefsForm>())
+            .await
+            .unwrap();
+        assert_eq!(Fit::Original, form.prefs.fit);
+        assert_eq!(Layout::Double, form.prefs.layout);
+        assert_eq!("/comic/a/page/3", form.back());
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::SystemTime;
+
+use serde::Serialize;
+
+use crate::{sort_comics, Comics, SortBy};
+
+/// Number of recently added comics listed in statistics
+const RECENT_LIMIT: usize = 10;
+
+/// Pages and size of comic, known once pages are listed
+#[derive(Debug, Serialize)]
+pub(crate) struct ListingStats {
+    pub(crate) pages: usize,
+    pub(crate) size: u64,
+}
+
+/// Summary of comic in statistics
+#[derive(Debug, Serialize)]
+pub(crate) struct ComicStats {
+    pub(crate) name: String,
+    #[serde(flatten)]
+    pub(crate) listing: Option<ListingStats>,
+    pub(crate) modified: String,
+}
+
+/// Statistics of library, served as HTML on /stats and JSON on /stats.json
+#[derive(Debug, Serialize)]
+pub(crate) struct Stats {
+    pub(crate) comics: usize,
+    /// Number of comics whose pages are listed, pages and size only count these comics
+    pub(crate) listed: usize,
+    pub(crate) pages: usize,
+    /// Total size of comics in bytes
+    pub(crate) size: u64,
+    pub(crate) updated: String,
+    /// Comics modified most recently, newest first
+    pub(crate) recent: Vec<ComicStats>,
+}
+
+fn rfc3339(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Local>::from(time).to_rfc3339()
+}
+
+impl Stats {
+    /// Only counts pages listed already, so comics are not listed while lock is held
+    pub(crate) fn new(comics: &Comics) -> Self {
+        let recent = sort_comics(&comics.comics, SortBy::Mtime)
+            .into_iter()
+            .take(RECENT_LIMIT)
+            .map(|c| ComicStats {
+                name: c.name.clone(),
+                listing: c.listed().map(|l| ListingStats {
+                    pages: l.pages.len(),
+                    size: l.size,
+                }),
+                modified: rfc3339(c.modified),
+            })
+            .collect();
+        let listed = comics.comics.iter().filter_map(|c| c.listed());
+        Stats {
+            comics: comics.comics.len(),
+            listed: listed.clone().count(),
+            pages: listed.clone().map(|l| l.pages.len()).sum(),
+            size: listed.map(|l| l.size).sum(),
+            updated: comics.updated.to_rfc3339(),
+            recent,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::list_comics;
+
+    #[test]
+    fn t_stats() {
+        let comics = list_comics(&["./data"]).unwrap();
+        let stats = Stats::new(&comics);
+        assert_eq!(3, stats.comics);
+        assert_eq!(0, stats.listed);
+        assert_eq!(0, stats.pages);
+        assert!(stats.recent.iter().all(|c| c.listing.is_none()));
+
+        for comic in &comics.comics {
+            comic.listing();
+        }
+        let stats = Stats::new(&comics);
+        assert_eq!(3, stats.listed);
+        assert_eq!(5, stats.pages);
+        assert_eq!(475, stats.size);
+        assert_eq!(3, stats.recent.len());
+        assert!(stats
+            .recent
+            .iter()
+            .any(|c| c.name == "comic01" && c.listing.as_ref().unwrap().pages == 2));
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{has_extension, list_archive_entries, ARCHIVE_EXTENSIONS, IMAGE_EXTENSIONS};
+
+/// Problem found in data directories
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum Problem {
+    /// Directory or archive without any page
+    Empty(PathBuf),
+    /// File or directory could not be read
+    Unreadable(PathBuf, String),
+    /// File is neither archive of comic nor image of page
+    Unsupported(PathBuf),
+    /// Comics sharing same name, renamed with number when served
+    Duplicate(String, Vec<PathBuf>),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Empty(path) => write!(f, "empty: {}", path.display()),
+            Problem::Unreadable(path, error) => {
+                write!(f, "unreadable: {}: {error}", path.display())
+            }
+            Problem::Unsupported(path) => write!(f, "unsupported: {}", path.display()),
+            Problem::Duplicate(name, paths) => {
+                let paths = paths
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<String>>();
+                write!(f, "duplicate name {name}: {}", paths.join(", "))
+            }
+        }
+    }
+}
+
+/// Checks pages in comic directory
+fn validate_directory(dir: &Path, problems: &mut Vec<Problem>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(e) => return problems.push(Problem::Unreadable(dir.to_path_buf(), e.to_string())),
+    };
+    let mut pages = 0;
+    for entry in entries {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                problems.push(Problem::Unreadable(dir.to_path_buf(), e.to_string()));
+                continue;
+            }
+        };
+        let path = entry.path();
+        match entry.metadata() {
+            Ok(m) if m.is_file() => {}
+            // nested directories are not loaded as comics nor pages
+            Ok(_) => continue,
+            Err(e) => {
+                problems.push(Problem::Unreadable(path, e.to_string()));
+                continue;
+            }
+        }
+        if let Err(e) = fs::File::open(&path) {
+            problems.push(Problem::Unreadable(path, e.to_string()));
+            continue;
+        }
+        if !has_extension(&path, &IMAGE_EXTENSIONS) {
+            problems.push(Problem::Unsupported(path));
+            continue;
+        }
+        pages += 1;
+    }
+    if pages == 0 {
+        problems.push(Problem::Empty(dir.to_path_buf()));
+    }
+}
+
+fn validate_archive(path: &Path, problems: &mut Vec<Problem>) {
+    match list_archive_entries(path) {
+        Ok(entries) if entries.is_empty() => problems.push(Problem::Empty(path.to_path_buf())),
+        Ok(_) => {}
+        Err(e) => problems.push(Problem::Unreadable(path.to_path_buf(), e.to_string())),
+    }
+}
+
+/// Scans data directories without loading library, and reports problems in order of paths
+pub(crate) fn validate<T>(data_dirs: &[T]) -> Vec<Problem>
+where
+    T: AsRef<Path>,
+{
+    let mut problems = vec![];
+    let mut names: Vec<(String, Vec<PathBuf>)> = vec![];
+    let mut indices: HashMap<String, usize> = HashMap::new();
+
+    for data_dir in data_dirs {
+        let data_dir = data_dir.as_ref();
+        let entries = match fs::read_dir(data_dir) {
+            Ok(e) => e,
+            Err(e) => {
+                problems.push(Problem::Unreadable(data_dir.to_path_buf(), e.to_string()));
+                continue;
+            }
+        };
+        let mut paths = vec![];
+        for entry in entries {
+            match entry {
+                Ok(e) => paths.push(e.path()),
+                Err(e) => problems.push(Problem::Unreadable(data_dir.to_path_buf(), e.to_string())),
+            }
+        }
+        paths.sort_by(|a, b| natord::compare(&a.to_string_lossy(), &b.to_string_lossy()));
+
+        for path in paths {
+            let metadata = match fs::metadata(&path) {
+                Ok(m) => m,
+                Err(e) => {
+                    problems.push(Problem::Unreadable(path, e.to_string()));
+                    continue;
+                }
+            };
+            let name = if metadata.is_dir() {
+                validate_directory(&path, &mut problems);
+                path.file_name()
+            } else if has_extension(&path, &ARCHIVE_EXTENSIONS) {
+                validate_archive(&path, &mut problems);
+                path.file_stem()
+            } else {
+                problems.push(Problem::Unsupported(path));
+                continue;
+            };
+            let name = match name {
+                Some(n) => n.to_string_lossy().to_string(),
+                None => continue,
+            };
+            match indices.get(&name) {
+                Some(&i) => names[i].1.push(path),
+                None => {
+                    indices.insert(name.clone(), names.len());
+                    names.push((name, vec![path]));
+                }
+            }
+        }
+    }
+
+    for (name, paths) in names {
+        if paths.len() > 1 {
+            problems.push(Problem::Duplicate(name, paths));
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_validate() {
+        assert!(validate(&["./data"]).is_empty());
+
+        let dirs = vec![
+            std::env::temp_dir().join(format!("comics-{}-validate-0", std::process::id())),
+            std::env::temp_dir().join(format!("comics-{}-validate-1", std::process::id())),
+        ];
+        for dir in &dirs {
+            fs::create_dir_all(dir.join("comic01")).unwrap();
+            fs::write(dir.join("comic01").join("001.png"), b"").unwrap();
+        }
+        let dir = &dirs[0];
+        fs::create_dir(dir.join("empty")).unwrap();
+        fs::write(dir.join("comic01").join("notes.txt"), b"").unwrap();
+        fs::write(dir.join("readme.md"), b"").unwrap();
+        fs::write(dir.join("broken.cbz"), b"not a zip").unwrap();
+        fs::write(dir.join("rar.cbr"), b"Rar!").unwrap();
+
+        let problems = validate(&dirs);
+        for dir in &dirs {
+            fs::remove_dir_all(dir).unwrap();
+        }
+
+        assert_eq!(6, problems.len(), "{problems:?}");
+        assert!(matches!(&problems[0], Problem::Unreadable(p, _) if p == &dir.join("broken.cbz")));
+        assert_eq!(
+            vec![
+                Problem::Unsupported(dir.join("comic01").join("notes.txt")),
+                Problem::Empty(dir.join("empty")),
+                Problem::Unsupported(dir.join("rar.cbr")),
+                Problem::Unsupported(dir.join("readme.md")),
+                Problem::Duplicate(
+                    "comic01".to_string(),
+                    vec![dirs[0].join("comic01"), dirs[1].join("comic01")]
+                ),
+            ],
+            problems[1..]
+        );
+    }
+}
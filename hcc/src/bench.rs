@@ -0,0 +1,171 @@
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use hcc::{CheckedInner, Checker};
+use log::debug;
+
+/// Result of checking domain names repeatedly
+#[derive(Debug)]
+pub(crate) struct BenchReport {
+    checks: usize,
+    failures: usize,
+    concurrency: usize,
+    elapsed: Duration,
+    /// Latency of each check, sorted ascending
+    latencies: Vec<Duration>,
+}
+
+/// Latency below which given percent of checks complete, nearest-rank method
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (sorted.len() * percent + 99) / 100;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+impl BenchReport {
+    fn new(
+        mut latencies: Vec<Duration>,
+        failures: usize,
+        concurrency: usize,
+        elapsed: Duration,
+    ) -> Self {
+        latencies.sort();
+        BenchReport {
+            checks: latencies.len(),
+            failures,
+            concurrency,
+            elapsed,
+            latencies,
+        }
+    }
+
+    /// Checks completed per second
+    fn throughput(&self) -> f64 {
+        match self.elapsed.as_secs_f64() {
+            secs if secs > 0.0 => self.checks as f64 / secs,
+            _ => 0.0,
+        }
+    }
+}
+
+impl Display for BenchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let latency = |percent| percentile(&self.latencies, percent);
+        writeln!(
+            f,
+            "{} check(s), {} failed, concurrency {}, in {:?}",
+            self.checks, self.failures, self.concurrency, self.elapsed
+        )?;
+        writeln!(f, "throughput: {:.2} check(s)/s", self.throughput())?;
+        writeln!(
+            f,
+            "latency: min {:?}, p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+            self.latencies.first().copied().unwrap_or_default(),
+            latency(50),
+            latency(90),
+            latency(99),
+            self.latencies.last().copied().unwrap_or_default(),
+        )
+    }
+}
+
+/// Reads domain names from file, one per line, blank lines and lines starting with # are skipped
+pub(crate) fn read_domain_names(path: &Path) -> anyhow::Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+/// Checks domain names repeated given times, at most `concurrency` checks at once
+pub(crate) async fn bench(
+    checker: Checker,
+    domain_names: &[String],
+    repeat: usize,
+    concurrency: usize,
+) -> anyhow::Result<BenchReport> {
+    use futures::StreamExt as _;
+
+    let checker = Arc::new(checker);
+    let concurrency = concurrency.max(1);
+    let targets = domain_names
+        .iter()
+        .cycle()
+        .take(domain_names.len() * repeat)
+        .cloned()
+        .collect::<Vec<String>>();
+    let start = Instant::now();
+    let mut checks = futures::stream::iter(targets)
+        .map(|domain_name| {
+            let checker = checker.clone();
+            // checks block on network I/O, so they run on blocking threads to be concurrent
+            tokio::task::spawn_blocking(move || {
+                let start = Instant::now();
+                let checked = futures::executor::block_on(checker.check_one(domain_name.as_str()));
+                let failed = matches!(checked.inner, CheckedInner::Error { .. });
+                debug!("{domain_name} checked in {:?}", start.elapsed());
+                (start.elapsed(), failed)
+            })
+        })
+        .buffer_unordered(concurrency);
+
+    let mut latencies = vec![];
+    let mut failures = 0;
+    while let Some(check) = checks.next().await {
+        let (latency, failed) = check?;
+        latencies.push(latency);
+        failures += failed as usize;
+    }
+    Ok(BenchReport::new(
+        latencies,
+        failures,
+        concurrency,
+        start.elapsed(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_percentile() {
+        let ms = Duration::from_millis;
+        let sorted = (1..=100).map(ms).collect::<Vec<_>>();
+        assert_eq!(ms(50), percentile(&sorted, 50));
+        assert_eq!(ms(99), percentile(&sorted, 99));
+        assert_eq!(ms(100), percentile(&sorted, 100));
+        assert_eq!(ms(1), percentile(&sorted, 0));
+        assert_eq!(ms(3), percentile(&[ms(1), ms(2), ms(3)], 90));
+        assert_eq!(Duration::ZERO, percentile(&[], 50));
+
+        let report = BenchReport::new(
+            vec![ms(300), ms(100), ms(200)],
+            1,
+            2,
+            Duration::from_secs(2),
+        );
+        assert_eq!(1.5, report.throughput());
+        let text = report.to_string();
+        assert!(text.starts_with("3 check(s), 1 failed, concurrency 2, in 2s\n"));
+        assert!(text.contains("throughput: 1.50 check(s)/s"));
+        assert!(text.contains("min 100ms, p50 200ms, p90 300ms, p99 300ms, max 300ms"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn t_bench() {
+        // invalid domain names fail without network, measuring harness itself
+        let names = vec!["invalid domain".to_string(), "also invalid".to_string()];
+        let report = bench(Checker::default(), &names, 3, 4).await.unwrap();
+        assert_eq!(6, report.checks);
+        assert_eq!(6, report.failures);
+        assert_eq!(4, report.concurrency);
+    }
+}
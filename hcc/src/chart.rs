@@ -0,0 +1,243 @@
+use hcc::CheckedInner;
+
+use crate::sink::Output;
+use crate::State;
+
+/// Pixels of one dot of font
+const SCALE: usize = 2;
+/// Width of glyph including spacing, in dots
+const GLYPH_WIDTH: usize = 4;
+/// Height of row including spacing, in dots
+const ROW_HEIGHT: usize = 7;
+/// Padding around chart in pixels
+const MARGIN: usize = 8;
+/// Width of bar of certificate with [`MAX_DAYS`] or more remaining, in pixels
+const BAR_WIDTH: usize = 180;
+/// Days remaining filling the whole bar
+const MAX_DAYS: i64 = 90;
+/// Characters of domain name shown, longer ones are truncated
+const MAX_LABEL: usize = 32;
+/// Characters of days column e.g. "-12d" or "err"
+const DAYS_LABEL: usize = 5;
+
+type Rgb = [u8; 3];
+
+const BACKGROUND: Rgb = [255, 255, 255];
+const TEXT: Rgb = [36, 41, 47];
+const TRACK: Rgb = [234, 238, 242];
+const GREEN: Rgb = [46, 160, 67];
+const AMBER: Rgb = [219, 154, 4];
+const RED: Rgb = [207, 34, 46];
+
+/// Rows of 3x5 glyph, highest of three bits is leftmost dot
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_lowercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'a' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'b' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'c' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'd' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'e' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'f' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'g' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'h' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'i' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'j' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'k' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'l' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'm' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'n' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'o' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'p' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'r' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        's' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        't' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'u' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'v' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'w' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'x' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '*' => [0b000, 0b101, 0b010, 0b101, 0b000],
+        ' ' => [0b000; 5],
+        _ => [0b111, 0b001, 0b010, 0b000, 0b010], // question mark
+    }
+}
+
+/// RGB pixels of chart
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: BACKGROUND.repeat(width * height),
+        }
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb) {
+        for row in y..(y + height).min(self.height) {
+            for col in x..(x + width).min(self.width) {
+                let i = (row * self.width + col) * 3;
+                self.pixels[i..i + 3].copy_from_slice(&color);
+            }
+        }
+    }
+
+    fn text(&mut self, x: usize, y: usize, text: &str, color: Rgb) {
+        for (i, c) in text.chars().enumerate() {
+            let left = x + i * GLYPH_WIDTH * SCALE;
+            for (dy, bits) in glyph(c).iter().enumerate() {
+                for dx in 0..3 {
+                    if bits & (0b100 >> dx) != 0 {
+                        let (px, py) = (left + dx * SCALE, y + dy * SCALE);
+                        self.fill(px, py, SCALE, SCALE, color);
+                    }
+                }
+            }
+        }
+    }
+
+    fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        let mut png = vec![];
+        let mut encoder = png::Encoder::new(&mut png, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+        Ok(png)
+    }
+}
+
+/// Days remaining of certificate, None if check failed
+fn days_remaining(output: &Output<'_>) -> Option<i64> {
+    match &output.checked.inner {
+        CheckedInner::Ok { not_after, .. } | CheckedInner::HostnameMismatch { not_after, .. } => {
+            Some((*not_after - output.checked.checked_at).num_days())
+        }
+        CheckedInner::Error { .. } => None,
+    }
+}
+
+/// Renders PNG of days remaining per domain name as bars, soonest expiry first and failures last
+pub(crate) fn render(outputs: &[Output<'_>]) -> anyhow::Result<Vec<u8>> {
+    let mut rows = outputs
+        .iter()
+        .map(|o| (o, days_remaining(o)))
+        .collect::<Vec<_>>();
+    rows.sort_by_key(|(_, days)| days.unwrap_or(i64::MAX));
+
+    let label_chars = rows
+        .iter()
+        .map(|(o, _)| o.checked.domain_name.chars().count().min(MAX_LABEL))
+        .max()
+        .unwrap_or_default();
+    let label_width = label_chars * GLYPH_WIDTH * SCALE;
+    let row_height = ROW_HEIGHT * SCALE;
+    let width = MARGIN * 4 + label_width + BAR_WIDTH + DAYS_LABEL * GLYPH_WIDTH * SCALE;
+    let height = MARGIN * 2 + rows.len().max(1) * row_height;
+
+    let mut canvas = Canvas::new(width, height);
+    for (i, (output, days)) in rows.iter().enumerate() {
+        let y = MARGIN + i * row_height;
+        let label = output
+            .checked
+            .domain_name
+            .chars()
+            .take(MAX_LABEL)
+            .collect::<String>();
+        canvas.text(MARGIN, y, &label, TEXT);
+
+        let color = match (days, output.state) {
+            (None, _) | (_, State::Expired) => RED,
+            (_, State::Warning) => AMBER,
+            (_, State::Valid) => GREEN,
+        };
+        let x = MARGIN * 2 + label_width;
+        let bar_height = 5 * SCALE;
+        canvas.fill(x, y, BAR_WIDTH, bar_height, TRACK);
+        let filled = days.unwrap_or_default().clamp(0, MAX_DAYS) as usize * BAR_WIDTH;
+        canvas.fill(x, y, filled / MAX_DAYS as usize, bar_height, color);
+
+        let days = match days {
+            Some(d) => format!("{d}d"),
+            None => "err".to_string(),
+        };
+        canvas.text(x + BAR_WIDTH + MARGIN, y, &days, color);
+    }
+    canvas.encode()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::Utc;
+    use hcc::Checked;
+
+    use crate::test::ok_checked;
+
+    #[test]
+    fn t_render() {
+        let now = Utc::now();
+        let ok = |domain_name, days| {
+            ok_checked(
+                domain_name,
+                now,
+                now + chrono::Duration::days(days) + chrono::Duration::hours(1),
+                None,
+            )
+        };
+        let a = ok("a.com", 30);
+        let b = ok("b.com", 3);
+        let c = Checked {
+            checked_at: now,
+            domain_name: "c.com".into(),
+            address: None,
+            inner: CheckedInner::Error {
+                error: anyhow::anyhow!("connection refused"),
+            },
+        };
+        let output = |checked, state| Output {
+            checked,
+            message: String::new(),
+            state,
+            notify: true,
+        };
+        let outputs = vec![
+            output(&a, State::Valid),
+            output(&b, State::Warning),
+            output(&c, State::Expired),
+        ];
+        assert_eq!(Some(30), days_remaining(&outputs[0]));
+        assert_eq!(None, days_remaining(&outputs[2]));
+
+        let png = render(&outputs).unwrap();
+        let decoder = png::Decoder::new(png.as_slice());
+        let reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        let label_width = 5 * GLYPH_WIDTH * SCALE;
+        let days_width = DAYS_LABEL * GLYPH_WIDTH * SCALE;
+        assert_eq!(
+            (MARGIN * 4 + label_width + BAR_WIDTH + days_width) as u32,
+            info.width
+        );
+        assert_eq!((MARGIN * 2 + 3 * ROW_HEIGHT * SCALE) as u32, info.height);
+    }
+}
@@ -1,32 +1,201 @@
 use std::borrow::Cow;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
 use std::time::Duration;
 
 use chrono::{DateTime, Utc};
+use serde::{Serialize, Serializer};
+
+fn serialize_millis<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_u128(duration.as_millis())
+}
+
+fn serialize_error<S>(error: &anyhow::Error, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_str(error)
+}
+
+/// TLS protocol version negotiated with endpoint, ordered from oldest.
+/// TLS 1.0/1.1 are never negotiated, endpoints only offering them fail to handshake
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum TlsVersion {
+    /// TLS 1.2
+    #[serde(rename = "TLSv1.2")]
+    Tls12,
+    /// TLS 1.3
+    #[serde(rename = "TLSv1.3")]
+    Tls13,
+}
+
+impl FromStr for TlsVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_start_matches("TLSv") {
+            "1.2" => Ok(TlsVersion::Tls12),
+            "1.3" => Ok(TlsVersion::Tls13),
+            _ => Err(format!("unknown TLS version {s}, expect 1.2 or 1.3")),
+        }
+    }
+}
+
+impl fmt::Display for TlsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlsVersion::Tls12 => write!(f, "TLSv1.2"),
+            TlsVersion::Tls13 => write!(f, "TLSv1.3"),
+        }
+    }
+}
 
 /// Error or certificate information
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
 pub enum CheckedInner {
     /// An error occurred
     Error {
-        /// Root cause
+        /// Root cause, as string when serialized
+        #[serde(serialize_with = "serialize_error")]
         error: anyhow::Error,
     },
     /// Certificate is valid
     Ok {
-        /// Elapsed time checking
+        /// Elapsed time checking, in milliseconds when serialized
+        #[serde(rename = "elapsed_ms", serialize_with = "serialize_millis")]
         elapsed: Duration,
+        /// Elapsed time resolving domain name, zero when address is resolved beforehand
+        #[serde(rename = "dns_resolution_ms", serialize_with = "serialize_millis")]
+        dns_resolution: Duration,
+        /// Elapsed time establishing TCP connection
+        #[serde(rename = "tcp_connect_ms", serialize_with = "serialize_millis")]
+        tcp_connect: Duration,
+        /// Elapsed time of TLS handshake
+        #[serde(rename = "tls_handshake_ms", serialize_with = "serialize_millis")]
+        tls_handshake: Duration,
+        /// DNS names in subject alternative name extension of certificate
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        sans: Vec<String>,
+        /// TLS protocol version negotiated
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tls_version: Option<TlsVersion>,
+        /// Application protocol negotiated with ALPN, e.g. h2 or http/1.1
+        #[serde(skip_serializing_if = "Option::is_none")]
+        alpn: Option<String>,
+        /// Expiration time
+        not_after: DateTime<Utc>,
+    },
+    /// Certificate does not cover domain name, only reported when hostname is verified
+    HostnameMismatch {
+        /// DNS names in subject alternative name extension of certificate
+        sans: Vec<String>,
         /// Expiration time
         not_after: DateTime<Utc>,
     },
 }
 
 /// Check result
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct Checked<'a> {
     /// When is domain name checked
     pub checked_at: DateTime<Utc>,
     /// Domain name
     pub domain_name: Cow<'a, str>,
+    /// Address checked, only present when addresses are checked individually
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<IpAddr>,
     /// Error or certificate information
+    #[serde(flatten)]
     pub inner: CheckedInner,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_serialize() {
+        let checked_at: DateTime<Utc> = "2023-01-01T00:00:00Z".parse().unwrap();
+        let checked = Checked {
+            checked_at,
+            domain_name: "example.com".into(),
+            address: None,
+            inner: CheckedInner::Ok {
+                elapsed: Duration::from_millis(42),
+                dns_resolution: Duration::from_millis(2),
+                tcp_connect: Duration::from_millis(10),
+                tls_handshake: Duration::from_millis(30),
+                sans: vec![],
+                tls_version: None,
+                alpn: None,
+                not_after: "2023-02-01T00:00:00Z".parse().unwrap(),
+            },
+        };
+        assert_eq!(
+            r#"{"checked_at":"2023-01-01T00:00:00Z","domain_name":"example.com","state":"ok","elapsed_ms":42,"dns_resolution_ms":2,"tcp_connect_ms":10,"tls_handshake_ms":30,"not_after":"2023-02-01T00:00:00Z"}"#,
+            serde_json::to_string(&checked).unwrap()
+        );
+
+        let checked = Checked {
+            checked_at,
+            domain_name: "example.com".into(),
+            address: None,
+            inner: CheckedInner::Ok {
+                elapsed: Duration::from_millis(42),
+                dns_resolution: Duration::from_millis(2),
+                tcp_connect: Duration::from_millis(10),
+                tls_handshake: Duration::from_millis(30),
+                sans: vec![],
+                tls_version: Some(TlsVersion::Tls13),
+                alpn: Some("h2".to_string()),
+                not_after: "2023-02-01T00:00:00Z".parse().unwrap(),
+            },
+        };
+        assert_eq!(
+            r#"{"checked_at":"2023-01-01T00:00:00Z","domain_name":"example.com","state":"ok","elapsed_ms":42,"dns_resolution_ms":2,"tcp_connect_ms":10,"tls_handshake_ms":30,"tls_version":"TLSv1.3","alpn":"h2","not_after":"2023-02-01T00:00:00Z"}"#,
+            serde_json::to_string(&checked).unwrap()
+        );
+
+        let checked = Checked {
+            checked_at,
+            domain_name: "example.com".into(),
+            address: Some("127.0.0.1".parse().unwrap()),
+            inner: CheckedInner::Error {
+                error: anyhow::anyhow!("connection refused"),
+            },
+        };
+        assert_eq!(
+            r#"{"checked_at":"2023-01-01T00:00:00Z","domain_name":"example.com","address":"127.0.0.1","state":"error","error":"connection refused"}"#,
+            serde_json::to_string(&checked).unwrap()
+        );
+
+        let checked = Checked {
+            checked_at,
+            domain_name: "example.com".into(),
+            address: None,
+            inner: CheckedInner::HostnameMismatch {
+                sans: vec!["*.example.org".to_string()],
+                not_after: "2023-02-01T00:00:00Z".parse().unwrap(),
+            },
+        };
+        assert_eq!(
+            r#"{"checked_at":"2023-01-01T00:00:00Z","domain_name":"example.com","state":"hostname_mismatch","sans":["*.example.org"],"not_after":"2023-02-01T00:00:00Z"}"#,
+            serde_json::to_string(&checked).unwrap()
+        );
+    }
+
+    #[test]
+    fn t_tls_version() {
+        assert_eq!(Ok(TlsVersion::Tls12), "1.2".parse());
+        assert_eq!(Ok(TlsVersion::Tls13), "TLSv1.3".parse());
+        assert!("1.1".parse::<TlsVersion>().is_err());
+        assert!("1.4".parse::<TlsVersion>().is_err());
+        assert!(TlsVersion::Tls12 < TlsVersion::Tls13);
+        assert_eq!("TLSv1.3", TlsVersion::Tls13.to_string());
+    }
+}
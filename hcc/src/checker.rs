@@ -1,53 +1,199 @@
 use std::borrow::Cow;
 use std::fmt;
-use std::io::Write;
-use std::net::TcpStream;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
 use std::sync::Arc;
-use std::time::{Instant, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::Context as _;
-use chrono::{TimeZone, Utc};
+use chrono::{DateTime, TimeZone, Utc};
 use futures::stream::FuturesOrdered;
 use log::debug;
 use rustls::client::{ServerCertVerified, ServerCertVerifier};
 use rustls::{Certificate, ClientConfig, OwnedTrustAnchor, ServerName};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::GeneralName;
 use x509_parser::parse_x509_certificate;
 
 use crate::checked::Checked;
-use crate::CheckedInner;
+use crate::{CheckedInner, Proxy, TlsVersion};
 
-fn build_http_headers<'a, T>(domain_name: T) -> Cow<'a, str>
+fn resolve<T>(domain_name: T) -> anyhow::Result<Vec<SocketAddr>>
 where
     T: AsRef<str>,
 {
-    let domain_name = domain_name.as_ref();
-    format!(
-        "GET / HTTP/1.1\r\n\
-            Host: {domain_name}\r\n\
-            Connection: close\r\n\
-            Accept-Encoding: identity\r\n\
-            \r\n"
-    )
-    .into()
-}
-
-fn do_check_one<'a, T>(config: Arc<ClientConfig>, domain_name: T) -> anyhow::Result<Checked<'a>>
+    let mut addresses = vec![];
+    for address in (domain_name.as_ref(), 443).to_socket_addrs()? {
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+    Ok(addresses)
+}
+
+/// Resolves on blocking thread, so slow DNS does not block runtime or other resolutions
+async fn resolve_blocking(domain_name: String) -> anyhow::Result<Vec<SocketAddr>> {
+    tokio::task::spawn_blocking(move || resolve(domain_name)).await?
+}
+
+/// Domain name to check, with address resolved beforehand or error of resolution
+type Target = (String, Option<anyhow::Result<SocketAddr>>);
+
+/// Targets of domain name, one per address when resolved beforehand
+fn targets(domain_name: String, addresses: Option<anyhow::Result<Vec<SocketAddr>>>) -> Vec<Target> {
+    match addresses {
+        None => vec![(domain_name, None)],
+        Some(Ok(addresses)) => addresses
+            .into_iter()
+            .map(|address| (domain_name.clone(), Some(Ok(address))))
+            .collect(),
+        Some(Err(error)) => vec![(domain_name, Some(Err(error)))],
+    }
+}
+
+/// DNS names in subject alternative name extension of certificate
+fn subject_alt_names(cert: &X509Certificate<'_>) -> anyhow::Result<Vec<String>> {
+    let mut names = vec![];
+    if let Some(extension) = cert.subject_alternative_name()? {
+        for name in &extension.value.general_names {
+            if let GeneralName::DNSName(name) = name {
+                names.push(name.to_string());
+            }
+        }
+    }
+    Ok(names)
+}
+
+fn not_after(cert: &X509Certificate<'_>) -> anyhow::Result<DateTime<Utc>> {
+    Utc.timestamp_opt(cert.validity().not_after.timestamp(), 0)
+        .single()
+        .context("invalid timestamp")
+}
+
+/// Leaf certificate, the first one of PEM chain or DER, with its expiry and names
+fn parse_local(content: &[u8]) -> anyhow::Result<(DateTime<Utc>, Vec<String>)> {
+    use x509_parser::pem::Pem;
+
+    let der = if content.starts_with(b"-----BEGIN") {
+        let pem = Pem::iter_from_buffer(content)
+            .next()
+            .context("no certificate found")??;
+        pem.contents
+    } else {
+        content.to_vec()
+    };
+    let (_, cert) = parse_x509_certificate(&der)?;
+    Ok((not_after(&cert)?, subject_alt_names(&cert)?))
+}
+
+/// Whether name in certificate covers domain name, following RFC 6125:
+/// wildcard is only allowed as the whole left-most label, matches exactly one label,
+/// and is not allowed right above public suffix like `*.com`
+fn covers(name: &str, domain_name: &str) -> bool {
+    let name = name.trim_end_matches('.').to_ascii_lowercase();
+    let domain_name = domain_name.trim_end_matches('.').to_ascii_lowercase();
+    match name.strip_prefix("*.") {
+        Some(parent) => {
+            if !parent.contains('.') || parent.contains('*') {
+                return false;
+            }
+            match domain_name.split_once('.') {
+                Some((label, rest)) => !label.is_empty() && rest == parent,
+                None => false,
+            }
+        }
+        None => !name.contains('*') && name == domain_name,
+    }
+}
+
+fn tls_version(version: rustls::ProtocolVersion) -> Option<TlsVersion> {
+    use rustls::ProtocolVersion;
+
+    match version {
+        ProtocolVersion::TLSv1_2 => Some(TlsVersion::Tls12),
+        ProtocolVersion::TLSv1_3 => Some(TlsVersion::Tls13),
+        _ => None,
+    }
+}
+
+/// Explains handshake failure of endpoint only offering TLS 1.0/1.1, which is not supported
+fn handshake_error(error: std::io::Error) -> anyhow::Error {
+    use rustls::AlertDescription;
+
+    // other incompatibilities e.g. no shared cipher suite are reported as is
+    let legacy = matches!(
+        error
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<rustls::Error>()),
+        Some(rustls::Error::AlertReceived(
+            AlertDescription::ProtocolVersion
+        ))
+    );
+    let error = anyhow::Error::new(error);
+    if legacy {
+        error.context("endpoint does not support TLS 1.2 or later")
+    } else {
+        error
+    }
+}
+
+/// Options applied to every check
+#[derive(Clone, Debug, Default)]
+struct CheckOptions {
+    verify_hostname: bool,
+    proxy: Option<Proxy>,
+}
+
+fn do_check_one<'a, T>(
+    config: Arc<ClientConfig>,
+    options: &CheckOptions,
+    domain_name: T,
+    address: Option<SocketAddr>,
+) -> anyhow::Result<Checked<'a>>
 where
     T: Into<Cow<'a, str>>,
 {
-    use anyhow::Error;
-
     let now = Utc::now();
 
     let domain_name = domain_name.into();
     let server_name = ServerName::try_from(domain_name.as_ref())?;
     let mut conn = rustls::ClientConnection::new(config, server_name)?;
 
-    let mut stream = TcpStream::connect(format!("{domain_name}:443"))?;
-    let mut tls = rustls::Stream::new(&mut conn, &mut stream);
-
+    // address is only reported when resolved beforehand i.e. checked individually
     let start = Instant::now();
-    let _ = tls.write(build_http_headers(domain_name.as_ref()).as_bytes());
+    let (mut stream, dns_resolution, tcp_connect) = match (&options.proxy, address) {
+        // domain name is resolved by proxy, so resolution is included in connecting
+        (Some(proxy), None) => {
+            let stream = proxy.connect(&domain_name, 443)?;
+            (stream, Duration::ZERO, start.elapsed())
+        }
+        (Some(proxy), Some(address)) => {
+            let stream = proxy.connect(&address.ip().to_string(), address.port())?;
+            (stream, Duration::ZERO, start.elapsed())
+        }
+        (None, address) => {
+            let address = match address {
+                Some(address) => address,
+                None => *resolve(domain_name.as_ref())?
+                    .first()
+                    .context("no address resolved")?,
+            };
+            let dns_resolution = start.elapsed();
+            let connect_start = Instant::now();
+            let stream = TcpStream::connect(address)?;
+            (stream, dns_resolution, connect_start.elapsed())
+        }
+    };
+
+    let handshake_start = Instant::now();
+    while conn.is_handshaking() {
+        conn.complete_io(&mut stream).map_err(handshake_error)?;
+    }
+    let tls_handshake = handshake_start.elapsed();
+    let tls_version = conn.protocol_version().and_then(tls_version);
+    let alpn = conn
+        .alpn_protocol()
+        .map(|p| String::from_utf8_lossy(p).to_string());
+    let tls = rustls::Stream::new(&mut conn, &mut stream);
 
     let certificates = tls
         .conn
@@ -57,20 +203,27 @@ where
     let certificate = certificates.first().context("no peer certificate found")?;
 
     let (_, cert) = parse_x509_certificate(certificate.as_ref())?;
-    let not_after = match Utc
-        .timestamp_opt(cert.validity().not_after.timestamp(), 0)
-        .single()
-    {
-        Some(t) => t,
-        None => return Err(Error::msg("invalid timestamp")),
+    let not_after = not_after(&cert)?;
+    let sans = subject_alt_names(&cert)?;
+    let inner = if options.verify_hostname && !sans.iter().any(|n| covers(n, &domain_name)) {
+        CheckedInner::HostnameMismatch { sans, not_after }
+    } else {
+        CheckedInner::Ok {
+            elapsed: start.elapsed(),
+            dns_resolution,
+            tcp_connect,
+            tls_handshake,
+            sans,
+            tls_version,
+            alpn,
+            not_after,
+        }
     };
     Ok(Checked {
         checked_at: now,
         domain_name,
-        inner: CheckedInner::Ok {
-            elapsed: start.elapsed(),
-            not_after,
-        },
+        address: address.map(|a| a.ip()),
+        inner,
     })
 }
 
@@ -99,11 +252,16 @@ impl ServerCertVerifier for SkipServerVerification {
 /// Checker for SSL certificate
 pub struct Checker {
     config: Arc<ClientConfig>,
+    all_addresses: bool,
+    options: CheckOptions,
 }
 
 impl fmt::Debug for Checker {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Checker").finish()
+        f.debug_struct("Checker")
+            .field("all_addresses", &self.all_addresses)
+            .field("options", &self.options)
+            .finish()
     }
 }
 
@@ -118,18 +276,70 @@ impl Default for Checker {
             )
         }));
 
-        let config = ClientConfig::builder()
+        let mut config = ClientConfig::builder()
             .with_safe_defaults()
             .with_custom_certificate_verifier(SkipServerVerification::new())
             .with_no_client_auth();
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
 
         Checker {
             config: Arc::new(config),
+            all_addresses: false,
+            options: CheckOptions::default(),
         }
     }
 }
 
+fn error_checked<'a, T>(
+    domain_name: T,
+    address: Option<SocketAddr>,
+    error: anyhow::Error,
+) -> Checked<'a>
+where
+    T: Into<Cow<'a, str>>,
+{
+    Checked {
+        checked_at: Utc::now(),
+        domain_name: domain_name.into(),
+        address: address.map(|a| a.ip()),
+        inner: CheckedInner::Error { error },
+    }
+}
+
 impl Checker {
+    /// Check every resolved A/AAAA record of a domain name individually in [`Checker::check_many`]
+    ///
+    /// ```
+    /// # use hcc::Checker;
+    /// let client = Checker::default().all_addresses(true);
+    /// ```
+    pub fn all_addresses(mut self, all_addresses: bool) -> Self {
+        self.all_addresses = all_addresses;
+        self
+    }
+
+    /// Report [`CheckedInner::HostnameMismatch`] when certificate does not cover domain name
+    ///
+    /// ```
+    /// # use hcc::Checker;
+    /// let client = Checker::default().verify_hostname(true);
+    /// ```
+    pub fn verify_hostname(mut self, verify_hostname: bool) -> Self {
+        self.options.verify_hostname = verify_hostname;
+        self
+    }
+
+    /// Connect to domain names through HTTP or SOCKS5 proxy
+    ///
+    /// ```
+    /// # use hcc::{Checker, Proxy};
+    /// let client = Checker::default().proxy(Proxy::Socks5("127.0.0.1:1080".to_string()));
+    /// ```
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.options.proxy = Some(proxy);
+        self
+    }
+
     /// Check SSL certificate of one domain name
     ///
     /// ```
@@ -143,18 +353,66 @@ impl Checker {
         T: Into<Cow<'a, str>> + Clone,
     {
         let config = self.config.clone();
-        match do_check_one(config, domain_name.clone()) {
+        match do_check_one(config, &self.options, domain_name.clone(), None) {
             Ok(c) => c,
-            Err(error) => Checked {
-                checked_at: Utc::now(),
-                domain_name: domain_name.into(),
-                inner: CheckedInner::Error { error },
+            Err(error) => error_checked(domain_name, None, error),
+        }
+    }
+
+    /// Check local certificate in PEM, leaf first in chain, or DER without any network I/O,
+    /// and report it as name given e.g. path of file
+    ///
+    /// ```
+    /// # use hcc::Checker;
+    /// let client = Checker::default();
+    /// client.check_pem("cert.pem", b"-----BEGIN CERTIFICATE-----");
+    /// ```
+    pub fn check_pem<'a, T>(&self, name: T, content: &[u8]) -> Checked<'a>
+    where
+        T: Into<Cow<'a, str>>,
+    {
+        let (not_after, sans) = match parse_local(content) {
+            Ok(parsed) => parsed,
+            Err(error) => return error_checked(name, None, error),
+        };
+        Checked {
+            checked_at: Utc::now(),
+            domain_name: name.into(),
+            address: None,
+            inner: CheckedInner::Ok {
+                elapsed: Duration::ZERO,
+                dns_resolution: Duration::ZERO,
+                tcp_connect: Duration::ZERO,
+                tls_handshake: Duration::ZERO,
+                sans,
+                tls_version: None,
+                alpn: None,
+                not_after,
             },
         }
     }
 
+    /// Check SSL certificate served by every resolved address of one domain name
+    ///
+    /// ```
+    /// # use hcc::Checker;
+    /// let client = Checker::default();
+    /// client.check_addresses("sha256.badssl.com");
+    /// ```
+    pub async fn check_addresses<'a, T>(&'a self, domain_name: T) -> Vec<Checked<'a>>
+    where
+        T: Into<Cow<'a, str>>,
+    {
+        let domain_name = domain_name.into().into_owned();
+        let addresses = resolve_blocking(domain_name.clone()).await;
+        self.check_targets(targets(domain_name, Some(addresses)))
+            .await
+    }
+
     /// Check SSL certificates of multiple domain names
     ///
+    /// When [`Checker::all_addresses`] is enabled, one result is returned per resolved address.
+    ///
     /// ```
     /// # use hcc::Checker;
     /// let client = Checker::default();
@@ -170,32 +428,71 @@ impl Checker {
     {
         use futures::StreamExt as _;
 
-        let now = Utc::now();
+        // domain names are resolved concurrently, each on blocking thread
+        let mut resolutions = FuturesOrdered::new();
+        for domain_name in domain_names {
+            let domain_name = domain_name.as_ref().to_string();
+            let all_addresses = self.all_addresses;
+            resolutions.push_back(async move {
+                let addresses = if all_addresses {
+                    Some(resolve_blocking(domain_name.clone()).await)
+                } else {
+                    None
+                };
+                targets(domain_name, addresses)
+            });
+        }
+        let mut all_targets = vec![];
+        while let Some(targets) = resolutions.next().await {
+            all_targets.extend(targets);
+        }
+
+        Ok(self.check_targets(all_targets).await)
+    }
+
+    /// Checks every target in its own task, in order of targets
+    async fn check_targets(&self, targets: Vec<Target>) -> Vec<Checked<'static>> {
+        use futures::StreamExt as _;
 
         let mut tasks = FuturesOrdered::new();
-        for domain_name in domain_names {
+        let mut names = vec![];
+        for (domain_name, address) in targets {
+            let ip = match address {
+                Some(Ok(address)) => Some(address),
+                _ => None,
+            };
+            names.push((domain_name.clone(), ip));
             let config = self.config.clone();
-            let domain_name = domain_name.as_ref().to_string();
+            let options = self.options.clone();
             tasks.push_back(tokio::spawn(async move {
-                debug!("check {domain_name}");
-                let checked = match do_check_one(config, domain_name.clone()) {
-                    Ok(c) => c,
-                    Err(error) => Checked {
-                        checked_at: now,
-                        domain_name: domain_name.into(),
-                        inner: CheckedInner::Error { error },
-                    },
+                debug!("check {domain_name} address={address:?}");
+                let checked = match address {
+                    None => do_check_one(config, &options, domain_name.clone(), None)
+                        .unwrap_or_else(|error| error_checked(domain_name, None, error)),
+                    Some(Ok(address)) => {
+                        do_check_one(config, &options, domain_name.clone(), Some(address))
+                            .unwrap_or_else(|error| {
+                                error_checked(domain_name, Some(address), error)
+                            })
+                    }
+                    Some(Err(error)) => error_checked(domain_name, None, error),
                 };
                 debug!("{} checked", checked.domain_name);
                 checked
             }));
         }
 
+        // a panicked check only fails its own domain name, not the whole batch
         let mut results = vec![];
+        let mut names = names.into_iter();
         while let Some(task) = tasks.next().await {
-            results.push(task?);
+            let (domain_name, address) = names.next().unwrap_or_default();
+            match task {
+                Ok(checked) => results.push(checked),
+                Err(error) => results.push(error_checked(domain_name, address, error.into())),
+            }
         }
-        Ok(results)
+        results
     }
 }
 
@@ -203,6 +500,29 @@ impl Checker {
 mod test {
     use super::*;
 
+    #[test]
+    fn t_check_pem() {
+        let pem = include_bytes!("../fixtures/cert.pem");
+        let client = Checker::default();
+        let checked = client.check_pem("cert.pem", pem);
+        assert_eq!("cert.pem", checked.domain_name);
+        let (not_after, sans) = match checked.inner {
+            CheckedInner::Ok {
+                not_after, sans, ..
+            } => (not_after, sans),
+            _ => panic!("unexpected result {checked:?}"),
+        };
+        assert_eq!("2126-09-22T22:58:29+00:00", not_after.to_rfc3339());
+        assert_eq!(vec!["example.com", "*.example.com"], sans);
+
+        let der = x509_parser::pem::parse_x509_pem(pem).unwrap().1.contents;
+        let checked = client.check_pem("cert.der", &der);
+        assert!(matches!(checked.inner, CheckedInner::Ok { .. }));
+
+        let checked = client.check_pem("empty.pem", b"");
+        assert!(matches!(checked.inner, CheckedInner::Error { .. }));
+    }
+
     #[tokio::test]
     async fn t_good_certificate() {
         let client = Checker::default();
@@ -250,4 +570,37 @@ mod test {
         let result = client.check_one("example.invalid").await;
         assert!(matches!(result.inner, CheckedInner::Error { .. }));
     }
+
+    #[tokio::test]
+    async fn t_check_addresses_invalid() {
+        let client = Checker::default();
+        let results = client.check_addresses("example.invalid").await;
+        assert_eq!(1, results.len());
+        assert!(results[0].address.is_none());
+        assert!(matches!(results[0].inner, CheckedInner::Error { .. }));
+    }
+
+    #[test]
+    fn t_covers() {
+        assert!(covers("example.com", "example.com"));
+        assert!(covers("Example.COM.", "example.com"));
+        assert!(!covers("example.com", "www.example.com"));
+        assert!(covers("*.example.com", "www.example.com"));
+        assert!(!covers("*.example.com", "example.com"));
+        assert!(!covers("*.example.com", "a.b.example.com"));
+        assert!(!covers("*.com", "example.com"));
+        assert!(!covers("w*.example.com", "www.example.com"));
+        assert!(!covers("www.*.com", "www.example.com"));
+    }
+
+    #[tokio::test]
+    async fn t_check_many_all_addresses() {
+        let client = Checker::default().all_addresses(true);
+        let results = client.check_many(&["sha256.badssl.com"]).await.unwrap();
+        assert!(!results.is_empty());
+        for result in results {
+            assert!(result.address.is_some());
+            assert!(matches!(result.inner, CheckedInner::Ok { .. }));
+        }
+    }
 }
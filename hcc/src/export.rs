@@ -0,0 +1,225 @@
+use clap::ValueEnum;
+use hcc::CheckedInner;
+
+use crate::sink::Output;
+use crate::{state_key, State};
+
+/// Format of check results written by check command
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub(crate) enum OutputFormat {
+    /// One human readable line per result
+    #[default]
+    Text,
+    /// Comma-separated values with header
+    Csv,
+    /// JUnit XML, one test case per result
+    Junit,
+}
+
+impl OutputFormat {
+    /// Serializes results
+    pub(crate) fn export(&self, outputs: &[Output<'_>]) -> String {
+        match self {
+            OutputFormat::Text => text(outputs),
+            OutputFormat::Csv => csv(outputs),
+            OutputFormat::Junit => junit(outputs),
+        }
+    }
+}
+
+/// State of result in exported formats, errors and mismatches are told apart from expiry
+fn state_name(output: &Output<'_>) -> &'static str {
+    match (&output.checked.inner, output.state) {
+        (CheckedInner::Ok { .. }, State::Valid) => "ok",
+        (CheckedInner::Ok { .. }, State::Warning) => "warning",
+        (CheckedInner::Ok { .. }, State::Expired) => "expired",
+        (CheckedInner::HostnameMismatch { .. }, _) => "hostname_mismatch",
+        (CheckedInner::Error { .. }, _) => "error",
+    }
+}
+
+fn text(outputs: &[Output<'_>]) -> String {
+    outputs.iter().map(|o| format!("{}\n", o.message)).collect()
+}
+
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Serializes results into CSV with domain name, address, state, days left, expiry,
+/// elapsed time in milliseconds and error
+fn csv(outputs: &[Output<'_>]) -> String {
+    let mut lines = vec!["domain_name,address,state,days,not_after,elapsed_ms,error".to_string()];
+    for output in outputs {
+        let checked = output.checked;
+        let (days, not_after, elapsed, error) = match &checked.inner {
+            CheckedInner::Ok {
+                not_after, elapsed, ..
+            } => (
+                (*not_after - checked.checked_at).num_days().to_string(),
+                not_after.to_rfc3339(),
+                elapsed.as_millis().to_string(),
+                String::new(),
+            ),
+            CheckedInner::HostnameMismatch { not_after, .. } => (
+                (*not_after - checked.checked_at).num_days().to_string(),
+                not_after.to_rfc3339(),
+                String::new(),
+                String::new(),
+            ),
+            CheckedInner::Error { error } => (
+                String::new(),
+                String::new(),
+                String::new(),
+                error.to_string(),
+            ),
+        };
+        let address = checked.address.map(|a| a.to_string()).unwrap_or_default();
+        let fields = [
+            checked.domain_name.as_ref(),
+            &address,
+            state_name(output),
+            &days,
+            &not_after,
+            &elapsed,
+            &error,
+        ];
+        let fields = fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>();
+        lines.push(fields.join(","));
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Serializes results into JUnit XML, certificates expiring within grace period, expired
+/// or not covering domain name are failures, and check errors are errors
+fn junit(outputs: &[Output<'_>]) -> String {
+    let failures = outputs
+        .iter()
+        .filter(|o| matches!(state_name(o), "warning" | "expired" | "hostname_mismatch"))
+        .count();
+    let errors = outputs
+        .iter()
+        .filter(|o| matches!(o.checked.inner, CheckedInner::Error { .. }))
+        .count();
+    let timestamp = outputs
+        .iter()
+        .map(|o| o.checked.checked_at)
+        .min()
+        .unwrap_or_else(chrono::Utc::now);
+
+    let mut lines = vec![
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_string(),
+        format!(
+            r#"<testsuite name="hcc" tests="{}" failures="{failures}" errors="{errors}" timestamp="{}">"#,
+            outputs.len(),
+            timestamp.format("%Y-%m-%dT%H:%M:%S"),
+        ),
+    ];
+    for output in outputs {
+        let checked = output.checked;
+        let name = state_key(checked);
+        let time = match &checked.inner {
+            CheckedInner::Ok { elapsed, .. } => elapsed.as_secs_f64(),
+            _ => 0.0,
+        };
+        let testcase = format!(
+            r#"  <testcase classname="hcc" name="{}" time="{time:.3}""#,
+            xml_escape(&name)
+        );
+        let message = xml_escape(&output.message);
+        match state_name(output) {
+            "ok" => lines.push(format!("{testcase}/>")),
+            state => {
+                let element = if state == "error" { "error" } else { "failure" };
+                lines.push(format!("{testcase}>"));
+                lines.push(format!(
+                    r#"    <{element} type="{state}" message="{message}">{message}</{element}>"#
+                ));
+                lines.push("  </testcase>".to_string());
+            }
+        }
+    }
+    lines.push("</testsuite>".to_string());
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use chrono::{DateTime, Utc};
+    use hcc::Checked;
+
+    use crate::test::ok_checked;
+
+    #[test]
+    fn t_export() {
+        let checked_at: DateTime<Utc> = "2023-01-01T00:00:00Z".parse().unwrap();
+        let mut ok = ok_checked(
+            "a.com",
+            checked_at,
+            "2023-01-31T00:00:00Z".parse().unwrap(),
+            None,
+        );
+        if let CheckedInner::Ok { elapsed, .. } = &mut ok.inner {
+            *elapsed = Duration::from_millis(42);
+        }
+        let error = Checked {
+            checked_at,
+            domain_name: "b.com".into(),
+            address: Some("127.0.0.1".parse().unwrap()),
+            inner: CheckedInner::Error {
+                error: anyhow::anyhow!("refused, \"really\""),
+            },
+        };
+        let outputs = vec![
+            Output {
+                checked: &ok,
+                message: "a.com expires at 2023-01-31".into(),
+                state: State::Valid,
+                notify: false,
+            },
+            Output {
+                checked: &error,
+                message: "b.com: <refused>".into(),
+                state: State::Expired,
+                notify: true,
+            },
+        ];
+
+        assert_eq!(
+            "a.com expires at 2023-01-31\nb.com: <refused>\n",
+            OutputFormat::Text.export(&outputs)
+        );
+        assert_eq!(
+            "domain_name,address,state,days,not_after,elapsed_ms,error\n\
+             a.com,,ok,30,2023-01-31T00:00:00+00:00,42,\n\
+             b.com,127.0.0.1,error,,,,\"refused, \"\"really\"\"\"\n",
+            csv(&outputs)
+        );
+
+        let xml = junit(&outputs);
+        assert!(
+            xml.contains(r#"tests="2" failures="0" errors="1" timestamp="2023-01-01T00:00:00""#)
+        );
+        assert!(xml.contains(r#"<testcase classname="hcc" name="a.com" time="0.042"/>"#));
+        assert!(xml.contains(r#"<testcase classname="hcc" name="b.com (127.0.0.1)" time="0.000">"#));
+        assert!(xml.contains(r#"<error type="error" message="b.com: &lt;refused&gt;">"#));
+    }
+}
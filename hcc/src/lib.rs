@@ -12,8 +12,10 @@
 
 //! HTTPS Certificate Check
 
-pub use checked::{Checked, CheckedInner};
+pub use checked::{Checked, CheckedInner, TlsVersion};
 pub use checker::Checker;
+pub use proxy::Proxy;
 
 mod checked;
 mod checker;
+mod proxy;
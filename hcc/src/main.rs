@@ -12,19 +12,34 @@
 
 //! HTTPS Certificate Check
 
+use std::collections::HashMap;
 use std::fmt::Display;
+use std::path::{Path, PathBuf};
 use std::{borrow::Cow, time::Duration};
 
 use chrono::Utc;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use cron::Schedule;
 use futures::stream::FuturesUnordered;
-use hcc::{Checked, CheckedInner, Checker};
-use log::debug;
+use hcc::{Checked, CheckedInner, Checker, Proxy, TlsVersion};
+use log::{debug, error};
 use once_cell::sync::OnceCell;
-use pushover::{send_notification, NotificationError};
+use pushover::{Attachment, Notification, NotificationError};
 use supports_unicode::Stream;
 
+use crate::export::OutputFormat;
+use crate::report::{Calendar, Period, ReportFormat};
+use crate::scheduler::{DomainSchedule, Scheduler, Trigger};
+use crate::sink::{digest, digest_chart, write_file, Output, OutputSink, SinkConfig};
+
+#[cfg(feature = "bench")]
+mod bench;
+mod chart;
+mod export;
+mod report;
+mod scheduler;
+mod sink;
+
 fn get_opts() -> &'static Opts {
     static INSTANCE: OnceCell<Opts> = OnceCell::new();
     INSTANCE.get_or_init(Opts::parse)
@@ -39,6 +54,28 @@ struct Opts {
     /// Grace period in days
     #[arg(short, long = "grace", default_value = "7")]
     grace_in_days: i64,
+    /// Check every resolved IPv4 and IPv6 address individually
+    #[arg(long)]
+    all_addresses: bool,
+    /// Report certificates not covering domain name
+    #[arg(long)]
+    verify_hostname: bool,
+    /// Warn about endpoints negotiating TLS version older than this, only 1.3 is accepted.
+    /// Endpoints only offering TLS 1.0/1.1 fail to handshake and are reported as errors
+    #[arg(long, env = "MIN_TLS_VERSION", value_parser = parse_min_tls_version)]
+    min_tls_version: Option<TlsVersion>,
+    /// Connect through HTTP or SOCKS5 proxy e.g. http://127.0.0.1:3128, socks5://127.0.0.1:1080
+    #[arg(long, env = "PROXY")]
+    proxy: Option<Proxy>,
+    /// Send one notification summarizing all results instead of one per result
+    #[arg(long)]
+    digest: bool,
+    /// Attach chart of days remaining per domain to digest notification
+    #[arg(long, requires = "digest")]
+    digest_chart: bool,
+    /// Which check results are sent as notifications
+    #[arg(long, value_enum, default_value = "all", env = "NOTIFY_ON")]
+    notify_on: NotifyOn,
     /// Pushover token
     #[arg(long, env = "PUSHOVER_TOKEN")]
     pushover_token: Option<String>,
@@ -49,6 +86,91 @@ struct Opts {
     command: Option<Commands>,
 }
 
+/// Which check results are sent as notifications
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+enum NotifyOn {
+    /// Every result
+    #[default]
+    All,
+    /// Certificates expiring within grace period, expired or failed
+    Warning,
+    /// Certificates expired or failed
+    Expired,
+    /// Results whose state differs from previous check
+    Change,
+}
+
+/// State of certificate, ordered by severity
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum State {
+    Valid,
+    Warning,
+    Expired,
+}
+
+/// Minimum TLS version, only 1.3 since TLS 1.2 is the oldest version negotiated
+fn parse_min_tls_version(s: &str) -> Result<TlsVersion, String> {
+    match s.parse() {
+        Ok(TlsVersion::Tls13) => Ok(TlsVersion::Tls13),
+        _ => Err(format!(
+            "unsupported minimum TLS version {s}, expect 1.3 since older versions never warn"
+        )),
+    }
+}
+
+/// TLS version negotiated by endpoint when older than minimum
+fn outdated_tls(checked: &Checked<'_>, min_tls_version: Option<TlsVersion>) -> Option<TlsVersion> {
+    match (&checked.inner, min_tls_version) {
+        (
+            CheckedInner::Ok {
+                tls_version: Some(version),
+                ..
+            },
+            Some(min),
+        ) if *version < min => Some(*version),
+        _ => None,
+    }
+}
+
+impl State {
+    fn of(checked: &Checked<'_>, grace_in_days: i64, min_tls_version: Option<TlsVersion>) -> Self {
+        let grace = chrono::Duration::days(grace_in_days);
+        match &checked.inner {
+            CheckedInner::Ok { not_after, .. } => {
+                if not_after <= &checked.checked_at {
+                    State::Expired
+                } else if not_after <= &(checked.checked_at + grace)
+                    || outdated_tls(checked, min_tls_version).is_some()
+                {
+                    State::Warning
+                } else {
+                    State::Valid
+                }
+            }
+            CheckedInner::HostnameMismatch { .. } | CheckedInner::Error { .. } => State::Expired,
+        }
+    }
+}
+
+impl NotifyOn {
+    fn should_notify(&self, state: State, previous: Option<State>) -> bool {
+        match self {
+            NotifyOn::All => true,
+            NotifyOn::Warning => state >= State::Warning,
+            NotifyOn::Expired => state >= State::Expired,
+            NotifyOn::Change => previous.unwrap_or(State::Valid) != state,
+        }
+    }
+}
+
+/// Key to remember state of domain name, or its address
+fn state_key(checked: &Checked<'_>) -> String {
+    match checked.address {
+        Some(address) => format!("{} ({address})", checked.domain_name),
+        None => checked.domain_name.to_string(),
+    }
+}
+
 #[derive(Debug, Subcommand)]
 enum Commands {
     /// Check domain name(s) immediately
@@ -56,6 +178,15 @@ enum Commands {
         /// Send notification
         #[arg(long)]
         notify: bool,
+        /// Format of results
+        #[arg(long, value_enum, default_value = "text")]
+        output: OutputFormat,
+        /// Write results to file instead of standard output
+        #[arg(long)]
+        output_file: Option<PathBuf>,
+        /// Check local certificate in PEM or DER instead of connecting. Could be repeated
+        #[arg(long = "file")]
+        files: Vec<PathBuf>,
         /// One or many domain names to check
         #[arg()]
         domain_names: Vec<String>,
@@ -65,43 +196,132 @@ enum Commands {
         /// Cron
         #[arg(short, long, default_value = "0 0 0 * * *")]
         cron: String,
+        /// Interval instead of cron e.g. "6h", counted from completion of last run so runs never overlap
+        #[arg(long, env = "EVERY", conflicts_with = "cron")]
+        every: Option<humantime::Duration>,
+        /// Cron of one domain name instead of default one e.g. "x.com=0 0 * * * *".
+        /// Could be repeated, or separated with semicolon in environment variable
+        #[arg(long = "schedule", env = "SCHEDULES", value_delimiter = ';')]
+        schedules: Vec<DomainSchedule>,
+        /// Send notification when checks, notifications or sinks fail
+        #[arg(long)]
+        notify_failure: bool,
+        /// Where results of each run go: stdout, json:PATH, csv:PATH, junit:PATH, webhook:URL or pushover.
+        /// Could be repeated. Pushover if Pushover token and user are set by default
+        #[arg(long = "sink", env = "SINKS", value_delimiter = ',')]
+        sinks: Vec<SinkConfig>,
+        /// Shared secret to sign payload of webhook sinks with HMAC-SHA256 in X-HCC-Signature header
+        #[arg(long, env = "WEBHOOK_SECRET")]
+        webhook_secret: Option<String>,
         /// One or many domain names to check
         #[arg(env = "DOMAIN_NAMES")]
         domain_names: Vec<String>,
     },
+    /// Check domain names, and print calendar of certificate expiries, soonest first
+    Report {
+        /// Format of calendar
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+        /// Period which expiries are grouped by
+        #[arg(long, value_enum, default_value = "week")]
+        group_by: Period,
+        /// Write calendar to file instead of standard output
+        #[arg(long)]
+        output_file: Option<PathBuf>,
+        /// One or many domain names to check
+        #[arg(env = "DOMAIN_NAMES")]
+        domain_names: Vec<String>,
+    },
+    /// Check domain names repeatedly, and report throughput and latency percentiles
+    #[cfg(feature = "bench")]
+    Bench {
+        /// Checks running at once
+        #[arg(long, default_value = "16")]
+        concurrency: usize,
+        /// Times to check each domain name
+        #[arg(long, default_value = "1")]
+        repeat: usize,
+        /// Read domain names from file, one per line
+        #[arg(long)]
+        domains_file: Option<PathBuf>,
+        /// One or many domain names to check
+        #[arg()]
+        domain_names: Vec<String>,
+    },
 }
 
 struct CheckedString<'a> {
     inner: &'a Checked<'a>,
     grace_in_days: i64,
+    min_tls_version: Option<TlsVersion>,
+    /// Append timing breakdown and negotiated protocols
+    verbose: bool,
 }
 
 impl<'a> Display for CheckedString<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let is_unicode = supports_unicode::on(Stream::Stdout);
-        let domain_name = &self.inner.domain_name;
-        let grace = chrono::Duration::days(self.grace_in_days);
+        let domain_name = state_key(self.inner);
         match &self.inner.inner {
-            CheckedInner::Ok { not_after, .. } => {
-                if not_after > &(self.inner.checked_at + grace) {
-                    let icon = if is_unicode { "\u{2705}" } else { "[v]" };
-                    write!(f, "{icon} {domain_name} expires at {not_after}")
-                } else if not_after > &self.inner.checked_at {
-                    let icon = if is_unicode {
-                        "\u{26a0}\u{fe0f}"
-                    } else {
-                        "[!]"
-                    };
-                    let duration = *not_after - self.inner.checked_at;
-                    let days = duration.num_days();
+            CheckedInner::Ok {
+                elapsed,
+                dns_resolution,
+                tcp_connect,
+                tls_handshake,
+                tls_version,
+                alpn,
+                not_after,
+                ..
+            } => {
+                let outdated = outdated_tls(self.inner, self.min_tls_version);
+                match State::of(self.inner, self.grace_in_days, self.min_tls_version) {
+                    State::Valid => {
+                        let icon = if is_unicode { "\u{2705}" } else { "[v]" };
+                        write!(f, "{icon} {domain_name} expires at {not_after}")
+                    }
+                    State::Warning => {
+                        let icon = if is_unicode {
+                            "\u{26a0}\u{fe0f}"
+                        } else {
+                            "[!]"
+                        };
+                        let duration = *not_after - self.inner.checked_at;
+                        let days = duration.num_days();
+                        write!(
+                            f,
+                            "{icon} {domain_name} expires in {days} day(s) at {not_after}"
+                        )?;
+                        match outdated {
+                            Some(version) => write!(f, ", negotiates outdated {version}"),
+                            None => Ok(()),
+                        }
+                    }
+                    State::Expired => {
+                        let icon = if is_unicode { "\u{274c}" } else { "[x]" };
+                        write!(f, "{icon} {domain_name} expired at {not_after}")
+                    }
+                }?;
+                if self.verbose {
                     write!(
                         f,
-                        "{icon} {domain_name} expires in {days} day(s) at {not_after}"
-                    )
-                } else {
-                    let icon = if is_unicode { "\u{274c}" } else { "[x]" };
-                    write!(f, "{icon} {domain_name} expired at {not_after}")
+                        " (dns {dns_resolution:?}, connect {tcp_connect:?}, tls {tls_handshake:?}, total {elapsed:?})"
+                    )?;
+                    if let Some(version) = tls_version {
+                        write!(f, " {version}")?;
+                    }
+                    if let Some(alpn) = alpn {
+                        write!(f, " {alpn}")?;
+                    }
                 }
+                Ok(())
+            }
+            CheckedInner::HostnameMismatch { sans, not_after } => {
+                let icon = if is_unicode { "\u{274c}" } else { "[x]" };
+                let sans = sans.join(", ");
+                write!(
+                    f,
+                    "{icon} {domain_name} is not covered by certificate for [{sans}] expiring at {not_after}"
+                )
             }
             CheckedInner::Error { error } => {
                 let icon = if is_unicode { "\u{274c}" } else { "[x]" };
@@ -119,40 +339,157 @@ async fn main() -> anyhow::Result<()> {
     if let Some(Commands::Check {
         domain_names,
         notify,
+        output,
+        output_file,
+        files,
     }) = &opts.command
     {
-        check_command(&opts, domain_names, *notify).await?;
+        check_command(
+            &opts,
+            domain_names,
+            files,
+            *notify,
+            *output,
+            output_file.as_deref(),
+        )
+        .await?;
     }
-    if let Some(Commands::Daemon { cron, domain_names }) = &opts.command {
-        daemon_command(&opts, cron, domain_names).await?;
+    if let Some(Commands::Daemon {
+        cron,
+        every,
+        schedules,
+        notify_failure,
+        sinks,
+        webhook_secret,
+        domain_names,
+    }) = &opts.command
+    {
+        let sinks = build_sinks(&opts, sinks, webhook_secret.as_deref())?;
+        let trigger = match every {
+            Some(every) if every.is_zero() => anyhow::bail!("interval must not be zero"),
+            Some(every) => Trigger::Every(**every),
+            None => Trigger::Cron(Box::new(cron.parse::<Schedule>()?)),
+        };
+        daemon_command(
+            &opts,
+            trigger,
+            schedules,
+            *notify_failure,
+            &sinks,
+            domain_names,
+        )
+        .await?;
+    }
+    if let Some(Commands::Report {
+        format,
+        group_by,
+        output_file,
+        domain_names,
+    }) = &opts.command
+    {
+        let client = build_checker(&opts);
+        let results = client.check_many(domain_names).await?;
+        let calendar = Calendar::new(&results).export(*format, *group_by);
+        match output_file {
+            Some(path) => write_file(path, calendar)?,
+            None => print!("{calendar}"),
+        }
+    }
+    #[cfg(feature = "bench")]
+    if let Some(Commands::Bench {
+        concurrency,
+        repeat,
+        domains_file,
+        domain_names,
+    }) = &opts.command
+    {
+        let mut domain_names = domain_names.clone();
+        if let Some(path) = domains_file {
+            domain_names.extend(bench::read_domain_names(path)?);
+        }
+        if domain_names.is_empty() {
+            anyhow::bail!("no domain name to check");
+        }
+        let report = bench::bench(build_checker(&opts), &domain_names, *repeat, *concurrency);
+        print!("{}", report.await?);
     }
     Ok(())
 }
 
+fn build_checker(opts: &Opts) -> Checker {
+    let client = Checker::default()
+        .all_addresses(opts.all_addresses)
+        .verify_hostname(opts.verify_hostname);
+    match opts.proxy {
+        Some(ref proxy) => client.proxy(proxy.clone()),
+        None => client,
+    }
+}
+
 async fn check_command<T>(
     opts: &Opts,
     domain_names: &[T],
+    files: &[PathBuf],
     should_notify: bool,
+    output: OutputFormat,
+    output_file: Option<&Path>,
 ) -> anyhow::Result<()>
 where
     T: AsRef<str>,
 {
     use futures::StreamExt as _;
 
-    let client = Checker::default();
-    let results = client.check_many(domain_names).await?;
+    let client = build_checker(opts);
+    let mut results = client.check_many(domain_names).await?;
+    for path in files {
+        let name = path.display().to_string();
+        let checked = match std::fs::read(path) {
+            Ok(content) => client.check_pem(name, &content),
+            Err(e) => Checked {
+                checked_at: Utc::now(),
+                domain_name: name.into(),
+                address: None,
+                inner: CheckedInner::Error { error: e.into() },
+            },
+        };
+        results.push(checked);
+    }
 
     let mut tasks = FuturesUnordered::new();
-    for result in results.iter() {
+    let mut outputs = vec![];
+    for checked in results.iter() {
+        let state = State::of(checked, opts.grace_in_days, opts.min_tls_version);
         let result = CheckedString {
-            inner: result,
+            inner: checked,
             grace_in_days: opts.grace_in_days,
+            min_tls_version: opts.min_tls_version,
+            verbose: opts.verbose,
         }
         .to_string();
-        println!("{result}");
-        if should_notify {
+        let notify_result = should_notify && opts.notify_on.should_notify(state, None);
+        if notify_result && !opts.digest {
+            let result = result.clone();
             tasks.push(tokio::spawn(async move { notify(result).await }));
         }
+        outputs.push(Output {
+            checked,
+            message: result,
+            state,
+            notify: notify_result,
+        });
+    }
+    if opts.digest && outputs.iter().any(|o| o.notify) {
+        let message = digest(&outputs);
+        let attachment = opts.digest_chart.then(|| digest_chart(&outputs)).flatten();
+        tasks.push(tokio::spawn(async move {
+            notify_with_attachment(message, attachment.as_ref()).await
+        }));
+    }
+
+    let exported = output.export(&outputs);
+    match output_file {
+        Some(path) => write_file(path, exported)?,
+        None => print!("{exported}"),
     }
 
     while let Some(task) = tasks.next().await {
@@ -162,20 +499,42 @@ where
     Ok(())
 }
 
-async fn daemon_command<'a, T, U>(opts: &Opts, cron: T, domain_names: &[U]) -> anyhow::Result<()>
+fn build_sinks(
+    opts: &Opts,
+    configs: &[SinkConfig],
+    webhook_secret: Option<&str>,
+) -> anyhow::Result<Vec<Box<dyn OutputSink>>> {
+    let pushover = opts
+        .pushover_token
+        .as_deref()
+        .zip(opts.pushover_user.as_deref());
+    let mut configs = configs.to_vec();
+    if configs.is_empty() && pushover.is_some() {
+        configs.push(SinkConfig::Pushover);
+    }
+    configs
+        .iter()
+        .map(|c| c.build(pushover, opts.digest, opts.digest_chart, webhook_secret))
+        .collect()
+}
+
+async fn daemon_command<U>(
+    opts: &Opts,
+    trigger: Trigger,
+    schedules: &[DomainSchedule],
+    notify_failure: bool,
+    sinks: &[Box<dyn OutputSink>],
+    domain_names: &[U],
+) -> anyhow::Result<()>
 where
-    T: AsRef<str>,
     U: AsRef<str> + std::fmt::Debug,
 {
-    use futures::StreamExt as _;
-    use std::str::FromStr as _;
-
-    let client = Checker::default();
+    let client = build_checker(opts);
 
-    let cron = cron.as_ref();
-    let schedule = Schedule::from_str(cron)?;
+    let mut scheduler = Scheduler::new(trigger, domain_names, schedules, Utc::now());
+    let mut states: HashMap<String, State> = HashMap::new();
 
-    for next in schedule.upcoming(Utc) {
+    while let Some((next, domain_names)) = scheduler.next() {
         debug!("check certificates of {domain_names:?} at {next:?}");
         loop {
             if Utc::now().timestamp() >= next.timestamp() {
@@ -185,27 +544,64 @@ where
         }
 
         debug!("check {domain_names:?}");
-        let results = client.check_many(domain_names).await?;
+        let results = match client.check_many(&domain_names).await {
+            Ok(results) => results,
+            Err(e) => {
+                scheduler.complete(Utc::now());
+                report_failure(
+                    format!("failed to check {domain_names:?}: {e}"),
+                    notify_failure,
+                )
+                .await;
+                continue;
+            }
+        };
 
-        let mut tasks = FuturesUnordered::new();
-        for result in results.iter() {
-            let result = CheckedString {
-                inner: result,
+        let mut outputs = vec![];
+        for checked in results.iter() {
+            let state = State::of(checked, opts.grace_in_days, opts.min_tls_version);
+            let previous = states.insert(state_key(checked), state);
+            let message = CheckedString {
+                inner: checked,
                 grace_in_days: opts.grace_in_days,
+                min_tls_version: opts.min_tls_version,
+                verbose: opts.verbose,
             }
             .to_string();
-            debug!("{result}");
-            tasks.push(tokio::spawn(async move { notify(result).await }));
+            debug!("{message}");
+            outputs.push(Output {
+                checked,
+                message,
+                state,
+                notify: opts.notify_on.should_notify(state, previous),
+            });
         }
 
-        while let Some(task) = tasks.next().await {
-            task??;
+        let mut failures = vec![];
+        for sink in sinks {
+            if let Err(e) = sink.emit(&outputs).await {
+                failures.push(format!("failed to emit results to {}: {e}", sink.name()));
+            }
         }
+        for failure in failures {
+            report_failure(failure, notify_failure).await;
+        }
+        scheduler.complete(Utc::now());
     }
 
     Ok(())
 }
 
+/// Logs failure in daemon, and tries to notify about it if asked to
+async fn report_failure(message: String, should_notify: bool) {
+    error!("{message}");
+    if should_notify {
+        if let Err(e) = notify(message).await {
+            error!("failed to notify failure: {e}");
+        }
+    }
+}
+
 fn get_pushover_config<'a>() -> Option<(Cow<'a, str>, Cow<'a, str>)> {
     let opts = get_opts();
     let t = opts.pushover_token.as_ref()?;
@@ -214,6 +610,16 @@ fn get_pushover_config<'a>() -> Option<(Cow<'a, str>, Cow<'a, str>)> {
 }
 
 async fn notify<'a, T>(message: T) -> Result<(), NotificationError>
+where
+    T: Into<Cow<'a, str>>,
+{
+    notify_with_attachment(message, None).await
+}
+
+async fn notify_with_attachment<'a, T>(
+    message: T,
+    attachment: Option<&Attachment<'_>>,
+) -> Result<(), NotificationError>
 where
     T: Into<Cow<'a, str>>,
 {
@@ -223,7 +629,9 @@ where
         None => return Ok(()),
     };
     debug!("send pushover notification {message:?}");
-    let res = send_notification(token, user, message).await?;
+    let mut notification = Notification::new(token, user, message);
+    notification.attachment = attachment;
+    let res = notification.send_checked().await?;
     debug!("pushover response {res:?}");
     Ok(())
 }
@@ -232,24 +640,108 @@ where
 mod test {
     use super::*;
 
+    use chrono::DateTime;
+
     fn build_opts() -> Opts {
         Opts::default()
     }
 
+    /// Builds successful check result with zero durations
+    pub(crate) fn ok_checked(
+        domain_name: &'static str,
+        checked_at: DateTime<Utc>,
+        not_after: DateTime<Utc>,
+        tls_version: Option<TlsVersion>,
+    ) -> Checked<'static> {
+        Checked {
+            checked_at,
+            domain_name: domain_name.into(),
+            address: None,
+            inner: CheckedInner::Ok {
+                elapsed: Duration::ZERO,
+                dns_resolution: Duration::ZERO,
+                tcp_connect: Duration::ZERO,
+                tls_handshake: Duration::ZERO,
+                sans: vec![],
+                tls_version,
+                alpn: None,
+                not_after,
+            },
+        }
+    }
+
     #[tokio::test]
     async fn t_check_command() {
         let opts = build_opts();
-        check_command(&opts, &["sha256.badssl.com"], false)
-            .await
-            .unwrap();
+        check_command(
+            &opts,
+            &["sha256.badssl.com"],
+            &[],
+            false,
+            OutputFormat::Text,
+            None,
+        )
+        .await
+        .unwrap();
     }
 
     #[tokio::test]
     async fn t_check_command_expired() {
         let opts = build_opts();
-        check_command(&opts, &["expired.badssl.com"], false)
-            .await
-            .unwrap();
+        check_command(
+            &opts,
+            &["expired.badssl.com"],
+            &[],
+            false,
+            OutputFormat::Text,
+            None,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn t_should_notify() {
+        assert!(NotifyOn::All.should_notify(State::Valid, None));
+        assert!(!NotifyOn::Warning.should_notify(State::Valid, None));
+        assert!(NotifyOn::Warning.should_notify(State::Warning, None));
+        assert!(NotifyOn::Warning.should_notify(State::Expired, None));
+        assert!(!NotifyOn::Expired.should_notify(State::Warning, None));
+        assert!(NotifyOn::Expired.should_notify(State::Expired, None));
+        assert!(!NotifyOn::Change.should_notify(State::Valid, None));
+        assert!(NotifyOn::Change.should_notify(State::Warning, None));
+        assert!(!NotifyOn::Change.should_notify(State::Warning, Some(State::Warning)));
+        assert!(NotifyOn::Change.should_notify(State::Valid, Some(State::Warning)));
+    }
+
+    #[test]
+    fn t_state() {
+        let now = Utc::now();
+        let checked = |days, tls_version| {
+            ok_checked(
+                "example.com",
+                now,
+                now + chrono::Duration::days(days),
+                tls_version,
+            )
+        };
+        let tls12 = Some(TlsVersion::Tls12);
+        assert_eq!(State::Valid, State::of(&checked(30, tls12), 7, None));
+        assert_eq!(State::Warning, State::of(&checked(3, tls12), 7, None));
+        assert_eq!(State::Expired, State::of(&checked(-1, tls12), 7, None));
+
+        let tls13 = Some(TlsVersion::Tls13);
+        assert_eq!(State::Warning, State::of(&checked(30, tls12), 7, tls13));
+        assert_eq!(State::Valid, State::of(&checked(30, tls13), 7, tls13));
+        assert_eq!(State::Expired, State::of(&checked(-1, tls12), 7, tls13));
+    }
+
+    #[test]
+    fn t_min_tls_version() {
+        assert_eq!(Ok(TlsVersion::Tls13), parse_min_tls_version("1.3"));
+        assert_eq!(Ok(TlsVersion::Tls13), parse_min_tls_version("TLSv1.3"));
+        assert!(parse_min_tls_version("1.2").is_err());
+        assert!(parse_min_tls_version("1.0").is_err());
     }
 
     #[tokio::test]
@@ -264,6 +756,8 @@ mod test {
             let result = CheckedString {
                 inner: &checked,
                 grace_in_days,
+                min_tls_version: None,
+                verbose: false,
             }
             .to_string();
             assert!(result.contains(&format!("expires in {days} day(s)")));
@@ -0,0 +1,250 @@
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::{IpAddr, TcpStream};
+use std::str::FromStr;
+
+use anyhow::{bail, Context as _};
+
+/// Proxy to connect to domain names through, e.g. bastion in front of internal hosts
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Proxy {
+    /// HTTP proxy supporting CONNECT method, e.g. `http://127.0.0.1:3128`
+    Http(String),
+    /// SOCKS5 proxy without authentication, e.g. `socks5://127.0.0.1:1080`.
+    /// Domain name is resolved by proxy
+    Socks5(String),
+}
+
+impl FromStr for Proxy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (scheme, address) = match s.split_once("://") {
+            Some((scheme, address)) => (scheme, address.trim_end_matches('/')),
+            None => {
+                return Err(format!(
+                    "proxy {s} has no scheme, expect http:// or socks5://"
+                ))
+            }
+        };
+        if address.is_empty() {
+            return Err(format!("proxy {s} has no address"));
+        }
+        match scheme {
+            "http" => Ok(Proxy::Http(address.to_string())),
+            "socks5" | "socks5h" => Ok(Proxy::Socks5(address.to_string())),
+            _ => Err(format!(
+                "unknown proxy scheme {scheme}, expect http:// or socks5://"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Proxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Proxy::Http(address) => write!(f, "http://{address}"),
+            Proxy::Socks5(address) => write!(f, "socks5://{address}"),
+        }
+    }
+}
+
+impl Proxy {
+    /// Opens TCP connection to host and port through proxy
+    pub(crate) fn connect(&self, host: &str, port: u16) -> anyhow::Result<TcpStream> {
+        match self {
+            Proxy::Http(address) => {
+                let stream = TcpStream::connect(address.as_str())
+                    .with_context(|| format!("failed to connect to proxy {self}"))?;
+                http_connect(stream, host, port)
+            }
+            Proxy::Socks5(address) => {
+                let stream = TcpStream::connect(address.as_str())
+                    .with_context(|| format!("failed to connect to proxy {self}"))?;
+                socks5_connect(stream, host, port)
+            }
+        }
+    }
+}
+
+/// Host and port in authority form, IPv6 address is bracketed
+fn authority(host: &str, port: u16) -> String {
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V6(ip)) => format!("[{ip}]:{port}"),
+        _ => format!("{host}:{port}"),
+    }
+}
+
+fn http_connect(mut stream: TcpStream, host: &str, port: u16) -> anyhow::Result<TcpStream> {
+    let authority = authority(host, port);
+    write!(
+        stream,
+        "CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n\r\n"
+    )?;
+
+    // read byte by byte so TLS handshake after response headers is left in stream
+    let mut response = vec![];
+    let mut byte = [0u8; 1];
+    while !response.ends_with(b"\r\n\r\n") {
+        if stream.read(&mut byte)? == 0 {
+            bail!("proxy closed connection before response");
+        }
+        response.push(byte[0]);
+        if response.len() > 8192 {
+            bail!("proxy response headers too large");
+        }
+    }
+    let response = String::from_utf8_lossy(&response);
+    let status = response.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(stream),
+        _ => bail!("proxy refused to connect to {authority}: {status}"),
+    }
+}
+
+fn socks5_connect(mut stream: TcpStream, host: &str, port: u16) -> anyhow::Result<TcpStream> {
+    // version 5, one method, no authentication
+    stream.write_all(&[5, 1, 0])?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply)?;
+    if reply != [5, 0] {
+        bail!("SOCKS5 proxy requires unsupported authentication");
+    }
+
+    let mut request = vec![5, 1, 0];
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(1);
+            request.extend(ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            request.push(4);
+            request.extend(ip.octets());
+        }
+        Err(_) => {
+            let len = u8::try_from(host.len()).context("domain name too long for SOCKS5")?;
+            request.push(3);
+            request.push(len);
+            request.extend(host.as_bytes());
+        }
+    }
+    request.extend(port.to_be_bytes());
+    stream.write_all(&request)?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply)?;
+    if reply[1] != 0 {
+        bail!(
+            "SOCKS5 proxy refused to connect to {}: reply {}",
+            authority(host, port),
+            reply[1]
+        );
+    }
+    // skip bound address and port
+    let len = match reply[3] {
+        1 => 4,
+        4 => 16,
+        3 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len)?;
+            len[0] as usize
+        }
+        t => bail!("SOCKS5 proxy replied unknown address type {t}"),
+    };
+    let mut bound = vec![0u8; len + 2];
+    stream.read_exact(&mut bound)?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn t_parse() {
+        assert_eq!(
+            Ok(Proxy::Http("127.0.0.1:3128".into())),
+            "http://127.0.0.1:3128".parse()
+        );
+        assert_eq!(
+            Ok(Proxy::Socks5("bastion:1080".into())),
+            "socks5h://bastion:1080/".parse()
+        );
+        assert!("127.0.0.1:3128".parse::<Proxy>().is_err());
+        assert!("ftp://127.0.0.1".parse::<Proxy>().is_err());
+        assert!("http://".parse::<Proxy>().is_err());
+    }
+
+    #[test]
+    fn t_http_connect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let n = stream.read(&mut request).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\nhello")
+                .unwrap();
+            String::from_utf8_lossy(&request[..n]).to_string()
+        });
+
+        let proxy = Proxy::Http(address.to_string());
+        let mut stream = proxy.connect("example.com", 443).unwrap();
+        let mut tunneled = String::new();
+        stream.read_to_string(&mut tunneled).unwrap();
+        let request = server.join().unwrap();
+        assert!(request.starts_with("CONNECT example.com:443 HTTP/1.1\r\n"));
+        assert_eq!("hello", tunneled);
+    }
+
+    #[test]
+    fn t_http_connect_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            stream.write_all(b"HTTP/1.1 403 Forbidden\r\n\r\n").unwrap();
+        });
+
+        let proxy = Proxy::Http(address.to_string());
+        let error = proxy.connect("example.com", 443).unwrap_err();
+        assert!(error.to_string().contains("403 Forbidden"));
+    }
+
+    #[test]
+    fn t_socks5_connect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut greeting = [0u8; 3];
+            stream.read_exact(&mut greeting).unwrap();
+            stream.write_all(&[5, 0]).unwrap();
+            let mut request = [0u8; 5 + 11 + 2];
+            stream.read_exact(&mut request).unwrap();
+            stream
+                .write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0x01, 0xbb])
+                .unwrap();
+            stream.write_all(b"hello").unwrap();
+            (greeting, request.to_vec())
+        });
+
+        let proxy = Proxy::Socks5(address.to_string());
+        let mut stream = proxy.connect("example.com", 443).unwrap();
+        let mut tunneled = String::new();
+        stream.read_to_string(&mut tunneled).unwrap();
+        let (greeting, request) = server.join().unwrap();
+        assert_eq!([5, 1, 0], greeting);
+        let mut expected = vec![5, 1, 0, 3, 11];
+        expected.extend(b"example.com");
+        expected.extend([0x01, 0xbb]);
+        assert_eq!(expected, request);
+        assert_eq!("hello", tunneled);
+    }
+}
@@ -0,0 +1,257 @@
+use chrono::{DateTime, Datelike as _, Utc};
+use clap::ValueEnum;
+use hcc::{Checked, CheckedInner};
+
+use crate::export::csv_field;
+use crate::state_key;
+
+/// Format of expiry calendar written by report command
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub(crate) enum ReportFormat {
+    /// Human readable table grouped by period
+    #[default]
+    Table,
+    /// Comma-separated values with header
+    Csv,
+    /// iCalendar, one all-day event per expiry
+    Ical,
+}
+
+/// Period which expiries are grouped by
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub(crate) enum Period {
+    /// ISO week e.g. 2023-W05
+    #[default]
+    Week,
+    /// Month e.g. 2023-01
+    Month,
+}
+
+impl Period {
+    fn of(&self, at: DateTime<Utc>) -> String {
+        match self {
+            Period::Week => {
+                let week = at.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+            Period::Month => at.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+/// Certificate expiry of domain name, or of its address
+#[derive(Debug)]
+struct Expiry {
+    name: String,
+    not_after: DateTime<Utc>,
+    days: i64,
+}
+
+/// Certificate expiries soonest first, and domain names failed to check
+#[derive(Debug)]
+pub(crate) struct Calendar {
+    checked_at: DateTime<Utc>,
+    expiries: Vec<Expiry>,
+    failures: Vec<(String, String)>,
+}
+
+impl Calendar {
+    pub(crate) fn new(results: &[Checked<'_>]) -> Self {
+        let mut expiries = vec![];
+        let mut failures = vec![];
+        for checked in results {
+            let name = state_key(checked);
+            match &checked.inner {
+                CheckedInner::Ok { not_after, .. }
+                | CheckedInner::HostnameMismatch { not_after, .. } => expiries.push(Expiry {
+                    name,
+                    not_after: *not_after,
+                    days: (*not_after - checked.checked_at).num_days(),
+                }),
+                CheckedInner::Error { error } => failures.push((name, error.to_string())),
+            }
+        }
+        expiries.sort_by(|a, b| (a.not_after, &a.name).cmp(&(b.not_after, &b.name)));
+        let checked_at = results
+            .iter()
+            .map(|c| c.checked_at)
+            .min()
+            .unwrap_or_else(Utc::now);
+        Calendar {
+            checked_at,
+            expiries,
+            failures,
+        }
+    }
+
+    /// Expiries grouped by period, in order
+    fn groups(&self, period: Period) -> Vec<(String, Vec<&Expiry>)> {
+        let mut groups: Vec<(String, Vec<&Expiry>)> = vec![];
+        for expiry in &self.expiries {
+            let label = period.of(expiry.not_after);
+            match groups.last_mut() {
+                Some((last, expiries)) if *last == label => expiries.push(expiry),
+                _ => groups.push((label, vec![expiry])),
+            }
+        }
+        groups
+    }
+
+    /// Serializes calendar, period is not in effect in iCalendar
+    pub(crate) fn export(&self, format: ReportFormat, period: Period) -> String {
+        match format {
+            ReportFormat::Table => self.table(period),
+            ReportFormat::Csv => self.csv(period),
+            ReportFormat::Ical => self.ical(),
+        }
+    }
+
+    fn table(&self, period: Period) -> String {
+        let width = self
+            .expiries
+            .iter()
+            .map(|e| e.name.len())
+            .chain(self.failures.iter().map(|(n, _)| n.len()))
+            .max()
+            .unwrap_or_default();
+        let mut lines = vec![];
+        for (label, expiries) in self.groups(period) {
+            lines.push(label);
+            for e in expiries {
+                lines.push(format!(
+                    "  {}  {:width$}  {} day(s)",
+                    e.not_after.format("%Y-%m-%d"),
+                    e.name,
+                    e.days
+                ));
+            }
+        }
+        if !self.failures.is_empty() {
+            lines.push("failed to check".to_string());
+            for (name, error) in &self.failures {
+                lines.push(format!("  {name:width$}  {error}"));
+            }
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    fn csv(&self, period: Period) -> String {
+        let mut lines = vec!["period,domain_name,not_after,days,error".to_string()];
+        for (label, expiries) in self.groups(period) {
+            for e in expiries {
+                let fields = [
+                    label.as_str(),
+                    &e.name,
+                    &e.not_after.to_rfc3339(),
+                    &e.days.to_string(),
+                    "",
+                ];
+                let fields = fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>();
+                lines.push(fields.join(","));
+            }
+        }
+        for (name, error) in &self.failures {
+            lines.push(format!(",{},,,{}", csv_field(name), csv_field(error)));
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    fn ical(&self) -> String {
+        let stamp = self.checked_at.format("%Y%m%dT%H%M%SZ");
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//hcc//certificate expiry//EN".to_string(),
+        ];
+        for e in &self.expiries {
+            let name = ical_text(&e.name);
+            lines.extend([
+                "BEGIN:VEVENT".to_string(),
+                format!("UID:{}-{}@hcc", name, e.not_after.timestamp()),
+                format!("DTSTAMP:{stamp}"),
+                format!("DTSTART;VALUE=DATE:{}", e.not_after.format("%Y%m%d")),
+                format!("SUMMARY:Certificate of {name} expires"),
+                format!("DESCRIPTION:Certificate expires at {}", e.not_after),
+                "END:VEVENT".to_string(),
+            ]);
+        }
+        lines.push("END:VCALENDAR".to_string());
+        lines.push(String::new());
+        // lines of iCalendar end with CRLF, RFC 5545
+        lines.join("\r\n")
+    }
+}
+
+/// Escapes TEXT value of iCalendar
+fn ical_text(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test::ok_checked;
+
+    fn checked(domain_name: &'static str, not_after: Option<&str>) -> Checked<'static> {
+        let checked_at = "2023-01-01T00:00:00Z".parse().unwrap();
+        match not_after {
+            Some(not_after) => {
+                ok_checked(domain_name, checked_at, not_after.parse().unwrap(), None)
+            }
+            None => Checked {
+                checked_at,
+                domain_name: domain_name.into(),
+                address: None,
+                inner: CheckedInner::Error {
+                    error: anyhow::anyhow!("refused"),
+                },
+            },
+        }
+    }
+
+    #[test]
+    fn t_calendar() {
+        let calendar = Calendar::new(&[
+            checked("c.com", Some("2023-03-01T00:00:00Z")),
+            checked("b.com", None),
+            checked("long.a.com", Some("2023-01-31T00:00:00Z")),
+            checked("d.com", Some("2023-02-01T00:00:00Z")),
+        ]);
+
+        assert_eq!(
+            "2023-W05\n\
+             \x20 2023-01-31  long.a.com  30 day(s)\n\
+             \x20 2023-02-01  d.com       31 day(s)\n\
+             2023-W09\n\
+             \x20 2023-03-01  c.com       59 day(s)\n\
+             failed to check\n\
+             \x20 b.com       refused\n",
+            calendar.export(ReportFormat::Table, Period::Week)
+        );
+        assert_eq!(
+            "period,domain_name,not_after,days,error\n\
+             2023-01,long.a.com,2023-01-31T00:00:00+00:00,30,\n\
+             2023-02,d.com,2023-02-01T00:00:00+00:00,31,\n\
+             2023-03,c.com,2023-03-01T00:00:00+00:00,59,\n\
+             ,b.com,,,refused\n",
+            calendar.export(ReportFormat::Csv, Period::Month)
+        );
+
+        let ical = calendar.export(ReportFormat::Ical, Period::Week);
+        assert!(ical.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ical.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(3, ical.matches("BEGIN:VEVENT").count());
+        assert!(ical.contains(
+            "DTSTAMP:20230101T000000Z\r\n\
+             DTSTART;VALUE=DATE:20230131\r\n\
+             SUMMARY:Certificate of long.a.com expires\r\n"
+        ));
+        assert_eq!("a\\,b\\;c", ical_text("a,b;c"));
+    }
+}
@@ -0,0 +1,221 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+
+/// Cron of domain name configured on command line e.g. "x.com=0 0 * * * *"
+#[derive(Clone, Debug)]
+pub(crate) struct DomainSchedule {
+    pub(crate) domain_name: String,
+    pub(crate) schedule: Schedule,
+}
+
+impl FromStr for DomainSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (domain_name, cron) = match s.split_once('=') {
+            Some((d, c)) if !d.trim().is_empty() => (d.trim(), c.trim()),
+            _ => return Err(format!("unknown schedule {s}, expect DOMAIN=CRON")),
+        };
+        let schedule =
+            Schedule::from_str(cron).map_err(|e| format!("invalid cron of {domain_name}: {e}"))?;
+        Ok(DomainSchedule {
+            domain_name: domain_name.to_string(),
+            schedule,
+        })
+    }
+}
+
+/// When domain names are checked
+#[derive(Clone, Debug)]
+pub(crate) enum Trigger {
+    /// Run at times of cron
+    Cron(Box<Schedule>),
+    /// Run after interval since last run completed, so runs never overlap
+    Every(Duration),
+}
+
+impl Trigger {
+    /// Next run time after given time
+    fn after(&self, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Trigger::Cron(schedule) => schedule.after(&at).next(),
+            Trigger::Every(interval) => Some(at + chrono::Duration::from_std(*interval).ok()?),
+        }
+    }
+
+    fn is_same(&self, other: &Trigger) -> bool {
+        match (self, other) {
+            (Trigger::Cron(a), Trigger::Cron(b)) => a.to_string() == b.to_string(),
+            (Trigger::Every(a), Trigger::Every(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// Domain names sharing one trigger
+struct Job {
+    trigger: Trigger,
+    domain_names: Vec<String>,
+}
+
+/// Single scheduler over all triggers, keeps next run time of each trigger in priority queue
+pub(crate) struct Scheduler {
+    jobs: Vec<Job>,
+    queue: BinaryHeap<Reverse<(DateTime<Utc>, usize)>>,
+    /// Jobs with interval popped, queued again once their run completes
+    running: Vec<usize>,
+}
+
+impl Scheduler {
+    /// Groups domain names by trigger, domain names without their own cron follow default one
+    pub(crate) fn new<T>(
+        default: Trigger,
+        domain_names: &[T],
+        schedules: &[DomainSchedule],
+        now: DateTime<Utc>,
+    ) -> Self
+    where
+        T: AsRef<str>,
+    {
+        let mut jobs: Vec<Job> = vec![];
+        let mut add = |trigger: Trigger, domain_name: &str| match jobs
+            .iter_mut()
+            .find(|j| j.trigger.is_same(&trigger))
+        {
+            Some(job) => job.domain_names.push(domain_name.to_string()),
+            None => jobs.push(Job {
+                trigger,
+                domain_names: vec![domain_name.to_string()],
+            }),
+        };
+        for domain_name in domain_names {
+            let domain_name = domain_name.as_ref();
+            if !schedules.iter().any(|s| s.domain_name == domain_name) {
+                add(default.clone(), domain_name);
+            }
+        }
+        for s in schedules {
+            add(Trigger::Cron(Box::new(s.schedule.clone())), &s.domain_name);
+        }
+
+        let mut queue = BinaryHeap::new();
+        for (i, job) in jobs.iter().enumerate() {
+            if let Some(next) = job.trigger.after(now) {
+                queue.push(Reverse((next, i)));
+            }
+        }
+        Scheduler {
+            jobs,
+            queue,
+            running: vec![],
+        }
+    }
+
+    /// Pops next run time with domain names due then, triggers due at same time are merged.
+    /// Jobs with interval are not due again until [`Scheduler::complete`]
+    pub(crate) fn next(&mut self) -> Option<(DateTime<Utc>, Vec<String>)> {
+        let Reverse((at, _)) = *self.queue.peek()?;
+        let mut domain_names = vec![];
+        while let Some(&Reverse((next, i))) = self.queue.peek() {
+            if next != at {
+                break;
+            }
+            self.queue.pop();
+            let job = &self.jobs[i];
+            domain_names.extend(job.domain_names.iter().cloned());
+            match job.trigger {
+                Trigger::Cron(_) => {
+                    if let Some(next) = job.trigger.after(at) {
+                        self.queue.push(Reverse((next, i)));
+                    }
+                }
+                Trigger::Every(_) => self.running.push(i),
+            }
+        }
+        Some((at, domain_names))
+    }
+
+    /// Queues jobs with interval popped by last [`Scheduler::next`], counted from completion
+    pub(crate) fn complete(&mut self, now: DateTime<Utc>) {
+        for i in self.running.drain(..) {
+            if let Some(next) = self.jobs[i].trigger.after(now) {
+                self.queue.push(Reverse((next, i)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_scheduler() {
+        let hourly: DomainSchedule = "a.com=0 0 * * * *".parse().unwrap();
+        assert_eq!("a.com", hourly.domain_name);
+        assert!("0 0 * * * *".parse::<DomainSchedule>().is_err());
+        assert!("a.com=every hour".parse::<DomainSchedule>().is_err());
+
+        let daily = Trigger::Cron(Box::new(Schedule::from_str("0 0 0 * * *").unwrap()));
+        let now: DateTime<Utc> = "2023-01-01T22:30:00Z".parse().unwrap();
+        let mut scheduler = Scheduler::new(daily, &["a.com", "b.com", "c.com"], &[hourly], now);
+
+        let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(
+            Some((at("2023-01-01T23:00:00Z"), vec!["a.com".to_string()])),
+            scheduler.next()
+        );
+        let (next, mut domain_names) = scheduler.next().unwrap();
+        domain_names.sort();
+        assert_eq!(at("2023-01-02T00:00:00Z"), next);
+        assert_eq!(vec!["a.com", "b.com", "c.com"], domain_names);
+        assert_eq!(
+            Some((at("2023-01-02T01:00:00Z"), vec!["a.com".to_string()])),
+            scheduler.next()
+        );
+    }
+
+    #[test]
+    fn t_scheduler_every() {
+        let hourly: DomainSchedule = "a.com=0 0 * * * *".parse().unwrap();
+        let every = Trigger::Every(Duration::from_secs(6 * 3600));
+        let now: DateTime<Utc> = "2023-01-01T22:30:00Z".parse().unwrap();
+        let mut scheduler = Scheduler::new(every, &["a.com", "b.com"], &[hourly], now);
+
+        let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+        let b = || vec!["b.com".to_string()];
+        for _ in 0..6 {
+            assert_eq!(
+                Some(vec!["a.com".to_string()]),
+                scheduler.next().map(|n| n.1)
+            );
+        }
+        assert_eq!(Some((at("2023-01-02T04:30:00Z"), b())), scheduler.next());
+        // next run counts from completion of slow run, instead of when it started
+        scheduler.complete(at("2023-01-02T04:40:00Z"));
+        for _ in 0..6 {
+            assert_eq!(
+                Some(vec!["a.com".to_string()]),
+                scheduler.next().map(|n| n.1)
+            );
+        }
+        assert_eq!(Some((at("2023-01-02T10:40:00Z"), b())), scheduler.next());
+        assert!(scheduler.next().is_some());
+
+        let mut scheduler = Scheduler::new(
+            Trigger::Every(Duration::from_secs(60)),
+            &["b.com"],
+            &[],
+            now,
+        );
+        assert!(scheduler.next().is_some());
+        assert!(scheduler.next().is_none());
+        scheduler.complete(now);
+        assert!(scheduler.next().is_some());
+    }
+}
@@ -0,0 +1,446 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{bail, Context as _};
+use futures::future::{BoxFuture, FutureExt as _};
+use hcc::{Checked, CheckedInner};
+use log::{debug, info, warn};
+use pushover::{Attachment, Notification};
+
+use crate::chart;
+use crate::export::OutputFormat;
+use crate::State;
+
+/// Check result handed to sinks
+pub(crate) struct Output<'a> {
+    /// Check result
+    pub(crate) checked: &'a Checked<'a>,
+    /// Human readable message
+    pub(crate) message: String,
+    /// State of certificate
+    pub(crate) state: State,
+    /// Whether result passes notification filter
+    pub(crate) notify: bool,
+}
+
+/// Destination of check results of each daemon run
+pub(crate) trait OutputSink: Send + Sync {
+    /// Name shown in logs and failures
+    fn name(&self) -> String;
+
+    /// Emits check results of one run, blocking I/O is done off the async runtime
+    fn emit<'a>(&'a self, outputs: &'a [Output<'a>]) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+/// Sink configured on command line e.g. stdout, json:/path/to/file, webhook:https://x.com, pushover
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum SinkConfig {
+    Stdout,
+    Json(PathBuf),
+    Csv(PathBuf),
+    Junit(PathBuf),
+    Webhook(String),
+    Pushover,
+}
+
+impl FromStr for SinkConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("json", path)) if !path.is_empty() => Ok(SinkConfig::Json(path.into())),
+            Some(("csv", path)) if !path.is_empty() => Ok(SinkConfig::Csv(path.into())),
+            Some(("junit", path)) if !path.is_empty() => Ok(SinkConfig::Junit(path.into())),
+            Some(("webhook", url)) if !url.is_empty() => Ok(SinkConfig::Webhook(url.into())),
+            None if s == "stdout" => Ok(SinkConfig::Stdout),
+            None if s == "pushover" => Ok(SinkConfig::Pushover),
+            _ => Err(format!(
+                "unknown sink {s}, expect stdout, json:PATH, csv:PATH, junit:PATH, webhook:URL or pushover"
+            )),
+        }
+    }
+}
+
+impl SinkConfig {
+    /// Builds sink, Pushover sink requires token and user, and summarizes results in one
+    /// notification when digest is set, with chart attached when chart is set.
+    /// Webhook sink signs payload when secret is set
+    pub(crate) fn build(
+        &self,
+        pushover: Option<(&str, &str)>,
+        digest: bool,
+        chart: bool,
+        webhook_secret: Option<&str>,
+    ) -> anyhow::Result<Box<dyn OutputSink>> {
+        Ok(match self {
+            SinkConfig::Stdout => Box::new(StdoutSink),
+            SinkConfig::Json(path) => Box::new(JsonFileSink { path: path.clone() }),
+            SinkConfig::Csv(path) => Box::new(ExportFileSink {
+                path: path.clone(),
+                format: OutputFormat::Csv,
+            }),
+            SinkConfig::Junit(path) => Box::new(ExportFileSink {
+                path: path.clone(),
+                format: OutputFormat::Junit,
+            }),
+            SinkConfig::Webhook(url) => Box::new(WebhookSink {
+                url: url.clone(),
+                secret: webhook_secret.map(String::from),
+            }),
+            SinkConfig::Pushover => match pushover {
+                Some((token, user)) => Box::new(PushoverSink {
+                    token: token.to_string(),
+                    user: user.to_string(),
+                    digest,
+                    chart,
+                }),
+                None => bail!("pushover sink requires Pushover token and user"),
+            },
+        })
+    }
+}
+
+/// Prints every result to standard output
+struct StdoutSink;
+
+impl OutputSink for StdoutSink {
+    fn name(&self) -> String {
+        "stdout".to_string()
+    }
+
+    fn emit<'a>(&'a self, outputs: &'a [Output<'a>]) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            for output in outputs {
+                println!("{}", output.message);
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+/// Overwrites file with results of latest run in JSON
+struct JsonFileSink {
+    path: PathBuf,
+}
+
+impl OutputSink for JsonFileSink {
+    fn name(&self) -> String {
+        format!("json:{}", self.path.display())
+    }
+
+    fn emit<'a>(&'a self, outputs: &'a [Output<'a>]) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let results = outputs.iter().map(|o| o.checked).collect::<Vec<_>>();
+            let json = serde_json::to_vec_pretty(&results)?;
+            write_file_blocking(self.path.clone(), json).await
+        }
+        .boxed()
+    }
+}
+
+/// Overwrites file with results of latest run in CSV or JUnit XML
+struct ExportFileSink {
+    path: PathBuf,
+    format: OutputFormat,
+}
+
+impl OutputSink for ExportFileSink {
+    fn name(&self) -> String {
+        let format = match self.format {
+            OutputFormat::Text => "text",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Junit => "junit",
+        };
+        format!("{format}:{}", self.path.display())
+    }
+
+    fn emit<'a>(&'a self, outputs: &'a [Output<'a>]) -> BoxFuture<'a, anyhow::Result<()>> {
+        let content = self.format.export(outputs);
+        write_file_blocking(self.path.clone(), content.into_bytes()).boxed()
+    }
+}
+
+/// Writes to temporary file then renames, so readers never see partial content
+pub(crate) fn write_file<C: AsRef<[u8]>>(path: &Path, content: C) -> anyhow::Result<()> {
+    let mut tmp = path.to_path_buf().into_os_string();
+    tmp.push(".tmp");
+    fs::write(&tmp, content).with_context(|| format!("failed to write {tmp:?}"))?;
+    fs::rename(&tmp, path).with_context(|| format!("failed to rename {tmp:?} to {path:?}"))?;
+    debug!("results written to {path:?}");
+    Ok(())
+}
+
+/// Writes file on blocking thread, so async runtime is not blocked by slow storage
+async fn write_file_blocking(path: PathBuf, content: Vec<u8>) -> anyhow::Result<()> {
+    tokio::task::spawn_blocking(move || write_file(&path, content)).await?
+}
+
+/// Header carrying HMAC-SHA256 of webhook payload e.g. sha256=0123...
+pub(crate) const SIGNATURE_HEADER: &str = "X-HCC-Signature";
+
+/// Signs payload with shared secret, so receivers could authenticate origin
+pub(crate) fn sign(secret: &str, payload: &[u8]) -> String {
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = ring::hmac::sign(&key, payload);
+    let hex = tag
+        .as_ref()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>();
+    format!("sha256={hex}")
+}
+
+/// Posts results of each run to URL in JSON, signed when secret is set
+struct WebhookSink {
+    url: String,
+    secret: Option<String>,
+}
+
+impl OutputSink for WebhookSink {
+    fn name(&self) -> String {
+        format!("webhook:{}", self.url)
+    }
+
+    fn emit<'a>(&'a self, outputs: &'a [Output<'a>]) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let results = outputs.iter().map(|o| o.checked).collect::<Vec<_>>();
+            let payload = serde_json::to_vec(&results)?;
+            let mut req = ureq::post(&self.url)
+                .timeout(Duration::from_secs(30))
+                .set("Content-Type", "application/json");
+            if let Some(ref secret) = self.secret {
+                req = req.set(SIGNATURE_HEADER, &sign(secret, &payload));
+            }
+            // ureq blocks until webhook responds
+            let res =
+                tokio::task::spawn_blocking(move || req.send_bytes(&payload).map_err(Box::new))
+                    .await??;
+            debug!("webhook responds {}", res.status());
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+/// Summarizes results grouped by ok, warning, expired and error, with soonest expiry
+pub(crate) fn digest(outputs: &[Output<'_>]) -> String {
+    let mut groups: [(&str, Vec<String>); 4] = [
+        ("ok", vec![]),
+        ("warning", vec![]),
+        ("expired", vec![]),
+        ("error", vec![]),
+    ];
+    let mut soonest: Option<&Output<'_>> = None;
+    for output in outputs {
+        let group = match (&output.checked.inner, output.state) {
+            (CheckedInner::Ok { .. }, State::Valid) => 0,
+            (CheckedInner::Ok { .. }, State::Warning) => 1,
+            (CheckedInner::Ok { .. }, State::Expired) => 2,
+            _ => 3,
+        };
+        groups[group].1.push(output.checked.domain_name.to_string());
+        if let CheckedInner::Ok { not_after, .. } = &output.checked.inner {
+            match soonest.map(|o| &o.checked.inner) {
+                Some(CheckedInner::Ok { not_after: n, .. }) if n <= not_after => {}
+                _ => soonest = Some(output),
+            }
+        }
+    }
+
+    let counts = groups
+        .iter()
+        .map(|(name, names)| format!("{} {name}", names.len()))
+        .collect::<Vec<String>>();
+    let mut lines = vec![format!(
+        "{} result(s): {}",
+        outputs.len(),
+        counts.join(", ")
+    )];
+    // healthy domain names are only counted to keep message short
+    for (name, names) in groups.iter().skip(1).filter(|(_, n)| !n.is_empty()) {
+        lines.push(format!("{name}: {}", names.join(", ")));
+    }
+    if let Some(output) = soonest {
+        if let CheckedInner::Ok { not_after, .. } = &output.checked.inner {
+            let domain_name = &output.checked.domain_name;
+            lines.push(format!("soonest expiry: {domain_name} at {not_after}"));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Chart of days remaining per domain name attached to digest, None if failed to render
+pub(crate) fn digest_chart(outputs: &[Output<'_>]) -> Option<Attachment<'static>> {
+    match chart::render(outputs) {
+        Ok(png) => Some(Attachment::new("expiry.png", mime::IMAGE_PNG, &png)),
+        Err(e) => {
+            warn!("failed to render chart, send digest without it: {e}");
+            None
+        }
+    }
+}
+
+/// Sends results passing notification filter to Pushover, one by one or in a digest
+struct PushoverSink {
+    token: String,
+    user: String,
+    digest: bool,
+    chart: bool,
+}
+
+impl OutputSink for PushoverSink {
+    fn name(&self) -> String {
+        "pushover".to_string()
+    }
+
+    fn emit<'a>(&'a self, outputs: &'a [Output<'a>]) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            if self.digest {
+                if !outputs.iter().any(|o| o.notify) {
+                    return Ok(());
+                }
+                let message = digest(outputs);
+                let attachment = self.chart.then(|| digest_chart(outputs)).flatten();
+                let mut notification =
+                    Notification::new(self.token.as_str(), self.user.as_str(), &message);
+                notification.attachment = attachment.as_ref();
+                let res = notification.send_checked().await?;
+                debug!("pushover response {res:?}");
+                info!("digest of {} result(s) sent", outputs.len());
+                return Ok(());
+            }
+
+            let mut errors = vec![];
+            for output in outputs.iter().filter(|o| o.notify) {
+                let notification =
+                    Notification::new(self.token.as_str(), self.user.as_str(), &output.message);
+                match notification.send_checked().await {
+                    Ok(res) => debug!("pushover response {res:?}"),
+                    Err(e) => errors.push(e.to_string()),
+                }
+            }
+            if !errors.is_empty() {
+                bail!(errors.join(", "));
+            }
+            info!(
+                "{} notification(s) sent",
+                outputs.iter().filter(|o| o.notify).count()
+            );
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::Utc;
+    use hcc::Checked;
+
+    use crate::test::ok_checked;
+
+    #[test]
+    fn t_sink_config() {
+        assert_eq!(Ok(SinkConfig::Stdout), "stdout".parse());
+        assert_eq!(Ok(SinkConfig::Pushover), "pushover".parse());
+        assert_eq!(
+            Ok(SinkConfig::Json("/tmp/hcc.json".into())),
+            "json:/tmp/hcc.json".parse()
+        );
+        assert_eq!(
+            Ok(SinkConfig::Webhook("https://x.com/hook".into())),
+            "webhook:https://x.com/hook".parse()
+        );
+        assert_eq!(
+            Ok(SinkConfig::Junit("/tmp/hcc.xml".into())),
+            "junit:/tmp/hcc.xml".parse()
+        );
+        assert!("json:".parse::<SinkConfig>().is_err());
+        assert!("csv:".parse::<SinkConfig>().is_err());
+        assert!("unknown".parse::<SinkConfig>().is_err());
+        assert!(SinkConfig::Pushover
+            .build(None, false, false, None)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn t_json_file_sink() {
+        let path = std::env::temp_dir().join("hcc-json-file-sink.json");
+        let checked = Checked {
+            checked_at: Utc::now(),
+            domain_name: "example.com".into(),
+            address: None,
+            inner: CheckedInner::Error {
+                error: anyhow::anyhow!("connection refused"),
+            },
+        };
+        let outputs = vec![Output {
+            checked: &checked,
+            message: "example.com: connection refused".into(),
+            state: State::Expired,
+            notify: true,
+        }];
+        let sink = SinkConfig::Json(path.clone())
+            .build(None, false, false, None)
+            .unwrap();
+        sink.emit(&outputs).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!("example.com", json[0]["domain_name"]);
+        assert_eq!("connection refused", json[0]["error"]);
+    }
+
+    #[test]
+    fn t_sign() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            sign("Jefe", b"what do ya want for nothing?")
+        );
+    }
+
+    #[test]
+    fn t_digest() {
+        let now = Utc::now();
+        let ok = |domain_name, days| {
+            ok_checked(domain_name, now, now + chrono::Duration::days(days), None)
+        };
+        let a = ok("a.com", 30);
+        let b = ok("b.com", 3);
+        let c = Checked {
+            checked_at: now,
+            domain_name: "c.com".into(),
+            address: None,
+            inner: CheckedInner::Error {
+                error: anyhow::anyhow!("connection refused"),
+            },
+        };
+        let output = |checked, state| Output {
+            checked,
+            message: String::new(),
+            state,
+            notify: true,
+        };
+        let outputs = vec![
+            output(&a, State::Valid),
+            output(&b, State::Warning),
+            output(&c, State::Expired),
+        ];
+        let digest = digest(&outputs);
+        let lines = digest.lines().collect::<Vec<&str>>();
+        assert_eq!(
+            vec![
+                "3 result(s): 1 ok, 1 warning, 0 expired, 1 error",
+                "warning: b.com",
+                "error: c.com",
+            ],
+            lines[..3]
+        );
+        assert!(lines[3].starts_with("soonest expiry: b.com at "));
+    }
+}
@@ -31,16 +31,32 @@
 //! ```
 //! $ po -h
 //! ```
+//!
+//! Exit codes,
+//!
+//! - 0: notification is sent
+//! - 1: other errors e.g. invalid arguments or attachment
+//! - 2: authentication error e.g. invalid token or user key
+//! - 3: network error e.g. timeout, rate limit or server error, retried with `--retry`
+//! - 4: Pushover API rejects the notification
 
-use anyhow::bail;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 
 use clap::Parser;
-use log::{debug, Level};
+use log::{debug, warn, Level};
 use logging_timer::{finish, stimer};
 
-use pushover::{Attachment, Monospace, Notification, Priority, Sound, HTML};
+use pushover::{Attachment, Monospace, Notification, NotificationError, Priority, Sound, HTML};
+
+const EXIT_OTHER: i32 = 1;
+const EXIT_AUTH: i32 = 2;
+const EXIT_NETWORK: i32 = 3;
+const EXIT_API: i32 = 4;
+
+const PRIORITIES: &str = "-2, -1, 0, 1, 2, lowest, low, normal, high or emergency";
+const SOUNDS: &str = "one of https://pushover.net/api#sounds";
 
 #[doc(hidden)]
 #[derive(Parser)]
@@ -89,32 +105,109 @@ struct Opts {
     /// A title for your supplementary URL, otherwise just the URL is shown. <https://pushover.net/api#urls>
     #[arg(long)]
     url_title: Option<String>,
+    /// Split message longer than 1024 characters into a numbered series of notifications. <https://pushover.net/api#limits>
+    #[arg(long)]
+    split_long_messages: bool,
+    /// Times to retry when network error occurs. Not allowed with `--split-long-messages`,
+    /// since retry would send parts delivered already again.
+    #[arg(long, default_value = "0", conflicts_with = "split_long_messages")]
+    retry: u32,
+    /// Delay between retries in seconds.
+    #[arg(long, default_value = "1")]
+    retry_delay: u64,
+}
+
+/// Parses numeric or named form of field, instead of silently ignoring invalid value.
+fn parse_field<T: FromStr>(field: &str, value: &str, expected: &str) -> anyhow::Result<T> {
+    match T::from_str(value.trim()) {
+        Ok(v) => Ok(v),
+        Err(_) => anyhow::bail!("invalid {field}: {value}, expected {expected}"),
+    }
+}
+
+/// Classifies error into exit code, see module documentation.
+fn exit_code(error: &anyhow::Error) -> i32 {
+    let error = match error.downcast_ref::<NotificationError>() {
+        Some(e) => e,
+        None => return EXIT_OTHER,
+    };
+    match error {
+        NotificationError::Api { errors, .. } => {
+            let is_auth = errors.iter().any(|e| {
+                let e = e.to_lowercase();
+                (e.contains("token") || e.contains("user"))
+                    && (e.contains("invalid") || e.contains("not a valid"))
+            });
+            if is_auth {
+                EXIT_AUTH
+            } else {
+                EXIT_API
+            }
+        }
+        NotificationError::UReq(e) => match e.as_ref() {
+            ureq::Error::Status(401 | 403, _) => EXIT_AUTH,
+            ureq::Error::Status(code, _) if *code == 429 || *code >= 500 => EXIT_NETWORK,
+            ureq::Error::Status(_, _) => EXIT_API,
+            ureq::Error::Transport(_) => EXIT_NETWORK,
+        },
+        NotificationError::Io(_) => EXIT_NETWORK,
+        _ => EXIT_OTHER,
+    }
+}
+
+/// Prints error of command line arguments and exits, with 0 for `--help` or `--version`
+/// and [`EXIT_OTHER`] for invalid arguments, since clap exits with 2 which means authentication error.
+fn exit_parse_error(error: clap::Error) -> ! {
+    let _ = error.print();
+    std::process::exit(parse_error_code(&error));
+}
+
+fn parse_error_code(error: &clap::Error) -> i32 {
+    if error.use_stderr() {
+        EXIT_OTHER
+    } else {
+        0
+    }
 }
 
 #[doc(hidden)]
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    use std::io::Read as _;
-
+async fn main() {
     pretty_env_logger::init();
 
-    let opts: Opts = Opts::parse();
+    let opts = match Opts::try_parse() {
+        Ok(opts) => opts,
+        Err(e) => exit_parse_error(e),
+    };
+
+    if let Err(e) = run(&opts).await {
+        eprintln!("Error: {e:?}");
+        std::process::exit(exit_code(&e));
+    }
+}
+
+async fn run(opts: &Opts) -> anyhow::Result<()> {
+    use std::io::Read as _;
 
     let mut notification = Notification::new(&opts.token, &opts.user, &opts.message);
     notification.device = opts.device.as_deref();
     notification.title = opts.title.as_deref();
     notification.timestamp = opts.timestamp;
-    notification.priority = opts
-        .priority
-        .as_deref()
-        .and_then(|p| Priority::from_str(p).ok());
-    notification.sound = opts.sound.as_deref().and_then(|s| Sound::from_str(s).ok());
+    notification.priority = match opts.priority.as_deref() {
+        Some(p) => Some(parse_field::<Priority>("priority", p, PRIORITIES)?),
+        None => None,
+    };
+    notification.sound = match opts.sound.as_deref() {
+        Some(s) => Some(parse_field::<Sound>("sound", s, SOUNDS)?),
+        None => None,
+    };
 
     notification.url = opts.url.as_deref();
     notification.url_title = opts.url_title.as_deref();
 
     notification.html = opts.html.then(|| HTML::HTML);
     notification.monospace = opts.monospace.then(|| Monospace::Monospace);
+    notification.split_long_messages = opts.split_long_messages;
 
     let attachment = if let Some(ref p) = opts.file {
         debug!("load attachment from {p:?}");
@@ -130,12 +223,24 @@ async fn main() -> anyhow::Result<()> {
     notification.attachment = attachment.as_ref();
 
     let tmr = stimer!(Level::Debug; "NOTIFY");
-    let res = notification.send().await?;
+    let mut attempt = 0;
+    let res = loop {
+        match notification.send_checked().await {
+            Ok(res) => break res,
+            Err(e) => {
+                let e = anyhow::Error::from(e);
+                if attempt >= opts.retry || exit_code(&e) != EXIT_NETWORK {
+                    return Err(e);
+                }
+                attempt += 1;
+                warn!("retry {attempt}/{} because of {e}", opts.retry);
+                tokio::time::sleep(Duration::from_secs(opts.retry_delay)).await;
+            }
+        }
+    };
     finish!(tmr);
 
-    if res.status != 1 {
-        bail!(format!("{res:?}"));
-    } else if opts.verbose {
+    if opts.verbose {
         println!("{res:?}");
     }
     Ok(())
@@ -145,7 +250,7 @@ async fn main() -> anyhow::Result<()> {
 mod tests {
     use clap::Parser;
 
-    use crate::Opts;
+    use super::*;
 
     #[test]
     fn test_negative_priority() {
@@ -177,4 +282,92 @@ mod tests {
         .unwrap();
         assert_eq!(parsed.priority, Some("-1".to_string()));
     }
+
+    #[test]
+    fn t_parse_field() {
+        assert_eq!(
+            Priority::High,
+            parse_field::<Priority>("priority", "1", PRIORITIES).unwrap()
+        );
+        assert_eq!(
+            Priority::Lowest,
+            parse_field::<Priority>("priority", " Lowest ", PRIORITIES).unwrap()
+        );
+        assert_eq!(
+            Sound::CashRegister,
+            parse_field::<Sound>("sound", "CashRegister", SOUNDS).unwrap()
+        );
+        let e = parse_field::<Priority>("priority", "3", PRIORITIES).unwrap_err();
+        assert_eq!(
+            format!("invalid priority: 3, expected {PRIORITIES}"),
+            e.to_string()
+        );
+        let e = parse_field::<Sound>("sound", "beep", SOUNDS).unwrap_err();
+        assert_eq!(1, exit_code(&e));
+    }
+
+    #[test]
+    fn t_exit_code() {
+        let api = |errors: &[&str]| {
+            anyhow::Error::from(NotificationError::Api {
+                errors: errors.iter().map(|e| e.to_string()).collect(),
+                request: "request".into(),
+            })
+        };
+        assert_eq!(
+            EXIT_AUTH,
+            exit_code(&api(&["application token is invalid"]))
+        );
+        assert_eq!(
+            EXIT_AUTH,
+            exit_code(&api(&[
+                "user identifier is not a valid user, group, or subscribed user key"
+            ]))
+        );
+        assert_eq!(EXIT_API, exit_code(&api(&["message cannot be blank"])));
+        assert_eq!(
+            EXIT_NETWORK,
+            exit_code(&NotificationError::Io(std::io::ErrorKind::TimedOut.into()).into())
+        );
+        let status = |code: u16| {
+            let response = ureq::Response::new(code, "status", "").unwrap();
+            let error = Box::new(ureq::Error::Status(code, response));
+            anyhow::Error::from(NotificationError::UReq(error))
+        };
+        // rate limit and server errors are retried with --retry
+        assert_eq!(EXIT_NETWORK, exit_code(&status(429)));
+        assert_eq!(EXIT_NETWORK, exit_code(&status(503)));
+        assert_eq!(EXIT_AUTH, exit_code(&status(401)));
+        assert_eq!(EXIT_API, exit_code(&status(400)));
+        assert_eq!(EXIT_OTHER, exit_code(&anyhow::anyhow!("other")));
+    }
+
+    #[test]
+    fn t_parse_error_code() {
+        let code = |args: &[&str]| match Opts::try_parse_from(args) {
+            Ok(_) => panic!("{args:?} should not be parsed"),
+            Err(e) => parse_error_code(&e),
+        };
+        assert_eq!(0, code(&["po", "--help"]));
+        assert_eq!(0, code(&["po", "--version"]));
+        assert_eq!(EXIT_OTHER, code(&["po", "-t", "token", "-u", "user"]));
+        assert_eq!(EXIT_OTHER, code(&["po", "--unknown"]));
+    }
+
+    #[test]
+    fn t_retry_with_split_long_messages() {
+        let args = ["--", "-t", "token", "-u", "user", "-m", "message"];
+        let parse = |extra: &[&str]| Opts::try_parse_from(args.iter().chain(extra));
+        assert_eq!(3, parse(&["--retry", "3"]).unwrap().retry);
+        assert!(
+            parse(&["--split-long-messages"])
+                .unwrap()
+                .split_long_messages
+        );
+        let e = match parse(&["--retry", "3", "--split-long-messages"]) {
+            Ok(_) => panic!("--retry should conflict with --split-long-messages"),
+            Err(e) => e,
+        };
+        assert_eq!(clap::error::ErrorKind::ArgumentConflict, e.kind());
+    }
 }
@@ -3,6 +3,7 @@ use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 use std::str::FromStr as _;
+use std::time::Duration;
 
 use log::debug;
 use mime::Mime;
@@ -24,6 +25,71 @@ pub enum AttachmentError {
     /// Failed to infer MIME type, no extra information.
     #[error("unknown MIME type")]
     Infer,
+    /// Attachment is larger than [`FetchLimits::max_size`] in bytes.
+    #[error("attachment exceeds {0} bytes")]
+    TooLarge(u64),
+    /// Scheme or host of URL is not allowed by [`FetchLimits`].
+    #[error("attachment URL not allowed: {0}")]
+    NotAllowed(String),
+    /// URL of attachment responds with status other than 2xx.
+    #[error("attachment URL responds {0}")]
+    Status(u16),
+}
+
+/// Attachment size limit of Pushover API in bytes. <https://pushover.net/api#attachments>
+pub const MAX_ATTACHMENT_SIZE: u64 = 5 * 1024 * 1024;
+
+/// Safeguards of [`Attachment::from_url_with_limits`], so URLs given by others
+/// could not exhaust memory or reach unexpected hosts.
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use pushover::FetchLimits;
+/// let limits = FetchLimits {
+///     max_size: 1024 * 1024,
+///     hosts: Some(vec!["images.example.com".into()]),
+///     timeout: Duration::from_secs(5),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Debug)]
+pub struct FetchLimits {
+    /// Maximum size of attachment in bytes, [`MAX_ATTACHMENT_SIZE`] by default.
+    pub max_size: u64,
+    /// Allowed schemes of URL, `http` and `https` by default.
+    pub schemes: Vec<String>,
+    /// Allowed hosts of URL, any host if not set.
+    pub hosts: Option<Vec<String>>,
+    /// Timeout of download, from connecting to reading whole body, 30 seconds by default.
+    pub timeout: Duration,
+}
+
+impl Default for FetchLimits {
+    fn default() -> Self {
+        Self {
+            max_size: MAX_ATTACHMENT_SIZE,
+            schemes: vec!["http".into(), "https".into()],
+            hosts: None,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl FetchLimits {
+    /// Checks scheme and host of URL.
+    fn check(&self, url: &Url) -> Result<(), AttachmentError> {
+        let scheme = url.scheme();
+        if !self.schemes.iter().any(|s| s.eq_ignore_ascii_case(scheme)) {
+            return Err(AttachmentError::NotAllowed(format!("scheme {scheme}")));
+        }
+        let host = url.host_str().unwrap_or_default();
+        match self.hosts {
+            Some(ref hosts) if !hosts.iter().any(|h| h.eq_ignore_ascii_case(host)) => {
+                Err(AttachmentError::NotAllowed(format!("host {host}")))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 /// Notification attachment. Image in most cases.
@@ -75,6 +141,14 @@ impl<'a> Attachment<'a> {
 
     /// Creates an [`Attachment`] from path.
     pub async fn from_path<T>(path: T) -> Result<Attachment<'a>, AttachmentError>
+    where
+        T: AsRef<Path>,
+    {
+        Self::from_path_blocking(path)
+    }
+
+    /// Creates an [`Attachment`] from path without async runtime.
+    pub fn from_path_blocking<T>(path: T) -> Result<Attachment<'a>, AttachmentError>
     where
         T: AsRef<Path>,
     {
@@ -92,20 +166,61 @@ impl<'a> Attachment<'a> {
         Ok(Self::new(filename.to_owned(), mime, &buffer))
     }
 
-    /// Creates an [`Attachment`] from URL.
+    /// Creates an [`Attachment`] from URL with default [`FetchLimits`].
     pub async fn from_url<T>(url: T) -> Result<Attachment<'a>, AttachmentError>
+    where
+        T: AsRef<str> + std::fmt::Display,
+    {
+        Self::from_url_with_limits(url, &FetchLimits::default()).await
+    }
+
+    /// Creates an [`Attachment`] from URL, rejecting URL not allowed by [`FetchLimits`]
+    /// and download exceeding size limit, whether told by `Content-Length` or not.
+    pub async fn from_url_with_limits<T>(
+        url: T,
+        limits: &FetchLimits,
+    ) -> Result<Attachment<'a>, AttachmentError>
     where
         T: AsRef<str> + std::fmt::Display,
     {
         let parsed = Url::parse(url.as_ref())?;
+        limits.check(&parsed)?;
         let filename = parsed
             .path_segments()
-            .map_or("untitled", |s| s.last().map_or("untitled", |s| s));
-        let res = ureq::get(parsed.as_str())
+            .map_or("untitled", |mut s| s.next_back().map_or("untitled", |s| s));
+        // redirects could lead to hosts not allowed, so they are not followed
+        let agent = ureq::AgentBuilder::new()
+            .timeout(limits.timeout)
+            .redirects(0)
+            .build();
+        let res = agent
+            .get(parsed.as_str())
             .call()
             .map_err(|e| AttachmentError::UReq(Box::new(e)))?;
+        match res.status() {
+            200..=299 => {}
+            300..=399 => {
+                let location = res.header("Location").unwrap_or_default();
+                return Err(AttachmentError::NotAllowed(format!(
+                    "redirects are not followed, to {location:?}"
+                )));
+            }
+            status => return Err(AttachmentError::Status(status)),
+        }
+        let length = res
+            .header("Content-Length")
+            .and_then(|l| l.parse::<u64>().ok());
+        if length.map_or(false, |l| l > limits.max_size) {
+            return Err(AttachmentError::TooLarge(limits.max_size));
+        }
         let mut buffer = Vec::new();
-        res.into_reader().read_to_end(&mut buffer)?;
+        // read one more byte than limit to tell whether body exceeds it
+        res.into_reader()
+            .take(limits.max_size + 1)
+            .read_to_end(&mut buffer)?;
+        if buffer.len() as u64 > limits.max_size {
+            return Err(AttachmentError::TooLarge(limits.max_size));
+        }
         let inferred = infer::get(&buffer).ok_or(AttachmentError::Infer)?;
         let mime = Mime::from_str(inferred.mime_type()).map_err(|_e| AttachmentError::Infer)?;
         let bytes = buffer.len();
@@ -127,6 +242,18 @@ mod tests {
         Attachment::new("untitled", Mime::from_str("plain/text").unwrap(), &[]);
     }
 
+    #[test]
+    fn t_attachment_from_path_blocking() {
+        let body = &[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+        let path = std::env::temp_dir().join("pushover-from-path-blocking.png");
+        std::fs::write(&path, body).unwrap();
+        let a = Attachment::from_path_blocking(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!("pushover-from-path-blocking.png", a.filename);
+        assert_eq!("image/png", a.mime.to_string());
+        assert_eq!(body.len(), a.content.len());
+    }
+
     #[tokio::test]
     async fn t_from_url() -> Result<(), AttachmentError> {
         let body = &[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
@@ -150,12 +277,63 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn t_from_url_with_limits() -> Result<(), AttachmentError> {
+        let body = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+        let _m = mock("GET", "/limited.png")
+            .with_status(200)
+            .with_body(body)
+            .create();
+
+        let url = format!("{}/limited.png", server_url());
+        let limits = FetchLimits {
+            max_size: body.len() as u64,
+            ..Default::default()
+        };
+        let a = Attachment::from_url_with_limits(&url, &limits).await?;
+        assert_eq!(body.len(), a.content.len());
+
+        let small = FetchLimits {
+            max_size: 4,
+            ..Default::default()
+        };
+        let result = Attachment::from_url_with_limits(&url, &small).await;
+        assert!(matches!(result, Err(AttachmentError::TooLarge(4))));
+
+        let hosts = FetchLimits {
+            hosts: Some(vec!["images.example.com".into()]),
+            ..Default::default()
+        };
+        let result = Attachment::from_url_with_limits(&url, &hosts).await;
+        assert!(matches!(result, Err(AttachmentError::NotAllowed(_))));
+
+        let result = Attachment::from_url("file:///etc/passwd").await;
+        assert!(matches!(result, Err(AttachmentError::NotAllowed(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn t_from_url_redirect() {
+        let _m = mock("GET", "/redirect.png")
+            .with_status(302)
+            .with_header("Location", "http://169.254.169.254/image.png")
+            .create();
+
+        let url = format!("{}/redirect.png", server_url());
+        match Attachment::from_url(&url).await {
+            Err(AttachmentError::NotAllowed(reason)) => {
+                assert!(reason.starts_with("redirects are not followed"), "{reason}")
+            }
+            r => panic!("unexpected result {r:?}"),
+        }
+    }
+
     #[tokio::test]
     async fn t_attach_url() -> Result<(), AttachmentError> {
         let body = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
         let _m = mock("GET", "/untitled.png")
             .with_status(200)
-            .with_body(&body)
+            .with_body(body)
             .create();
 
         let host = server_url();
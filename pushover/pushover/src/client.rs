@@ -0,0 +1,235 @@
+use std::borrow::Cow;
+use std::time::Duration;
+
+use log::debug;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{server_url, Notification, NotificationError, Response};
+
+/// Pushover client holding API token and agent reused across requests.
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use pushover::Client;
+/// let client = Client::new("token").with_timeout(Duration::from_secs(10));
+/// client.notify("user", "message");
+/// ```
+#[derive(Clone)]
+pub struct Client<'a> {
+    token: Cow<'a, str>,
+    agent: ureq::Agent,
+    timeout: Option<Duration>,
+    proxy: Option<ureq::Proxy>,
+}
+
+impl<'a> std::fmt::Debug for Client<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Client")
+            .field("token", &"***")
+            .field("timeout", &self.timeout)
+            .field("proxy", &self.proxy)
+            .finish()
+    }
+}
+
+/// User or group validated by Pushover. <https://pushover.net/api#verification>
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserValidation {
+    /// Names of active devices of user, empty for group.
+    #[serde(default)]
+    pub devices: Vec<String>,
+    /// Platforms user has licensed.
+    #[serde(default)]
+    pub licenses: Vec<String>,
+}
+
+/// Monthly message limits of application. <https://pushover.net/api#limits>
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Limits {
+    /// Messages application could send per month.
+    pub limit: u32,
+    /// Messages remaining this month.
+    pub remaining: u32,
+    /// Unix timestamp when remaining count resets.
+    pub reset: u64,
+}
+
+/// Converts body of API response, response whose status is not `1` becomes [`NotificationError::Api`].
+fn call<T>(result: Result<ureq::Response, ureq::Error>) -> Result<T, NotificationError>
+where
+    T: DeserializeOwned,
+{
+    let response = match result {
+        Ok(response) => response,
+        // Pushover responds 4xx with errors in body
+        Err(ureq::Error::Status(_, response)) => response,
+        Err(e) => return Err(NotificationError::UReq(Box::new(e))),
+    };
+    let body = response.into_string()?;
+    debug!("pushover response: {body}");
+    let res: Response = serde_json::from_str(&body)?;
+    if res.status != 1 {
+        return Err(res.into());
+    }
+    Ok(serde_json::from_str(&body)?)
+}
+
+impl<'a> Client<'a> {
+    /// Creates a [`Client`] with API token of application.
+    pub fn new<T>(token: T) -> Self
+    where
+        T: Into<Cow<'a, str>>,
+    {
+        Self {
+            token: token.into(),
+            agent: ureq::agent(),
+            timeout: None,
+            proxy: None,
+        }
+    }
+
+    fn rebuild_agent(mut self) -> Self {
+        let mut builder = ureq::AgentBuilder::new();
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(ref proxy) = self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        self.agent = builder.build();
+        self
+    }
+
+    /// Sets timeout of each request, from connecting to reading whole response.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self.rebuild_agent()
+    }
+
+    /// Sends requests through HTTP or SOCKS proxy e.g. `http://127.0.0.1:3128`.
+    pub fn with_proxy(mut self, proxy: &str) -> Result<Self, NotificationError> {
+        let proxy = ureq::Proxy::new(proxy).map_err(|e| NotificationError::UReq(Box::new(e)))?;
+        self.proxy = Some(proxy);
+        Ok(self.rebuild_agent())
+    }
+
+    /// Creates a [`Notification`] sent with agent of [`Client`],
+    /// so optional parameters could be set before sending.
+    pub fn notification<T>(&self, identifier: T, message: T) -> Notification<'a>
+    where
+        T: Into<Cow<'a, str>>,
+    {
+        let mut notification =
+            Notification::new(self.token.clone(), identifier.into(), message.into());
+        notification.agent = Some(self.agent.clone());
+        notification
+    }
+
+    /// Sends message to user or group.
+    pub async fn notify<T>(&self, identifier: T, message: T) -> Result<Response, NotificationError>
+    where
+        T: Into<Cow<'a, str>>,
+    {
+        self.notification(identifier, message).send().await
+    }
+
+    /// Validates user or group key, and device name if given.
+    /// <https://pushover.net/api#verification>
+    pub async fn validate_user(
+        &self,
+        identifier: &str,
+        device: Option<&str>,
+    ) -> Result<UserValidation, NotificationError> {
+        let host = server_url();
+        let uri = format!("{host}/1/users/validate.json");
+
+        let mut form = vec![("token", self.token.as_ref()), ("user", identifier)];
+        if let Some(device) = device {
+            form.push(("device", device));
+        }
+
+        debug!("validate user {identifier}");
+        call(self.agent.post(&uri).send_form(&form))
+    }
+
+    /// Fetches monthly message limits of application. <https://pushover.net/api#limits>
+    pub async fn limits(&self) -> Result<Limits, NotificationError> {
+        let host = server_url();
+        let uri = format!("{host}/1/apps/limits.json");
+
+        debug!("fetch limits");
+        call(self.agent.get(&uri).query("token", &self.token).call())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mockito::{mock, Matcher};
+
+    const REQUEST: &str = "00000000-0000-0000-0000-000000000000";
+
+    #[tokio::test]
+    async fn t_notify() -> Result<(), NotificationError> {
+        let _m = mock("POST", "/1/messages.json")
+            .with_status(200)
+            .with_body(format!(r#"{{"status":1,"request":"{REQUEST}"}}"#))
+            .create();
+
+        let client = Client::new("token").with_timeout(Duration::from_secs(1));
+        let res = client.notify("user", "message").await?;
+        assert_eq!(1, res.status);
+        assert!(!format!("{client:?}").contains("\"token\""));
+        assert!(client.clone().with_proxy("socks5://127.0.0.1:1080").is_ok());
+        assert!(client.clone().with_proxy("ftp://127.0.0.1").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn t_validate_user() -> Result<(), NotificationError> {
+        let _m = mock("POST", "/1/users/validate.json")
+            .match_body(Matcher::UrlEncoded("user".into(), "user".into()))
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"status":1,"group":0,"devices":["phone"],"licenses":["Android"],"request":"{REQUEST}"}}"#
+            ))
+            .create();
+        let _n = mock("POST", "/1/users/validate.json")
+            .match_body(Matcher::UrlEncoded("user".into(), "invalid".into()))
+            .with_status(400)
+            .with_body(format!(
+                r#"{{"user":"invalid","errors":["user key is invalid"],"status":0,"request":"{REQUEST}"}}"#
+            ))
+            .create();
+
+        let client = Client::new("token");
+        let validation = client.validate_user("user", None).await?;
+        assert_eq!(vec!["phone".to_string()], validation.devices);
+        assert_eq!(vec!["Android".to_string()], validation.licenses);
+        match client.validate_user("invalid", None).await {
+            Err(NotificationError::Api { errors, .. }) => {
+                assert_eq!(vec!["user key is invalid".to_string()], errors)
+            }
+            r => panic!("unexpected result {r:?}"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn t_limits() -> Result<(), NotificationError> {
+        let _m = mock("GET", "/1/apps/limits.json")
+            .match_query(Matcher::UrlEncoded("token".into(), "token".into()))
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"limit":10000,"remaining":7496,"reset":1393653600,"status":1,"request":"{REQUEST}"}}"#
+            ))
+            .create();
+
+        let limits = Client::new("token").limits().await?;
+        assert_eq!(10000, limits.limit);
+        assert_eq!(7496, limits.remaining);
+        assert_eq!(1393653600, limits.reset);
+        Ok(())
+    }
+}
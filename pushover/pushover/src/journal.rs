@@ -0,0 +1,186 @@
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{Notification, NotificationError, Response};
+
+/// Notification accepted by Pushover, recorded in [`Journal`].
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct JournalEntry {
+    /// Idempotency key supplied by caller.
+    pub key: String,
+    /// Request ID returned by Pushover. <https://pushover.net/api#response>
+    pub request: String,
+    /// Unix timestamp when Pushover accepted notification.
+    pub sent_at: u64,
+}
+
+/// Local send-journal, so notifications sent again with the same idempotency key
+/// e.g. retried after the process crashed, are not delivered twice.
+///
+/// Entries are appended to file as JSON lines once Pushover accepts notification.
+/// A crash between acceptance and append could still deliver notification twice.
+///
+/// ```rust,no_run
+/// # use pushover::{Journal, Notification};
+/// # async fn run() -> Result<(), pushover::NotificationError> {
+/// let mut journal = Journal::open("journal.jsonl")?;
+/// let notification = Notification::new("token", "user", "backup finished");
+/// let res = journal.send(&notification, "backup-2022-01-01").await?;
+/// // sent already, response of the first one is returned
+/// let again = journal.send(&notification, "backup-2022-01-01").await?;
+/// assert_eq!(res.request, again.request);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    entries: Vec<JournalEntry>,
+    /// Whether file ends with partial line, which is terminated before next append
+    partial: bool,
+}
+
+impl Journal {
+    /// Opens journal from file, which is created on first send if it does not exist.
+    ///
+    /// Lines which cannot be parsed e.g. partially written before crash are skipped.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, NotificationError> {
+        let path = path.as_ref().to_path_buf();
+        let content = match fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut entries = vec![];
+        for line in content.lines().filter(|l| !l.trim().is_empty()) {
+            match serde_json::from_str(line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => warn!("skip malformed line in journal {path:?}: {e}"),
+            }
+        }
+        debug!("{} entries loaded from journal {path:?}", entries.len());
+        Ok(Self {
+            path,
+            entries,
+            partial: !content.is_empty() && !content.ends_with('\n'),
+        })
+    }
+
+    /// Entries in order of sending.
+    pub fn entries(&self) -> &[JournalEntry] {
+        &self.entries
+    }
+
+    /// Finds entry of idempotency key.
+    pub fn get(&self, key: &str) -> Option<&JournalEntry> {
+        self.entries.iter().find(|e| e.key == key)
+    }
+
+    /// Sends [`Notification`] unless idempotency key is in journal, see [`Journal::send_blocking`].
+    pub async fn send(
+        &mut self,
+        notification: &Notification<'_>,
+        key: &str,
+    ) -> Result<Response, NotificationError> {
+        self.send_blocking(notification, key)
+    }
+
+    /// Sends [`Notification`] with [`Notification::send_checked_blocking`]
+    /// and records it under idempotency key.
+    ///
+    /// If key is in journal already, nothing is sent, and response carrying
+    /// request ID of the recorded notification is returned.
+    pub fn send_blocking(
+        &mut self,
+        notification: &Notification<'_>,
+        key: &str,
+    ) -> Result<Response, NotificationError> {
+        if let Some(entry) = self.get(key) {
+            debug!(
+                "skip notification sent as {key:?}, request {}",
+                entry.request
+            );
+            return Ok(Response {
+                status: 1,
+                request: entry.request.clone(),
+                errors: None,
+            });
+        }
+
+        let res = notification.send_checked_blocking()?;
+        let sent_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let entry = JournalEntry {
+            key: key.to_string(),
+            request: res.request.clone(),
+            sent_at,
+        };
+        self.append(&entry)?;
+        self.entries.push(entry);
+        Ok(res)
+    }
+
+    fn append(&mut self, entry: &JournalEntry) -> Result<(), NotificationError> {
+        let mut line = if self.partial { vec![b'\n'] } else { vec![] };
+        line.extend(serde_json::to_vec(entry)?);
+        line.push(b'\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&line)?;
+        file.sync_data()?;
+        self.partial = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mockito::mock;
+
+    #[tokio::test]
+    async fn t_journal() -> Result<(), NotificationError> {
+        let _m = mock("POST", "/1/messages.json")
+            .with_status(200)
+            .with_body(r#"{"status":1,"request":"11111111-1111-1111-1111-111111111111"}"#)
+            .expect(2)
+            .create();
+
+        let path = std::env::temp_dir().join(format!("pushover-{}.jsonl", std::process::id()));
+        let notification = Notification::new("token", "user", "message");
+        let mut journal = Journal::open(&path)?;
+        assert!(journal.entries().is_empty());
+        let res = journal.send(&notification, "key").await?;
+        assert_eq!("11111111-1111-1111-1111-111111111111", res.request);
+
+        // reopened as if process restarted, and a crash left partial line
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        file.write_all(br#"{"key":"partial","#)?;
+        let mut journal = Journal::open(&path)?;
+        let again = journal.send(&notification, "key").await?;
+        assert_eq!(res.request, again.request);
+        assert_eq!(1, journal.entries().len());
+        journal.send(&notification, "other").await?;
+        let journal = Journal::open(&path)?;
+        fs::remove_file(&path)?;
+        let keys = journal.entries().iter().map(|e| e.key.as_str());
+        assert_eq!(vec!["key", "other"], keys.collect::<Vec<_>>());
+        assert_eq!(
+            Some(res.request.as_str()),
+            journal.get("key").map(|e| e.request.as_str())
+        );
+        assert!(journal.get("partial").is_none());
+        _m.assert();
+        Ok(())
+    }
+}
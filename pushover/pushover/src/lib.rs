@@ -14,39 +14,115 @@
 
 use log::debug;
 use maplit::{hashmap, hashset};
+#[cfg(feature = "native")]
 use multipart::client::lazy::Multipart;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::fmt::Display;
+#[cfg(feature = "native")]
 use std::io::Cursor;
 use thiserror::Error;
 
-pub use attachment::{Attachment, AttachmentError};
+#[cfg(not(any(feature = "native", feature = "wasm")))]
+compile_error!("either `native` or `wasm` feature is required to send requests");
 
+#[cfg(feature = "native")]
+pub use attachment::{Attachment, AttachmentError, FetchLimits, MAX_ATTACHMENT_SIZE};
+#[cfg(feature = "native")]
+pub use client::{Client, Limits, UserValidation};
+#[cfg(feature = "journal")]
+pub use journal::{Journal, JournalEntry};
+#[cfg(feature = "open-client")]
+pub use open_client::{Message, OpenClient, OpenClientError};
+pub use request::NotificationRequest;
+#[cfg(feature = "template")]
+pub use template::{NotificationTemplate, RenderedNotification};
+
+#[cfg(feature = "native")]
 mod attachment;
+#[cfg(feature = "native")]
+mod client;
+#[cfg(feature = "journal")]
+mod journal;
+#[cfg(feature = "open-client")]
+mod open_client;
+mod request;
+#[cfg(feature = "template")]
+mod template;
 
 /// Notification error.
 #[derive(Error, Debug)]
 pub enum NotificationError {
     /// Error from [`ureq`] crate.
+    #[cfg(feature = "native")]
     #[error("ureq error: {0}")]
     UReq(#[from] Box<ureq::Error>),
+    /// Error from [`gloo_net`] crate when sending with fetch.
+    #[cfg(not(feature = "native"))]
+    #[error("fetch error: {0}")]
+    Fetch(#[from] gloo_net::Error),
     /// Error from [`serde_json`] crate.
     #[error("deserialization error: {0}")]
     Deserialize(#[from] serde_json::Error),
     /// Wrapped [`crate::AttachmentError`].
+    #[cfg(feature = "native")]
     #[error("attachment error: {0}")]
     Attachment(#[from] AttachmentError),
     /// HTML and monospace are mutually exclusive. <https://pushover.net/api#html>
     #[error("html and monospace are mutually exclusive")]
     HTMLMonospace,
+    /// Message is limited to 1024 characters, unless split into a series of notifications
+    /// with [`Notification::split_long_messages`]. <https://pushover.net/api#limits>
+    #[error("message is {0} characters, exceeds {MAX_MESSAGE_LENGTH}")]
+    MessageTooLong(usize),
+    /// Title is limited to 250 characters. <https://pushover.net/api#limits>
+    #[error("title is {0} characters, exceeds {MAX_TITLE_LENGTH}")]
+    TitleTooLong(usize),
+    /// URL is limited to 512 characters. <https://pushover.net/api#limits>
+    #[error("url is {0} characters, exceeds {MAX_URL_LENGTH}")]
+    URLTooLong(usize),
+    /// URL title is limited to 100 characters. <https://pushover.net/api#limits>
+    #[error("url title is {0} characters, exceeds {MAX_URL_TITLE_LENGTH}")]
+    URLTitleTooLong(usize),
+    /// Device name is up to 25 characters of letters, numbers, `_` and `-`.
+    /// <https://pushover.net/api#identifiers>
+    #[error("invalid device name: {0:?}")]
+    InvalidDevice(String),
     /// IO error.
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+    /// Error from [`minijinja`] crate when rendering [`NotificationTemplate`].
+    #[cfg(feature = "template")]
+    #[error("template error: {0}")]
+    Template(#[from] minijinja::Error),
+    /// Pushover API rejected request. <https://pushover.net/api#response>
+    #[error("API error: {}", errors.join(", "))]
+    Api {
+        /// Errors detailing which parameters were invalid.
+        errors: Vec<String>,
+        /// Unique token associated with the request.
+        request: String,
+    },
+}
+
+impl From<Response> for NotificationError {
+    fn from(res: Response) -> Self {
+        NotificationError::Api {
+            errors: res.errors.unwrap_or_default(),
+            request: res.request,
+        }
+    }
 }
 
+const MAX_MESSAGE_LENGTH: usize = 1024;
+const MAX_TITLE_LENGTH: usize = 250;
+const MAX_URL_LENGTH: usize = 512;
+const MAX_URL_TITLE_LENGTH: usize = 100;
+const MAX_DEVICE_NAME_LENGTH: usize = 25;
+
 /// Pushover API parameters <https://pushover.net/api#messages> and attachment.
-#[derive(Default, Debug)]
+/// See [`NotificationRequest`] for owned parameters.
+#[derive(Clone, Default, Debug)]
 pub struct Notification<'a> {
     token: Cow<'a, str>,
     identifier: Cow<'a, str>,
@@ -76,11 +152,20 @@ pub struct Notification<'a> {
     /// to play when receiving notifications. <https://pushover.net/api#sounds>
     pub sound: Option<Sound>,
     /// Optional [`Attachment`].
+    #[cfg(feature = "native")]
     pub attachment: Option<&'a Attachment<'a>>,
+    /// Split message longer than 1024 characters into a numbered series of notifications
+    /// e.g. `(1/2) ...` and `(2/2) ...`, instead of rejecting it.
+    /// Attachment is only sent with the first one. <https://pushover.net/api#limits>
+    pub split_long_messages: bool,
+    /// Agent of [`Client`] reused across requests, otherwise new one per request.
+    #[cfg(feature = "native")]
+    agent: Option<ureq::Agent>,
 }
 
 /// To enable HTML formatting. <https://pushover.net/api#html>
 #[derive(Clone, Copy, Debug, Eq, PartialEq, strum::Display, strum::EnumString)]
+#[strum(ascii_case_insensitive)]
 pub enum HTML {
     /// Plain text (default)
     #[strum(to_string = "0", serialize = "plain")]
@@ -92,6 +177,7 @@ pub enum HTML {
 
 /// To enable monospace messages. <https://pushover.net/api#html>
 #[derive(Clone, Copy, Debug, Eq, PartialEq, strum::Display, strum::EnumString)]
+#[strum(ascii_case_insensitive)]
 pub enum Monospace {
     /// Normal (default)
     #[strum(to_string = "0", serialize = "normal")]
@@ -104,6 +190,7 @@ pub enum Monospace {
 /// Messages may be sent with a different priority
 /// that affects how the message is presented to the user. <https://pushover.net/api#priority>
 #[derive(Clone, Copy, Debug, Eq, PartialEq, strum::Display, strum::EnumString)]
+#[strum(ascii_case_insensitive)]
 pub enum Priority {
     /// Normal (default)
     #[strum(to_string = "0", serialize = "normal")]
@@ -125,7 +212,7 @@ pub enum Priority {
 /// Users can choose from a number of different default sounds
 /// to play when receiving notifications. <https://pushover.net/api#sounds>
 #[derive(Clone, Copy, Debug, Eq, PartialEq, strum::Display, strum::EnumString)]
-#[strum(serialize_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
 pub enum Sound {
     /// pushover - Pushover (default)
     Pushover,
@@ -175,6 +262,60 @@ pub enum Sound {
     None,
 }
 
+/// Visitor of option from its name e.g. `"high"`, or value of Pushover API e.g. `"1"` or `1`.
+struct OptionVisitor<T>(std::marker::PhantomData<T>);
+
+impl<'de, T> serde::de::Visitor<'de> for OptionVisitor<T>
+where
+    T: std::str::FromStr,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("name or value of option")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<T, E> {
+        v.parse()
+            .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<T, E> {
+        v.to_string()
+            .parse()
+            .map_err(|_| E::invalid_value(serde::de::Unexpected::Signed(v), &self))
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<T, E> {
+        v.to_string()
+            .parse()
+            .map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(v), &self))
+    }
+}
+
+/// Options are serialized as values of Pushover API, numeric ones as numbers.
+macro_rules! impl_option_serde {
+    ($($t:ty),*) => {$(
+        impl Serialize for $t {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let value = self.to_string();
+                match value.parse::<i64>() {
+                    Ok(n) => serializer.serialize_i64(n),
+                    Err(_) => serializer.serialize_str(&value),
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $t {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_any(OptionVisitor(std::marker::PhantomData))
+            }
+        }
+    )*};
+}
+
+impl_option_serde!(HTML, Monospace, Priority, Sound);
+
 #[cfg(test)]
 fn server_url() -> String {
     mockito::server_url()
@@ -185,12 +326,13 @@ fn server_url() -> String {
     "https://api.pushover.net".to_string()
 }
 
-/// Shorthand function to send notification to Pushover.
+/// Shorthand function to send notification to Pushover, see [`Client`] to send many.
 /// ```
 /// use pushover::send_notification;
 /// send_notification("token", "user", "message");
 /// send_notification("token", "group", "message");
 /// ```
+#[cfg(feature = "native")]
 pub async fn send_notification<'a, S>(
     token: S,
     identifier: S,
@@ -199,7 +341,7 @@ pub async fn send_notification<'a, S>(
 where
     S: Into<Cow<'a, str>>,
 {
-    Notification::new(token, identifier, message).send().await
+    Client::new(token).notify(identifier, message).await
 }
 
 #[doc(hidden)]
@@ -222,9 +364,13 @@ where
         .into()
 }
 
-fn add_optional_text<T: Display>(f: &mut Multipart, n: &'static str, v: Option<T>) {
+fn add_optional_text<T: Display>(
+    f: &mut Vec<(&'static str, String)>,
+    n: &'static str,
+    v: Option<T>,
+) {
     if let Some(v) = v {
-        f.add_text(n, v.to_string());
+        f.push((n, v.to_string()));
     }
 }
 
@@ -255,18 +401,120 @@ impl<'a> Notification<'a> {
         }
     }
 
-    /// Send [`Notification`] to Pushover.
+    /// Send [`Notification`] to Pushover, with fetch when `native` feature is disabled.
     pub async fn send(&self) -> Result<Response, NotificationError> {
+        #[cfg(feature = "native")]
+        {
+            self.send_blocking()
+        }
+        #[cfg(not(feature = "native"))]
+        {
+            self.validate()?;
+            let mut res = None;
+            for message in self.messages() {
+                let sent = self.fetch_message(&message).await?;
+                if sent.status != 1 {
+                    return Ok(sent);
+                }
+                res = Some(sent);
+            }
+            // message is split into at least one part
+            Ok(res.expect("no message sent"))
+        }
+    }
+
+    /// Validate parameters against limits of Pushover API, before sending [`Notification`].
+    ///
+    /// ```rust
+    /// # use pushover::{Notification, NotificationError};
+    /// let mut notification = Notification::new("token", "user", "message");
+    /// notification.device = Some("phone,tablet");
+    /// assert!(notification.validate().is_ok());
+    /// notification.device = Some("my phone");
+    /// assert!(matches!(notification.validate(), Err(NotificationError::InvalidDevice(_))));
+    /// ```
+    pub fn validate(&self) -> Result<(), NotificationError> {
         // HTML and monospace are mutually exclusive <https://pushover.net/api#html>
         if self.html == Some(HTML::HTML) && self.monospace == Some(Monospace::Monospace) {
             return Err(NotificationError::HTMLMonospace);
         }
+        let len = self.message.chars().count();
+        if len > MAX_MESSAGE_LENGTH && !self.split_long_messages {
+            return Err(NotificationError::MessageTooLong(len));
+        }
+        if let Some(title) = self.title {
+            let len = title.chars().count();
+            if len > MAX_TITLE_LENGTH {
+                return Err(NotificationError::TitleTooLong(len));
+            }
+        }
+        if let Some(url) = self.url {
+            let len = url.chars().count();
+            if len > MAX_URL_LENGTH {
+                return Err(NotificationError::URLTooLong(len));
+            }
+        }
+        if let Some(url_title) = self.url_title {
+            let len = url_title.chars().count();
+            if len > MAX_URL_TITLE_LENGTH {
+                return Err(NotificationError::URLTitleTooLong(len));
+            }
+        }
+        if let Some(device) = self.device {
+            // multiple devices may be separated by a comma
+            for name in device.split(',') {
+                let valid = !name.is_empty()
+                    && name.len() <= MAX_DEVICE_NAME_LENGTH
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+                if !valid {
+                    return Err(NotificationError::InvalidDevice(name.to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
 
-        let mut form = Multipart::new();
+    /// Send [`Notification`] to Pushover without async runtime.
+    ///
+    /// When message is split with [`Notification::split_long_messages`],
+    /// notifications are sent in order, and response of the last one is returned.
+    /// Sending stops at the first one rejected.
+    #[cfg(feature = "native")]
+    pub fn send_blocking(&self) -> Result<Response, NotificationError> {
+        self.validate()?;
+
+        let mut res = None;
+        for (i, message) in self.messages().iter().enumerate() {
+            let sent = self.send_message(message, i == 0)?;
+            if sent.status != 1 {
+                return Ok(sent);
+            }
+            res = Some(sent);
+        }
+        // message is split into at least one part
+        Ok(res.expect("no message sent"))
+    }
+
+    /// Messages to send, sanitized before split into a series if asked to,
+    /// so tags and entities are not broken across parts.
+    fn messages(&self) -> Vec<String> {
+        let message = sanitize_message(self.message.as_ref());
+        if self.split_long_messages {
+            split_message(&message, MAX_MESSAGE_LENGTH)
+        } else {
+            vec![message.into_owned()]
+        }
+    }
 
-        form.add_text("token", self.token.to_string());
-        form.add_text("user", self.identifier.to_string()); // User or group key
-        form.add_text("message", sanitize_message(self.message.clone()));
+    /// Parameters of message except attachment.
+    fn form(&self, message: &str) -> Vec<(&'static str, String)> {
+        let mut form = vec![
+            ("token", self.token.to_string()),
+            ("user", self.identifier.to_string()), // User or group key
+            ("message", message.to_string()),
+        ];
 
         add_optional_text(&mut form, "device", self.device.as_ref());
         add_optional_text(&mut form, "title", self.title.as_ref());
@@ -277,8 +525,21 @@ impl<'a> Notification<'a> {
         add_optional_text(&mut form, "url", self.url.as_ref());
         add_optional_text(&mut form, "url_title", self.url_title.as_ref());
         add_optional_text(&mut form, "sound", self.sound.as_ref());
+        form
+    }
 
-        if let Some(a) = self.attachment {
+    #[cfg(feature = "native")]
+    fn send_message(
+        &self,
+        message: &str,
+        with_attachment: bool,
+    ) -> Result<Response, NotificationError> {
+        let mut form = Multipart::new();
+        for (name, value) in self.form(message) {
+            form.add_text(name, value);
+        }
+
+        if let Some(a) = self.attachment.filter(|_| with_attachment) {
             let reader = Cursor::new(&a.content);
             form.add_stream(
                 "attachment",
@@ -295,8 +556,12 @@ impl<'a> Notification<'a> {
         let boundary = form.boundary();
         let content_type = format!("multipart/form-data; boundary={boundary}");
 
-        debug!("send message: {self:?}");
-        let response = ureq::post(&uri)
+        debug!("send message: {message:?} of {self:?}");
+        let request = match self.agent {
+            Some(ref agent) => agent.post(&uri),
+            None => ureq::post(&uri),
+        };
+        let response = request
             .set("Content-Type", &content_type)
             .send(form)
             .map_err(|e| NotificationError::UReq(Box::new(e)))?;
@@ -307,6 +572,139 @@ impl<'a> Notification<'a> {
         debug!("pushover response: {res:?}");
         Ok(res)
     }
+
+    /// Sends message URL-encoded with fetch, since attachment is not supported.
+    #[cfg(not(feature = "native"))]
+    async fn fetch_message(&self, message: &str) -> Result<Response, NotificationError> {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(self.form(message))
+            .finish();
+        let host = server_url();
+        let uri = format!("{host}/1/messages.json");
+
+        debug!("send message: {message:?} of {self:?}");
+        let response = gloo_net::http::Request::post(&uri)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)?
+            .send()
+            .await?;
+        // fetch resolves 4xx responses too, whose body carries errors
+        let body = response.text().await?;
+
+        let res = serde_json::from_str(&body)?;
+        debug!("pushover response: {res:?}");
+        Ok(res)
+    }
+
+    /// Send [`Notification`] to Pushover,
+    /// and convert response whose status is not `1` into [`NotificationError::Api`].
+    pub async fn send_checked(&self) -> Result<Response, NotificationError> {
+        #[cfg(feature = "native")]
+        {
+            self.send_checked_blocking()
+        }
+        #[cfg(not(feature = "native"))]
+        {
+            match self.send().await? {
+                res if res.status == 1 => Ok(res),
+                res => Err(res.into()),
+            }
+        }
+    }
+
+    /// Send [`Notification`] to Pushover without async runtime,
+    /// and convert response whose status is not `1` into [`NotificationError::Api`].
+    /// Rate limit and server errors i.e. HTTP 429 and 5xx are kept as [`NotificationError::UReq`].
+    #[cfg(feature = "native")]
+    pub fn send_checked_blocking(&self) -> Result<Response, NotificationError> {
+        match self.send_blocking() {
+            Ok(res) if res.status == 1 => Ok(res),
+            Ok(res) => Err(res.into()),
+            Err(NotificationError::UReq(e)) => match *e {
+                // rate limit and server errors are kept as is, so callers could retry them
+                ureq::Error::Status(code, response) if code == 429 || code >= 500 => Err(
+                    NotificationError::UReq(Box::new(ureq::Error::Status(code, response))),
+                ),
+                // Pushover responds 4xx with errors in body when parameters are invalid
+                ureq::Error::Status(_, response) => {
+                    let body = response.into_string()?;
+                    let res: Response = serde_json::from_str(&body)?;
+                    Err(res.into())
+                }
+                e => Err(NotificationError::UReq(Box::new(e))),
+            },
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Splits message into parts numbered like `(1/2) `, each no longer than `limit` characters.
+/// Parts break after last newline or whitespace if any, otherwise in the middle of words,
+/// but never inside a tag or an entity of sanitized message.
+fn split_message(message: &str, limit: usize) -> Vec<String> {
+    let len = message.chars().count();
+    if len <= limit {
+        return vec![message.to_string()];
+    }
+    // prefix width depends on number of parts, so split again until it settles
+    let mut total = len / limit + 1;
+    loop {
+        let prefix_len = format!("({total}/{total}) ").len();
+        let parts = split_chars(message, limit.saturating_sub(prefix_len).max(1));
+        if parts.len().to_string().len() <= total.to_string().len() {
+            let total = parts.len();
+            return parts
+                .into_iter()
+                .enumerate()
+                .map(|(i, part)| format!("({}/{total}) {part}", i + 1))
+                .collect();
+        }
+        total = parts.len();
+    }
+}
+
+/// Start of tag e.g. `<a href="…">` or entity e.g. `&amp;` left open at the end of head
+fn markup_start(head: &str) -> Option<usize> {
+    let tag = head.rfind('<').filter(|&i| !head[i..].contains('>'));
+    let entity = head.rfind('&').filter(|&i| {
+        let name = &head[i + 1..];
+        name.len() < 10 && name.chars().all(|c| c == '#' || c.is_ascii_alphanumeric())
+    });
+    match (tag, entity) {
+        (Some(t), Some(e)) => Some(t.min(e)),
+        (t, e) => t.or(e),
+    }
+}
+
+fn split_chars(message: &str, size: usize) -> Vec<String> {
+    let mut parts = vec![];
+    let mut rest = message.trim();
+    while !rest.is_empty() {
+        let end = match rest.char_indices().nth(size) {
+            Some((end, _)) => end,
+            None => {
+                parts.push(rest.to_string());
+                break;
+            }
+        };
+        let head = &rest[..end];
+        let end = match head.rfind('\n').or_else(|| head.rfind(char::is_whitespace)) {
+            Some(i) if i > 0 => i,
+            _ => end,
+        };
+        // tag or entity longer than part is broken anyway
+        let end = match markup_start(&rest[..end]) {
+            Some(i) if i > 0 => i,
+            _ => end,
+        };
+        parts.push(rest[..end].trim_end().to_string());
+        rest = rest[end..].trim_start();
+    }
+    // blank message is still sent, and rejected by Pushover
+    if parts.is_empty() {
+        parts.push(String::new());
+    }
+    parts
 }
 
 /// Pushover API response. <https://pushover.net/api#response>
@@ -320,7 +718,7 @@ pub struct Response {
     pub errors: Option<Vec<String>>,
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "native"))]
 mod tests {
     use super::*;
 
@@ -350,6 +748,57 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn t_send_blocking() -> Result<(), NotificationError> {
+        let _m = mock("POST", "/1/messages.json")
+            .with_status(200)
+            .with_body(r#"{"status":1,"request":"00000000-0000-0000-0000-000000000000"}"#)
+            .create();
+
+        let n = build_notification();
+
+        let res = n.send_blocking()?;
+        assert_eq!(1, res.status);
+        let res = n.send_checked_blocking()?;
+        assert_eq!("00000000-0000-0000-0000-000000000000", res.request);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn t_send_checked() {
+        let _m = mock("POST", "/1/messages.json")
+            .with_status(400)
+            .with_body(r#"{"user":"invalid","errors":["user identifier is invalid"],"status":0,"request":"00000000-0000-0000-0000-000000000000"}"#)
+            .create();
+
+        let n = build_notification();
+
+        match n.send_checked().await {
+            Err(NotificationError::Api { errors, request }) => {
+                assert_eq!(vec!["user identifier is invalid".to_string()], errors);
+                assert_eq!("00000000-0000-0000-0000-000000000000", request);
+            }
+            r => panic!("unexpected result {r:?}"),
+        }
+    }
+
+    #[test]
+    fn t_send_checked_server_error() {
+        let _m = mock("POST", "/1/messages.json")
+            .with_status(503)
+            .with_body("<html>service unavailable</html>")
+            .create();
+
+        let n = build_notification();
+
+        match n.send_checked_blocking() {
+            Err(NotificationError::UReq(e)) => {
+                assert!(matches!(*e, ureq::Error::Status(503, _)), "{e:?}")
+            }
+            r => panic!("unexpected result {r:?}"),
+        }
+    }
+
     #[tokio::test]
     async fn t_device() -> Result<(), NotificationError> {
         let _m = mock("POST", "/1/messages.json")
@@ -358,7 +807,7 @@ mod tests {
             .create();
 
         let mut n = build_notification();
-        n.device = Some("device".into());
+        n.device = Some("device");
 
         let res = n.send().await?;
         assert_eq!(1, res.status);
@@ -368,6 +817,139 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn t_validate() {
+        let mut n = build_notification();
+        assert!(n.validate().is_ok());
+
+        let url = format!("https://x.com/{}", "a".repeat(MAX_URL_LENGTH));
+        n.url = Some(&url);
+        assert!(matches!(
+            n.validate(),
+            Err(NotificationError::URLTooLong(_))
+        ));
+        n.url = Some("https://x.com");
+
+        let url_title = "a".repeat(MAX_URL_TITLE_LENGTH + 1);
+        n.url_title = Some(&url_title);
+        assert!(matches!(
+            n.validate(),
+            Err(NotificationError::URLTitleTooLong(101))
+        ));
+        n.url_title = Some("title");
+
+        let long = "a".repeat(MAX_DEVICE_NAME_LENGTH + 1);
+        for device in ["", "phone,", "my phone", "phone!", &long] {
+            n.device = Some(device);
+            assert!(
+                matches!(n.validate(), Err(NotificationError::InvalidDevice(_))),
+                "{device}"
+            );
+        }
+        n.device = Some("phone,my-tablet_2");
+        assert!(n.validate().is_ok());
+
+        let title = "a".repeat(MAX_TITLE_LENGTH + 1);
+        n.title = Some(&title);
+        assert!(matches!(
+            n.validate(),
+            Err(NotificationError::TitleTooLong(251))
+        ));
+        n.title = Some("title");
+
+        let message = "a".repeat(MAX_MESSAGE_LENGTH + 1);
+        n.message = message.as_str().into();
+        assert!(matches!(
+            n.validate(),
+            Err(NotificationError::MessageTooLong(1025))
+        ));
+        n.split_long_messages = true;
+        assert!(n.validate().is_ok());
+        n.split_long_messages = false;
+        n.message = "message".into();
+
+        // rejected before network call, no mock server is needed
+        n.device = Some("my phone");
+        assert!(matches!(
+            n.send_blocking(),
+            Err(NotificationError::InvalidDevice(_))
+        ));
+    }
+
+    #[test]
+    fn t_split_message() {
+        assert_eq!(vec!["short"], split_message("short", 10));
+        assert_eq!(
+            vec!["(1/3) aaa", "(2/3) bbb", "(3/3) ccc"],
+            split_message("aaa bbb ccc", 9)
+        );
+        assert_eq!(
+            vec!["(1/4) aaa", "(2/4) aaa", "(3/4) aaa", "(4/4) a"],
+            split_message("aaaaaaaaaa", 9)
+        );
+        assert_eq!(
+            vec!["(1/3) one", "(2/3) two", "(3/3) three"],
+            split_message("one\ntwo\nthree", 12)
+        );
+
+        let message = "word ".repeat(1000);
+        let parts = split_message(&message, MAX_MESSAGE_LENGTH);
+        assert_eq!(5, parts.len());
+        for (i, part) in parts.iter().enumerate() {
+            assert!(part.starts_with(&format!("({}/5) word", i + 1)));
+            assert!(part.chars().count() <= MAX_MESSAGE_LENGTH);
+        }
+    }
+
+    #[test]
+    fn t_split_html_message() {
+        assert_eq!(Some(2), markup_start("a <a href"));
+        assert_eq!(Some(2), markup_start("a &am"));
+        assert_eq!(None, markup_start("a <b>b</b> &amp; c"));
+
+        let mut n = Notification::new("token", "user", "");
+        n.html = Some(HTML::HTML);
+        n.split_long_messages = true;
+        // link crosses the limit of first part, and stays in second part as a whole
+        let message = format!(
+            "{} <a href=\"https://example.com\">link</a> &amp; more",
+            "a".repeat(1000)
+        );
+        n.message = message.as_str().into();
+        let parts = n.messages();
+        assert_eq!(2, parts.len());
+        assert_eq!(
+            r#"(2/2) <a href="https://example.com" rel="noopener noreferrer">link</a> &amp; more"#,
+            parts[1]
+        );
+        for part in &parts {
+            assert!(part.chars().count() <= MAX_MESSAGE_LENGTH);
+            assert_eq!(part, &sanitize_message(part.as_str()));
+        }
+    }
+
+    #[test]
+    fn t_send_split_messages() -> Result<(), NotificationError> {
+        let m = mock("POST", "/1/messages.json")
+            .with_status(200)
+            .with_body(r#"{"status":1,"request":"00000000-0000-0000-0000-000000000000"}"#)
+            .expect(2)
+            .create();
+
+        let message = "a".repeat(MAX_MESSAGE_LENGTH + 1);
+        let mut n = Notification::new("token", "user", message.as_str());
+        assert!(matches!(
+            n.send_blocking(),
+            Err(NotificationError::MessageTooLong(_))
+        ));
+
+        n.split_long_messages = true;
+        let res = n.send_checked_blocking()?;
+        assert_eq!(1, res.status);
+        m.assert();
+        Ok(())
+    }
+
     fn build_notification<'a>() -> Notification<'a> {
         let user = "user";
         let token = "token";
@@ -414,6 +996,8 @@ mod tests {
         assert_eq!("2", Priority::Emergency.to_string());
         assert_eq!(Priority::Emergency, Priority::from_str("2")?);
         assert_eq!(Priority::Emergency, Priority::from_str("emergency")?);
+        assert_eq!(Priority::Emergency, Priority::from_str("Emergency")?);
+        assert!(Priority::from_str("3").is_err());
         Ok(())
     }
 
@@ -421,6 +1005,7 @@ mod tests {
     fn t_sound() -> Result<(), strum::ParseError> {
         assert_eq!("pushover", Sound::Pushover.to_string());
         assert_eq!(Sound::Pushover, Sound::from_str("pushover")?);
+        assert_eq!(Sound::Pushover, Sound::from_str("Pushover")?);
         assert_eq!("bike", Sound::Bike.to_string());
         assert_eq!(Sound::Bike, Sound::from_str("bike")?);
         assert_eq!("bugle", Sound::Bugle.to_string());
@@ -0,0 +1,330 @@
+use std::borrow::Cow;
+
+use log::debug;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::server_url;
+
+/// Open Client API error.
+#[derive(Error, Debug)]
+pub enum OpenClientError {
+    /// Error from [`ureq`] crate.
+    #[error("ureq error: {0}")]
+    UReq(#[from] Box<ureq::Error>),
+    /// Error from [`serde_json`] crate.
+    #[error("deserialization error: {0}")]
+    Deserialize(#[from] serde_json::Error),
+    /// IO error.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    /// Pushover API rejected request. <https://pushover.net/api/client#errors>
+    #[error("API error: {}", errors.join(", "))]
+    Api {
+        /// Errors detailing which parameters were invalid.
+        errors: Vec<String>,
+        /// Unique token associated with the request.
+        request: String,
+    },
+    /// Device is not registered yet, see [`OpenClient::register_device`].
+    #[error("device is not registered")]
+    NoDevice,
+}
+
+/// Message downloaded from Pushover. <https://pushover.net/api/client#download>
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Message {
+    /// Unique ID of message, relative to device.
+    pub id: u64,
+    /// Unique ID of message, across all devices.
+    pub umid: u64,
+    /// Message body.
+    pub message: String,
+    /// Name of application that sent message.
+    pub app: String,
+    /// Unique ID of application that sent message.
+    pub aid: u64,
+    /// Icon of application.
+    pub icon: String,
+    /// Unix timestamp when message was sent.
+    pub date: u64,
+    /// Priority of message.
+    pub priority: i8,
+    /// Whether emergency message is acknowledged, `1` if acknowledged.
+    #[serde(default)]
+    pub acked: u8,
+    /// Title of message.
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Supplementary URL.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Title of supplementary URL.
+    #[serde(default)]
+    pub url_title: Option<String>,
+    /// Whether message is HTML, `1` if HTML.
+    #[serde(default)]
+    pub html: u8,
+}
+
+#[derive(Deserialize)]
+struct Status {
+    status: u8,
+    #[serde(default)]
+    request: String,
+    #[serde(default)]
+    errors: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct LoginResponse {
+    id: String,
+    secret: String,
+}
+
+#[derive(Deserialize)]
+struct DeviceResponse {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct MessagesResponse {
+    messages: Vec<Message>,
+}
+
+/// Pushover client receiving messages. <https://pushover.net/api/client>
+pub struct OpenClient<'a> {
+    user_id: Cow<'a, str>,
+    secret: Cow<'a, str>,
+    device_id: Option<Cow<'a, str>>,
+}
+
+impl<'a> std::fmt::Debug for OpenClient<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenClient")
+            .field("user_id", &self.user_id)
+            .field("secret", &"***")
+            .field("device_id", &self.device_id)
+            .finish()
+    }
+}
+
+/// Parses body of API response, converting status other than `1` into [`OpenClientError::Api`].
+fn parse<T>(body: &str) -> Result<T, OpenClientError>
+where
+    T: DeserializeOwned,
+{
+    let status: Status = serde_json::from_str(body)?;
+    if status.status != 1 {
+        return Err(OpenClientError::Api {
+            errors: status.errors.unwrap_or_default(),
+            request: status.request,
+        });
+    }
+    Ok(serde_json::from_str(body)?)
+}
+
+fn call<T>(result: Result<ureq::Response, ureq::Error>) -> Result<T, OpenClientError>
+where
+    T: DeserializeOwned,
+{
+    let response = match result {
+        Ok(response) => response,
+        // Pushover responds 4xx with errors in body
+        Err(ureq::Error::Status(_, response)) => response,
+        Err(e) => return Err(OpenClientError::UReq(Box::new(e))),
+    };
+    let body = response.into_string()?;
+    debug!("pushover response: {body}");
+    parse(&body)
+}
+
+impl<'a> OpenClient<'a> {
+    /// Creates an [`OpenClient`] from user ID, secret and device ID saved from previous login.
+    pub fn new<T>(user_id: T, secret: T, device_id: Option<T>) -> Self
+    where
+        T: Into<Cow<'a, str>>,
+    {
+        Self {
+            user_id: user_id.into(),
+            secret: secret.into(),
+            device_id: device_id.map(Into::into),
+        }
+    }
+
+    /// Logs in with email and password, and two-factor code if enabled.
+    /// <https://pushover.net/api/client#login>
+    pub async fn login(
+        email: &str,
+        password: &str,
+        twofa: Option<&str>,
+    ) -> Result<OpenClient<'a>, OpenClientError> {
+        let host = server_url();
+        let uri = format!("{host}/1/users/login.json");
+
+        let mut form = vec![("email", email), ("password", password)];
+        if let Some(twofa) = twofa {
+            form.push(("twofa", twofa));
+        }
+
+        debug!("login as {email}");
+        let res: LoginResponse = call(ureq::post(&uri).send_form(&form))?;
+        Ok(Self::new(res.id, res.secret, None))
+    }
+
+    /// User ID.
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    /// Secret of user, should be kept for later sessions.
+    pub fn secret(&self) -> &str {
+        &self.secret
+    }
+
+    /// ID of registered device, should be kept for later sessions.
+    pub fn device_id(&self) -> Option<&str> {
+        self.device_id.as_deref()
+    }
+
+    /// Registers new desktop device with given name, up to 25 characters of letters, numbers, _ and -.
+    /// <https://pushover.net/api/client#register>
+    pub async fn register_device(&mut self, name: &str) -> Result<&str, OpenClientError> {
+        let host = server_url();
+        let uri = format!("{host}/1/devices.json");
+
+        let form = [
+            ("secret", self.secret.as_ref()),
+            ("name", name),
+            ("os", "O"),
+        ];
+
+        debug!("register device {name}");
+        let res: DeviceResponse = call(ureq::post(&uri).send_form(&form))?;
+        Ok(self.device_id.insert(res.id.into()))
+    }
+
+    /// Downloads messages pending on device. <https://pushover.net/api/client#download>
+    pub async fn messages(&self) -> Result<Vec<Message>, OpenClientError> {
+        let device_id = self.device_id().ok_or(OpenClientError::NoDevice)?;
+        let host = server_url();
+        let uri = format!("{host}/1/messages.json");
+
+        debug!("download messages of device {device_id}");
+        let req = ureq::get(&uri)
+            .query("secret", &self.secret)
+            .query("device_id", device_id);
+        let res: MessagesResponse = call(req.call())?;
+        Ok(res.messages)
+    }
+
+    /// Deletes messages up to and including given message ID from device.
+    /// <https://pushover.net/api/client#delete>
+    pub async fn delete_messages(&self, highest_message_id: u64) -> Result<(), OpenClientError> {
+        let device_id = self.device_id().ok_or(OpenClientError::NoDevice)?;
+        let host = server_url();
+        let uri = format!("{host}/1/devices/{device_id}/update_highest_message.json");
+
+        let message = highest_message_id.to_string();
+        let form = [("secret", self.secret.as_ref()), ("message", &message)];
+
+        debug!("delete messages up to {highest_message_id} of device {device_id}");
+        let _: Status = call(ureq::post(&uri).send_form(&form))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mockito::{mock, Matcher};
+
+    const REQUEST: &str = "00000000-0000-0000-0000-000000000000";
+
+    #[tokio::test]
+    async fn t_login() -> Result<(), OpenClientError> {
+        let _m = mock("POST", "/1/users/login.json")
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"status":1,"id":"user","secret":"secret","request":"{REQUEST}"}}"#
+            ))
+            .create();
+
+        let client = OpenClient::login("email", "password", None).await?;
+        assert_eq!("user", client.user_id());
+        assert_eq!("secret", client.secret());
+        assert!(client.device_id().is_none());
+        assert!(!format!("{client:?}").contains("\"secret\""));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn t_login_failed() {
+        let _m = mock("POST", "/1/users/login.json")
+            .with_status(412)
+            .with_body(format!(
+                r#"{{"status":0,"errors":["invalid email or password"],"request":"{REQUEST}"}}"#
+            ))
+            .create();
+
+        match OpenClient::login("email", "password", None).await {
+            Err(OpenClientError::Api { errors, request }) => {
+                assert_eq!(vec!["invalid email or password".to_string()], errors);
+                assert_eq!(REQUEST, request);
+            }
+            r => panic!("unexpected result {r:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn t_register_device() -> Result<(), OpenClientError> {
+        let _m = mock("POST", "/1/devices.json")
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"status":1,"id":"device","request":"{REQUEST}"}}"#
+            ))
+            .create();
+
+        let mut client = OpenClient::new("user", "secret", None);
+        assert_eq!("device", client.register_device("name").await?);
+        assert_eq!(Some("device"), client.device_id());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn t_messages_and_delete() -> Result<(), OpenClientError> {
+        let _m = mock("GET", "/1/messages.json")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("secret".into(), "secret".into()),
+                Matcher::UrlEncoded("device_id".into(), "device".into()),
+            ]))
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"status":1,"messages":[{{"id":4,"umid":1,"message":"message","app":"app","aid":2,"icon":"icon","date":1,"priority":0,"title":"title"}}],"request":"{REQUEST}"}}"#
+            ))
+            .create();
+        let _n = mock("POST", "/1/devices/device/update_highest_message.json")
+            .with_status(200)
+            .with_body(format!(r#"{{"status":1,"request":"{REQUEST}"}}"#))
+            .create();
+
+        let client = OpenClient::new("user", "secret", Some("device"));
+        let messages = client.messages().await?;
+        assert_eq!(1, messages.len());
+        assert_eq!(4, messages[0].id);
+        assert_eq!(Some("title".to_string()), messages[0].title);
+
+        client.delete_messages(messages[0].id).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn t_no_device() {
+        let client = OpenClient::new("user", "secret", None);
+        assert!(matches!(
+            client.messages().await,
+            Err(OpenClientError::NoDevice)
+        ));
+    }
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Monospace, Notification, Priority, Sound, HTML};
+
+/// Owned parameters of [`Notification`] without token and attachment,
+/// so they could be cloned, stored, or accepted from JSON by HTTP layers.
+///
+/// Options are deserialized from either names or values of Pushover API
+/// e.g. priority `"high"`, `"1"` or `1`, and serialized as values.
+///
+/// ```rust
+/// # use pushover::{NotificationRequest, Priority};
+/// let request: NotificationRequest =
+///     serde_json::from_str(r#"{"user":"user","message":"message","priority":"high"}"#).unwrap();
+/// assert_eq!(Some(Priority::High), request.priority);
+/// let notification = request.notification("token");
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct NotificationRequest {
+    /// User or group key. <https://pushover.net/api#identifiers>
+    #[serde(rename = "user")]
+    pub identifier: String,
+    /// Your message.
+    pub message: String,
+    /// See [`Notification::device`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// See [`Notification::title`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// See [`Notification::html`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub html: Option<HTML>,
+    /// See [`Notification::monospace`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monospace: Option<Monospace>,
+    /// See [`Notification::timestamp`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    /// See [`Notification::priority`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    /// See [`Notification::url`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// See [`Notification::url_title`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url_title: Option<String>,
+    /// See [`Notification::sound`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sound: Option<Sound>,
+    /// See [`Notification::split_long_messages`].
+    #[serde(default)]
+    pub split_long_messages: bool,
+}
+
+impl NotificationRequest {
+    /// Creates a [`NotificationRequest`].
+    pub fn new<T>(identifier: T, message: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            identifier: identifier.into(),
+            message: message.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a [`Notification`] borrowing parameters, to be sent with API token of application.
+    pub fn notification<'a>(&'a self, token: &'a str) -> Notification<'a> {
+        let mut notification =
+            Notification::new(token, self.identifier.as_str(), self.message.as_str());
+        notification.device = self.device.as_deref();
+        notification.title = self.title.as_deref();
+        notification.html = self.html;
+        notification.monospace = self.monospace;
+        notification.timestamp = self.timestamp;
+        notification.priority = self.priority;
+        notification.url = self.url.as_deref();
+        notification.url_title = self.url_title.as_deref();
+        notification.sound = self.sound;
+        notification.split_long_messages = self.split_long_messages;
+        notification
+    }
+}
+
+impl<'a> From<&Notification<'a>> for NotificationRequest {
+    fn from(notification: &Notification<'a>) -> Self {
+        Self {
+            identifier: notification.identifier.to_string(),
+            message: notification.message.to_string(),
+            device: notification.device.map(String::from),
+            title: notification.title.map(String::from),
+            html: notification.html,
+            monospace: notification.monospace,
+            timestamp: notification.timestamp,
+            priority: notification.priority,
+            url: notification.url.map(String::from),
+            url_title: notification.url_title.map(String::from),
+            sound: notification.sound,
+            split_long_messages: notification.split_long_messages,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_notification_request() {
+        let request: NotificationRequest = serde_json::from_str(
+            r#"{"user":"user","message":"message","html":1,"priority":"High","sound":"siren","title":"title"}"#,
+        )
+        .unwrap();
+        assert_eq!("user", request.identifier);
+        assert_eq!(Some(HTML::HTML), request.html);
+        assert_eq!(Some(Priority::High), request.priority);
+        assert_eq!(Some(Sound::Siren), request.sound);
+        assert_eq!(Some("title"), request.title.as_deref());
+
+        let json = serde_json::to_string(&request.clone()).unwrap();
+        assert_eq!(
+            r#"{"user":"user","message":"message","title":"title","html":1,"priority":1,"sound":"siren","split_long_messages":false}"#,
+            json
+        );
+
+        let notification = request.notification("token");
+        assert_eq!(Some("title"), notification.title);
+        assert_eq!(Some(Priority::High), notification.priority);
+        assert_eq!(request, NotificationRequest::from(&notification.clone()));
+
+        for priority in ["-2", "-1", "0", "1", "2"] {
+            let json = format!(r#"{{"user":"user","message":"message","priority":{priority}}}"#);
+            let request: NotificationRequest = serde_json::from_str(&json).unwrap();
+            assert_eq!(priority, request.priority.unwrap().to_string());
+        }
+        assert!(serde_json::from_str::<NotificationRequest>(
+            r#"{"user":"user","message":"message","priority":3}"#
+        )
+        .is_err());
+        assert!(serde_json::from_str::<NotificationRequest>(
+            r#"{"user":"user","message":"message","sound":"unknown"}"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use minijinja::{AutoEscape, Environment};
+use serde::Serialize;
+
+use crate::{Monospace, Notification, NotificationError, HTML};
+
+/// Renders message and title of [`Notification`] from [minijinja](https://docs.rs/minijinja)
+/// templates and serializable context.
+///
+/// Values are HTML-escaped in message when HTML formatting is enabled, and kept as they are
+/// in plain text or monospace messages. Title is always plain text.
+///
+/// ```rust
+/// # use pushover::{NotificationTemplate, HTML};
+/// # use serde_json::json;
+/// let mut template = NotificationTemplate::new("<b>{{ domain }}</b> expires in {{ days }} day(s)");
+/// template.title = Some("{{ domain }}");
+/// template.html = Some(HTML::HTML);
+/// let rendered = template.render(json!({ "domain": "<x>.com", "days": 3 })).unwrap();
+/// assert_eq!("<b>&lt;x&gt;.com</b> expires in 3 day(s)", rendered.message);
+/// let notification = rendered.notification("token", "user");
+/// ```
+#[derive(Debug, Default)]
+pub struct NotificationTemplate<'a> {
+    message: &'a str,
+    /// Template of title.
+    pub title: Option<&'a str>,
+    /// To enable HTML formatting, and escape values in message. <https://pushover.net/api#html>
+    pub html: Option<HTML>,
+    /// To enable monospace messages. <https://pushover.net/api#html>
+    pub monospace: Option<Monospace>,
+}
+
+/// Message and title rendered by [`NotificationTemplate`].
+#[derive(Debug)]
+pub struct RenderedNotification {
+    /// Rendered message.
+    pub message: String,
+    /// Rendered title.
+    pub title: Option<String>,
+    html: Option<HTML>,
+    monospace: Option<Monospace>,
+}
+
+impl<'a> NotificationTemplate<'a> {
+    /// Creates a [`NotificationTemplate`] from template of message.
+    pub fn new(message: &'a str) -> Self {
+        Self {
+            message,
+            ..Default::default()
+        }
+    }
+
+    /// Renders message and title with context.
+    pub fn render<S: Serialize>(
+        &self,
+        context: S,
+    ) -> Result<RenderedNotification, NotificationError> {
+        let mut env = Environment::new();
+        // template name decides escaping, see below
+        env.set_auto_escape_callback(|name| match name {
+            "message.html" => AutoEscape::Html,
+            _ => AutoEscape::None,
+        });
+        let name = if self.html == Some(HTML::HTML) {
+            "message.html"
+        } else {
+            "message.txt"
+        };
+        let message = env.render_named_str(name, self.message, &context)?;
+        let title = match self.title {
+            Some(title) => Some(env.render_named_str("title.txt", title, &context)?),
+            None => None,
+        };
+        Ok(RenderedNotification {
+            message,
+            title,
+            html: self.html,
+            monospace: self.monospace,
+        })
+    }
+}
+
+impl RenderedNotification {
+    /// Creates a [`Notification`] with rendered message and title,
+    /// and formatting of [`NotificationTemplate`].
+    pub fn notification<'a>(&'a self, token: &'a str, identifier: &'a str) -> Notification<'a> {
+        let mut notification = Notification::new(token, identifier, self.message.as_str());
+        notification.title = self.title.as_deref();
+        notification.html = self.html;
+        notification.monospace = self.monospace;
+        notification
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn t_render() {
+        let context = json!({ "domain": "<b>x.com</b>", "days": 3 });
+
+        let mut template = NotificationTemplate::new("{{ domain }} expires in {{ days }} day(s)");
+        template.title = Some("{{ domain }} & co");
+        let rendered = template.render(&context).unwrap();
+        assert_eq!("<b>x.com</b> expires in 3 day(s)", rendered.message);
+        assert_eq!(Some("<b>x.com</b> & co"), rendered.title.as_deref());
+
+        template.html = Some(HTML::HTML);
+        let rendered = template.render(&context).unwrap();
+        assert_eq!(
+            "&lt;b&gt;x.com&lt;&#x2f;b&gt; expires in 3 day(s)",
+            rendered.message
+        );
+        assert_eq!(Some("<b>x.com</b> & co"), rendered.title.as_deref());
+        let notification = rendered.notification("token", "user");
+        assert_eq!(Some(HTML::HTML), notification.html);
+        assert_eq!(Some("<b>x.com</b> & co"), notification.title);
+
+        template.html = None;
+        template.monospace = Some(Monospace::Monospace);
+        let rendered = template.render(&context).unwrap();
+        assert_eq!("<b>x.com</b> expires in 3 day(s)", rendered.message);
+
+        let template = NotificationTemplate::new("{{ unclosed");
+        assert!(matches!(
+            template.render(&context),
+            Err(NotificationError::Template(_))
+        ));
+    }
+}
@@ -14,12 +14,40 @@
 //!
 //! Windows is **NOT SUPPORTED**.
 
+#[cfg(not(target_os = "windows"))]
+use std::{net::SocketAddr, process::Command, time::Duration};
+
+#[cfg(not(target_os = "windows"))]
+use clap::Parser;
 #[cfg(target_os = "windows")]
 use log::error;
 #[cfg(not(target_os = "windows"))]
-use log::info;
+use log::{debug, error, info, warn};
 #[cfg(not(target_os = "windows"))]
-use tokio::signal::unix::{signal, SignalKind};
+use tokio::{
+    io::{AsyncReadExt as _, AsyncWriteExt as _},
+    net::{TcpListener, TcpStream},
+    signal::unix::{signal, SignalKind},
+};
+
+/// Argument parser
+#[cfg(not(target_os = "windows"))]
+#[derive(Debug, Parser)]
+#[command(about, author, version)]
+struct Opts {
+    /// Serve health check endpoint /healthz on given host and port e.g. 0.0.0.0:8080
+    #[arg(short, long, env = "BIND")]
+    bind: Option<SocketAddr>,
+    /// Exit if no signal arrives within given seconds
+    #[arg(short, long, env = "TIMEOUT")]
+    timeout: Option<u64>,
+    /// Exit code when timeout is reached
+    #[arg(long, default_value = "1", env = "TIMEOUT_EXIT_CODE")]
+    timeout_exit_code: i32,
+    /// Shell command run after SIGINT or SIGTERM is received, before exit
+    #[arg(long, env = "ON_SIGNAL")]
+    on_signal: Option<String>,
+}
 
 #[cfg(target_os = "windows")]
 fn main() {
@@ -33,15 +61,127 @@ fn main() {
 async fn main() -> anyhow::Result<()> {
     pretty_env_logger::init();
 
+    let opts = Opts::parse();
+
+    if let Some(bind) = opts.bind {
+        let listener = TcpListener::bind(bind).await?;
+        info!("serve health check on {}", listener.local_addr()?);
+        tokio::spawn(serve_health(listener));
+    }
+
     info!("wait for SIGINT or SIGTERM");
 
     let mut int = signal(SignalKind::interrupt())?;
     let mut term = signal(SignalKind::terminate())?;
 
+    let timeout = async {
+        match opts.timeout {
+            Some(secs) => tokio::time::sleep(Duration::from_secs(secs)).await,
+            None => std::future::pending().await,
+        }
+    };
+
     tokio::select! {
         _ = int.recv() => info!("SIGINT received"),
         _ = term.recv() => info!("SIGTERM received"),
+        _ = timeout => {
+            warn!("no signal received in time, exit with {}", opts.timeout_exit_code);
+            std::process::exit(opts.timeout_exit_code);
+        }
+    }
+
+    if let Some(ref command) = opts.on_signal {
+        run_hook(command);
     }
 
     Ok(())
 }
+
+/// Runs hook with shell, failure is logged but does not change exit code
+#[cfg(not(target_os = "windows"))]
+fn run_hook(command: &str) {
+    info!("run {command}");
+    match Command::new("sh").arg("-c").arg(command).status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => warn!("{command} exited with {status}"),
+        Err(e) => error!("failed to run {command}: {e}"),
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+async fn serve_health(listener: TcpListener) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(async move {
+                    if let Err(e) = respond_health(stream).await {
+                        debug!("failed to respond health check: {e}");
+                    }
+                });
+            }
+            Err(e) => debug!("failed to accept connection: {e}"),
+        }
+    }
+}
+
+/// Answers 200 to GET or HEAD /healthz, 404 to anything else
+#[cfg(not(target_os = "windows"))]
+async fn respond_health(mut stream: TcpStream) -> std::io::Result<()> {
+    let mut buf = [0u8; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let mut parts = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+
+    let (status, body) = match (method, path) {
+        ("GET" | "HEAD", "/healthz") => ("200 OK", "ok"),
+        _ => ("404 Not Found", "not found"),
+    };
+    let body = if method == "HEAD" { "" } else { body };
+    let response = format!(
+        "HTTP/1.1 {status}\r\ncontent-type: text/plain\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(all(test, not(target_os = "windows")))]
+mod tests {
+    use super::*;
+
+    async fn request(addr: SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[test]
+    fn t_opts() {
+        let opts = Opts::try_parse_from(["wfs", "--timeout", "3", "--on-signal", "true"]).unwrap();
+        assert_eq!(Some(3), opts.timeout);
+        assert_eq!(1, opts.timeout_exit_code);
+        assert_eq!(Some("true".to_string()), opts.on_signal);
+    }
+
+    #[tokio::test]
+    async fn t_serve_health() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_health(listener));
+
+        let response = request(addr, "GET /healthz HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("ok"));
+
+        let response = request(addr, "GET / HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+    }
+}